@@ -1,48 +1,86 @@
+mod alias;
+mod bit_range;
+mod sealed;
 mod split;
 
 use proc_macro2::{Ident, TokenStream};
 use proc_macro_error2::{abort, abort_call_site};
 use quote::quote;
 use split::SplitAttributes;
-use syn::{punctuated::Iter, spanned::Spanned, Fields, Item, ItemEnum, ItemStruct, Type, Variant};
+use syn::{parse_quote, punctuated::Iter, spanned::Spanned, Fields, Generics, Item, ItemEnum, ItemStruct, Type, Variant};
 
-use crate::shared::{self, enum_fills_bitsize, is_fallback_attribute, unreachable, BitSize, MAX_ENUM_BIT_SIZE};
+use crate::shared::{
+    self, discriminant_assigner, enum_fills_bitsize, is_fallback_attribute, options::BitsizeOptions, reserved::ReservedValue, tagged::Tagged,
+    unreachable, BitSize, MAX_ENUM_BIT_SIZE,
+};
 
 /// Intermediate Representation, just for bundling these together
 struct ItemIr {
     /// generated item (and size check)
     expanded: TokenStream,
+    /// the sealed module, the struct's original visibility and its name, see [`sealed::seal`]
+    sealed: Option<(Ident, syn::Visibility, Ident)>,
 }
 
 pub(super) fn bitsize(args: TokenStream, item: TokenStream) -> TokenStream {
-    let (item, declared_bitsize) = parse(item, args);
+    let (item, declared_bitsize, options) = parse(item, args);
     let attrs = SplitAttributes::from_item(&item);
     let ir = match item {
         Item::Struct(mut item) => {
+            if options.tag_bits.is_some() {
+                abort_call_site!("only enums can have a tag"; help = "remove `tag_bits`")
+            }
+            analyze_generics(&item);
+            let mut aliases = alias::take_aliases(&mut item.fields);
+            let bit_range_checks = bit_range::fill_bit_ranges(&mut item.fields, declared_bitsize);
             modify_special_field_names(&mut item.fields);
-            analyze_struct(&item.fields);
-            let expanded = generate_struct(&item, declared_bitsize);
-            ItemIr { expanded }
+            analyze_struct(&item.fields, &aliases);
+            let reserved_value_checks: TokenStream = item.fields.iter().map(ReservedValue::check).collect();
+            let sealed = options.sealed.then(|| sealed::seal(&mut item, &mut aliases));
+            let mut expanded = generate_struct(&item, declared_bitsize);
+            expanded.extend(bit_range_checks);
+            expanded.extend(reserved_value_checks);
+            expanded.extend(alias::generate_aliases(&item, &aliases, declared_bitsize, options));
+            ItemIr {
+                expanded,
+                sealed: sealed.map(|(module, vis)| (module, vis, item.ident)),
+            }
         }
         Item::Enum(item) => {
-            analyze_enum(declared_bitsize, item.variants.iter());
-            let expanded = generate_enum(&item);
-            ItemIr { expanded }
+            if options.sealed {
+                abort_call_site!("enums can't be sealed"; help = "`sealed` hides the value of structs, enums don't have one")
+            }
+            if !item.generics.params.is_empty() {
+                abort!(item.generics, "enums can't be generic"; help = "only structs can have generic fields")
+            }
+            let tagged = Tagged::analyze(item.variants.iter(), declared_bitsize, options);
+            analyze_enum(declared_bitsize, item.variants.iter(), tagged.is_some());
+            let mut expanded = generate_enum(&item);
+            match tagged {
+                Some(tagged) => expanded.extend(tagged.generate_payload_size_checks(&item.ident)),
+                None => expanded.extend(discriminant_assigner::generate_discriminant_consts(
+                    declared_bitsize,
+                    &item.ident,
+                    item.variants.iter(),
+                )),
+            }
+            ItemIr { expanded, sealed: None }
         }
         _ => unreachable(()),
     };
-    generate_common(ir, attrs, declared_bitsize)
+    generate_common(ir, attrs, declared_bitsize, options)
 }
 
-fn parse(item: TokenStream, args: TokenStream) -> (Item, BitSize) {
+fn parse(item: TokenStream, args: TokenStream) -> (Item, BitSize, BitsizeOptions) {
     let item = syn::parse2(item).unwrap_or_else(unreachable);
 
     if args.is_empty() {
         abort_call_site!("missing attribute value"; help = "you need to define the size like this: `#[bitsize(32)]`")
     }
 
-    let (declared_bitsize, _arb_int) = shared::bitsize_and_arbitrary_int_from(args);
-    (item, declared_bitsize)
+    let (declared_bitsize, _arb_int) = shared::bitsize_and_arbitrary_int_from(args.clone());
+    let options = BitsizeOptions::from_args(args);
+    (item, declared_bitsize, options)
 }
 
 fn check_type_is_supported(ty: &Type) {
@@ -90,7 +128,7 @@ fn modify_special_field_names(fields: &mut Fields) {
     }
 }
 
-fn analyze_struct(fields: &Fields) {
+fn analyze_struct(fields: &Fields, aliases: &[alias::Alias]) {
     if fields.is_empty() {
         abort_call_site!("structs without fields are not supported")
     }
@@ -100,9 +138,35 @@ fn analyze_struct(fields: &Fields) {
     for field in fields {
         check_type_is_supported(&field.ty)
     }
+    for alias in aliases {
+        check_type_is_supported(&alias.field.ty)
+    }
+}
+
+/// Generic structs get their size checked once they are instantiated, see [`generate_size_check`].
+///
+/// Bit ranges and aliases are checked right away, so they can't be used on generic structs.
+fn analyze_generics(item: &ItemStruct) {
+    let generics = &item.generics;
+    if let Some(lifetime) = generics.lifetimes().next() {
+        abort!(lifetime, "bitfields can't have lifetime parameters"; help = "fields are stored as bits, so they can't borrow anything")
+    }
+    if !shared::is_generic(generics) {
+        return;
+    }
+    let unsupported = ["bits", "bit", "alias"];
+    let attr = item
+        .fields
+        .iter()
+        .flat_map(|field| &field.attrs)
+        .find(|attr| unsupported.iter().any(|name| attr.path().is_ident(name)));
+    if let Some(attr) = attr {
+        abort!(attr, "bit ranges and aliases are not supported on generic structs"; help = "their offsets are checked before the struct's parameters are known")
+    }
 }
 
-fn analyze_enum(bitsize: BitSize, variants: Iter<Variant>) {
+/// Tagged enums have their variants checked by `Tagged::analyze`.
+fn analyze_enum(bitsize: BitSize, variants: Iter<Variant>, is_tagged: bool) {
     if bitsize > MAX_ENUM_BIT_SIZE {
         abort_call_site!("enum bitsize is limited to {}", MAX_ENUM_BIT_SIZE)
     }
@@ -114,46 +178,79 @@ fn analyze_enum(bitsize: BitSize, variants: Iter<Variant>) {
 
     let has_fallback = variants.flat_map(|variant| &variant.attrs).any(is_fallback_attribute);
 
-    if !has_fallback {
+    if !has_fallback && !is_tagged {
         // this has a side-effect of validating the enum count
         let _ = enum_fills_bitsize(bitsize, variant_count);
     }
 }
 
-fn generate_struct(item: &ItemStruct, declared_bitsize: u8) -> TokenStream {
+fn generate_struct(item: &ItemStruct, declared_bitsize: BitSize) -> TokenStream {
     let ItemStruct { vis, ident, fields, .. } = item;
-    let declared_bitsize = declared_bitsize as usize;
 
-    let computed_bitsize = fields.iter().fold(quote!(0), |acc, next| {
-        let field_size = shared::generate_type_bitsize(&next.ty);
-        quote!(#acc + #field_size)
-    });
+    // every type parameter is a field type, or part of one
+    let mut generics = item.generics.clone();
+    let type_params: Vec<_> = generics.type_params().map(|param| param.ident.clone()).collect();
+    for param in type_params {
+        generics.make_where_clause().predicates.push(parse_quote!(#param: ::bilge::BitsizedField));
+    }
+    let where_clause = &generics.where_clause;
 
     // we could remove this if the whole struct gets passed
     let is_tuple_struct = fields.iter().any(|field| field.ident.is_none());
     let fields_def = if is_tuple_struct {
         let fields = fields.iter();
         quote! {
-            ( #(#fields,)* );
+            ( #(#fields,)* ) #where_clause;
         }
     } else {
         let fields = fields.iter();
         quote! {
-            { #(#fields,)* }
+            #where_clause { #(#fields,)* }
         }
     };
 
+    let size_check = generate_size_check(ident, &generics, fields, declared_bitsize);
+
     quote! {
-        #vis struct #ident #fields_def
+        #vis struct #ident #generics #fields_def
+
+        #size_check
+    }
+}
 
-        // constness: when we get const blocks evaluated at compile time, add a const computed_bitsize
-        const _: () = assert!(
+/// Generic structs can't be checked before their parameters are known, so they get an associated const instead,
+/// which is evaluated whenever one of them is created, see `shared::generate_struct_init`.
+fn generate_size_check(ident: &Ident, generics: &Generics, fields: &Fields, declared_bitsize: BitSize) -> TokenStream {
+    let declared_bitsize = declared_bitsize as usize;
+
+    let computed_bitsize = fields.iter().fold(quote!(0), |acc, next| {
+        let field_size = shared::generate_type_bitsize(&next.ty);
+        quote!(#acc + #field_size)
+    });
+
+    let check = quote! {
+        assert!(
             (#computed_bitsize) == (#declared_bitsize),
             concat!("struct size and declared bit size differ: ",
             // stringify!(#computed_bitsize),
             " != ",
             stringify!(#declared_bitsize))
-        );
+        )
+    };
+
+    if shared::is_generic(generics) {
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+        quote! {
+            impl #impl_generics #ident #ty_generics #where_clause {
+                #[doc(hidden)]
+                const __BILGE_BITSIZE_CHECK: () = #check;
+            }
+        }
+    } else {
+        quote! {
+            // constness: when we get const blocks evaluated at compile time, add a const computed_bitsize
+            const _: () = #check;
+        }
     }
 }
 
@@ -169,19 +266,23 @@ fn generate_enum(item: &ItemEnum) -> TokenStream {
 
 /// we have _one_ generate_common function, which holds everything that struct and enum have _in common_.
 /// Everything else has its own generate_ functions.
-fn generate_common(ir: ItemIr, attrs: SplitAttributes, declared_bitsize: u8) -> TokenStream {
-    let ItemIr { expanded } = ir;
+fn generate_common(ir: ItemIr, attrs: SplitAttributes, declared_bitsize: BitSize, options: BitsizeOptions) -> TokenStream {
+    let ItemIr { expanded, sealed } = ir;
     let SplitAttributes {
         before_compression,
         after_compression,
     } = attrs;
 
-    let bitsize_internal_attr = quote! {#[::bilge::bitsize_internal(#declared_bitsize)]};
+    let bitsize_internal_attr = quote! {#[::bilge::bitsize_internal(#declared_bitsize #options)]};
 
-    quote! {
+    let expanded = quote! {
         #(#before_compression)*
         #bitsize_internal_attr
         #(#after_compression)*
         #expanded
+    };
+    match sealed {
+        Some((module, vis, ident)) => sealed::generate_module(&module, &vis, &ident, expanded),
+        None => expanded,
     }
 }
@@ -0,0 +1,132 @@
+use proc_macro2::TokenStream;
+use proc_macro_error2::abort;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Field, Fields, Ident, ItemStruct, LitStr, Meta};
+
+use crate::shared::{self, options::BitsizeOptions, unreachable, BitSize, Repr};
+
+/// An alternative view over the bits of another field, declared like this:
+/// ```ignore
+/// #[bitsize(16)]
+/// struct Register {
+///     raw: u16,
+///     #[alias(of = "raw")]
+///     split: (u8, u8),
+/// }
+/// ```
+/// `split` is removed from the struct, so it doesn't count towards its size,
+/// but still gets a getter and setter reading and writing the bits starting at `raw`.
+/// In `msb_first` bitfields, the alias starts at the most significant bit of `raw` instead.
+pub(super) struct Alias {
+    pub field: Field,
+    of: LitStr,
+}
+
+/// Removes all aliases from the struct, since they only describe other fields' bits.
+pub(super) fn take_aliases(fields: &mut Fields) -> Vec<Alias> {
+    let has_aliases = fields.iter().any(|field| field.attrs.iter().any(|attr| attr.path().is_ident("alias")));
+    if !has_aliases {
+        return vec![];
+    }
+
+    let Fields::Named(named) = fields else {
+        abort!(fields, "aliases are only supported on structs with named fields")
+    };
+
+    let mut aliases = vec![];
+    let mut remaining = syn::punctuated::Punctuated::new();
+    for mut field in std::mem::take(&mut named.named) {
+        match field.attrs.iter().position(|attr| attr.path().is_ident("alias")) {
+            Some(index) => {
+                let attr = field.attrs.remove(index);
+                let of = parse_alias_of(&attr.meta);
+                aliases.push(Alias { field, of });
+            }
+            None => remaining.push(field),
+        }
+    }
+    named.named = remaining;
+    aliases
+}
+
+fn parse_alias_of(meta: &Meta) -> LitStr {
+    let help = "use `#[alias(of = \"field\")]`";
+    let Meta::List(list) = meta else {
+        abort!(meta, "alias is missing the field it views"; help = help)
+    };
+
+    let mut of = None;
+    list.parse_nested_meta(|meta| {
+        if meta.path.is_ident("of") {
+            of = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unknown alias argument"))
+        }
+    })
+    .unwrap_or_else(|e| abort!(list.tokens, "failed to parse alias: {}", e; help = help));
+
+    of.unwrap_or_else(|| abort!(list, "alias is missing the field it views"; help = help))
+}
+
+/// Generates the accessors of every alias, as well as checks validating that it fits into the struct.
+///
+/// Since the aliased bits are only validated as the original field, an alias needs to be `FromBits`.
+pub(super) fn generate_aliases(item: &ItemStruct, aliases: &[Alias], declared_bitsize: BitSize, options: BitsizeOptions) -> TokenStream {
+    if aliases.is_empty() {
+        return quote!();
+    }
+
+    let ItemStruct { ident, fields, generics, .. } = item;
+    let repr = Repr::new(declared_bitsize, generics, fields, options);
+    let msb_first = options.msb_first;
+    let declared_bitsize = declared_bitsize as usize;
+
+    let (accessors, checks): (Vec<_>, Vec<_>) = aliases
+        .iter()
+        .map(|Alias { field, of }| {
+            let name = field.ident.as_ref().unwrap_or_else(|| unreachable(()));
+            let of_ident: Ident = of.parse().unwrap_or_else(|_| abort!(of, "`{}` is not a field name", of.value()));
+
+            // the alias starts where the viewed field starts
+            let Some(index) = fields.iter().position(|field| field.ident.as_ref() == Some(&of_ident)) else {
+                abort!(of, "there is no field `{}` to view", of_ident; help = "aliases can only view fields of the same struct, which are not aliases themselves")
+            };
+            let of_offset = &shared::generate_field_offsets(fields, msb_first)[index];
+            let size = shared::generate_type_bitsize(&field.ty);
+            let (offset, fits) = if msb_first {
+                // the alias ends where the viewed field ends, so it spans the fields declared after it
+                let of_size = shared::generate_type_bitsize(&fields.iter().nth(index).unwrap_or_else(|| unreachable(())).ty);
+                (quote!((#of_offset) + (#of_size) - (#size)), quote!((#size) <= (#of_offset) + (#of_size)))
+            } else {
+                (quote!(#of_offset), quote!((#of_offset) + (#size) <= #declared_bitsize))
+            };
+
+            // the accessors only convert the alias, not the fields it views
+            let repr = Repr {
+                const_fields: shared::has_const_types(std::iter::once(&field.ty), options.const_fields),
+                ..repr
+            };
+            let getter = crate::bitsize_internal::generate_getter(field, &offset, name, repr);
+            let setter = crate::bitsize_internal::generate_setter(field, &offset, name, repr);
+
+            let message = format!("alias `{name}` of `{of_ident}` does not fit into the struct");
+            let mut assumes = vec![];
+            crate::from_bits::generate_filled_check_for(&field.ty, &mut assumes);
+            // point at the alias type, if this fails
+            let check = quote_spanned! {field.ty.span()=>
+                const _: () = assert!(#fits, #message);
+                const _: () = { #( #assumes )* };
+            };
+
+            (quote!(#getter #setter), check)
+        })
+        .unzip();
+
+    quote! {
+        impl #ident {
+            #( #accessors )*
+        }
+        #( #checks )*
+    }
+}
@@ -0,0 +1,185 @@
+use proc_macro2::{Ident, TokenStream};
+use proc_macro_error2::abort;
+use quote::{quote, quote_spanned};
+use syn::{parse::Parser, spanned::Spanned, Attribute, Expr, ExprLit, ExprRange, Field, Fields, Lit, Meta, RangeLimits};
+
+use crate::shared::{self, unreachable, BitSize, MAX_INT_BIT_SIZE};
+
+/// A field's bits as given by `#[bits(lo..=hi)]`, `#[bits(lo..hi)]` or `#[bit(n)]`.
+struct BitRange {
+    /// the lowest bit of this field
+    lo: BitSize,
+    /// the highest bit of this field, inclusive
+    hi: BitSize,
+}
+
+impl BitRange {
+    fn width(&self) -> BitSize {
+        self.hi - self.lo + 1
+    }
+}
+
+/// Datasheets usually list the bit range of every field, so we allow copying these, like:
+/// ```ignore
+/// #[bitsize(16)]
+/// struct Register {
+///     #[bits(0..=3)]
+///     header: u4,
+///     #[bit(7)]
+///     enabled: bool,
+///     #[bits(8..16)]
+///     body: u8,
+/// }
+/// ```
+/// Every gap between the given ranges gets filled with a `reserved` field, which means the struct above
+/// turns into the usual `header: u4, reserved: u3, enabled: bool, body: u8`.
+///
+/// Returns the checks validating that every range matches its field type's `Bitsized::BITS`.
+pub(super) fn fill_bit_ranges(fields: &mut Fields, declared_bitsize: BitSize) -> TokenStream {
+    let ranges: Vec<_> = fields.iter_mut().map(take_bit_range).collect();
+    if ranges.iter().all(Option::is_none) {
+        return quote!();
+    }
+
+    let Fields::Named(named) = fields else {
+        abort!(fields, "bit ranges are only supported on structs with named fields")
+    };
+
+    let mut filled = syn::punctuated::Punctuated::new();
+    let mut checks = vec![];
+    let mut next_free_bit = 0;
+    let mut previous_lo = 0;
+    for (field, range) in named.named.iter().zip(ranges) {
+        let Some(range) = range else {
+            abort!(field, "field is missing a bit range"; help = "either all fields or none need `#[bits(..)]` or `#[bit(..)]`")
+        };
+
+        if range.hi >= declared_bitsize {
+            abort!(
+                field,
+                "bit range ends at bit {}, which is outside of the declared bitsize {}",
+                range.hi,
+                declared_bitsize
+            )
+        }
+        if range.lo < previous_lo {
+            abort!(
+                field,
+                "bit range {}..={} comes before the previous field", range.lo, range.hi;
+                help = "declare fields in ascending bit order, like the fields without bit ranges"
+            )
+        }
+        if range.lo < next_free_bit {
+            abort!(
+                field,
+                "bit range {}..={} overlaps with a previous field", range.lo, range.hi;
+                help = "fields need to be declared from the lowest bit up, without overlapping"
+            )
+        }
+
+        filled.extend(reserved_fields(range.lo - next_free_bit));
+        next_free_bit = range.hi + 1;
+        previous_lo = range.lo;
+
+        let field_size = shared::generate_type_bitsize(&field.ty);
+        let width = range.width() as usize;
+        let message = format!(
+            "field `{}` is declared with {} bits, but its type has a different size",
+            field.ident.as_ref().unwrap_or_else(|| unreachable(())),
+            width
+        );
+        // point at the field type, if this fails
+        checks.push(quote_spanned! {field.ty.span()=>
+            const _: () = assert!((#field_size) == #width, #message);
+        });
+        filled.push(field.clone());
+    }
+    filled.extend(reserved_fields(declared_bitsize - next_free_bit));
+
+    named.named = filled;
+    quote!(#( #checks )*)
+}
+
+/// Removes this field's bit range attribute, so it doesn't end up in the generated struct.
+fn take_bit_range(field: &mut Field) -> Option<BitRange> {
+    let mut range = None;
+    let mut error = None;
+    field.attrs.retain(|attr| {
+        let parsed = parse_bit_range(attr);
+        if parsed.is_some() {
+            if range.is_some() {
+                error = Some(attr.span());
+            }
+            range = parsed;
+            false
+        } else {
+            true
+        }
+    });
+    if let Some(span) = error {
+        abort!(span, "only one bit range per field is allowed")
+    }
+    range
+}
+
+fn parse_bit_range(attr: &Attribute) -> Option<BitRange> {
+    let Meta::List(list) = &attr.meta else {
+        return None;
+    };
+    let help = "use `#[bits(0..=3)]`, `#[bits(0..4)]` or `#[bit(0)]`";
+    let is_bits = list.path.is_ident("bits");
+    if !is_bits && !list.path.is_ident("bit") {
+        return None;
+    }
+
+    let expr: Expr = syn::parse2(list.tokens.clone()).unwrap_or_else(|_| abort!(list.tokens, "bit range is invalid"; help = help));
+    let range = match (&expr, is_bits) {
+        (
+            Expr::Range(ExprRange {
+                start: Some(start),
+                limits,
+                end: Some(end),
+                ..
+            }),
+            true,
+        ) => {
+            let lo = parse_bit(start);
+            let hi = match limits {
+                RangeLimits::Closed(_) => parse_bit(end),
+                RangeLimits::HalfOpen(_) => parse_bit(end).checked_sub(1).unwrap_or_else(|| abort!(expr, "bit range is empty")),
+            };
+            BitRange { lo, hi }
+        }
+        (Expr::Lit(_), false) => {
+            let bit = parse_bit(&expr);
+            BitRange { lo: bit, hi: bit }
+        }
+        _ => abort!(expr, "bit range is invalid"; help = help),
+    };
+
+    if range.hi < range.lo {
+        abort!(expr, "bit range is empty")
+    }
+    Some(range)
+}
+
+fn parse_bit(expr: &Expr) -> BitSize {
+    let Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) = expr else {
+        abort!(expr, "bit is not a number"; help = "only literal integers are currently supported")
+    };
+    int.base10_parse().unwrap_or_else(|_| abort!(expr, "bit is not a valid number"))
+}
+
+/// Fills a gap with `reserved` fields, which get numbered later on.
+/// A single arbitrary_int can't fill a gap of a wide bitfield, so we might need more than one.
+fn reserved_fields(mut gap: BitSize) -> Vec<Field> {
+    let mut fields = vec![];
+    while gap > 0 {
+        let size = gap.min(MAX_INT_BIT_SIZE);
+        gap -= size;
+        let ty: Ident = syn::parse_str(&format!("u{size}")).unwrap_or_else(unreachable);
+        let field = Field::parse_named.parse2(quote!(reserved: #ty)).unwrap_or_else(unreachable);
+        fields.push(field);
+    }
+    fields
+}
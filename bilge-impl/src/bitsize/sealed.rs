@@ -0,0 +1,81 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{parse_quote, ItemStruct, Visibility};
+
+/// Rust only has module privacy, so to make `value` unreachable, a sealed bitfield gets its own module:
+/// ```ignore
+/// #[bitsize(8, sealed)]
+/// #[derive(FromBits)]
+/// pub struct Register {
+///     field: u8,
+/// }
+/// ```
+/// turns into something like this:
+/// ```ignore
+/// mod __bilge_sealed_Register {
+///     use super::*;
+///     #[::bilge::bitsize_internal(8, sealed)]
+///     #[derive(FromBits)]
+///     pub struct Register {
+///         pub(super) field: u8,
+///     }
+/// }
+/// pub use __bilge_sealed_Register::Register;
+/// pub use __bilge_sealed_Register::RegisterBuilder;
+/// ```
+/// Derives get expanded inside of this module as well, which means they can still use `value`.
+/// Everything else only gets the accessors, `to_raw()` and `unsafe from_raw_unchecked()`.
+///
+/// Returns the module's name and the struct's original visibility, which the re-export needs.
+pub(super) fn seal(item: &mut ItemStruct, aliases: &mut [super::alias::Alias]) -> (Ident, Visibility) {
+    let module = Ident::new(&format!("__bilge_sealed_{}", item.ident), item.ident.span());
+    let vis = item.vis.clone();
+
+    // everything is one module deeper now, so visibilities need to reach one module further
+    item.vis = widen(&item.vis);
+    for field in item.fields.iter_mut().chain(aliases.iter_mut().map(|alias| &mut alias.field)) {
+        field.vis = widen(&field.vis);
+    }
+    (module, vis)
+}
+
+/// Puts the struct and everything generated for it into the sealed module.
+pub(super) fn generate_module(module: &Ident, vis: &Visibility, ident: &Ident, expanded: TokenStream) -> TokenStream {
+    let builder = crate::bitsize_internal::builder::builder_name(ident);
+    quote! {
+        #[doc(hidden)]
+        #[allow(non_snake_case)]
+        mod #module {
+            use super::*;
+            #expanded
+        }
+        #vis use #module::#ident;
+        // not every sealed bitfield gets built through its builder
+        #[allow(unused_imports)]
+        #vis use #module::#builder;
+    }
+}
+
+/// `x` -> `pub(super) x`, `pub(super) x` -> `pub(in super::super) x` and so on.
+fn widen(vis: &Visibility) -> Visibility {
+    match vis {
+        Visibility::Public(_) => vis.clone(),
+        Visibility::Inherited => parse_quote!(pub(super)),
+        Visibility::Restricted(restricted) => {
+            let path = &restricted.path;
+            if path.is_ident("crate") {
+                vis.clone()
+            } else if path.is_ident("self") {
+                parse_quote!(pub(super))
+            } else if path.segments.first().is_some_and(|first| first.ident == "crate") {
+                parse_quote!(pub(in #path))
+            } else if path.segments.first().is_some_and(|first| first.ident == "self") {
+                let rest = path.segments.iter().skip(1);
+                parse_quote!(pub(in super #(::#rest)*))
+            } else {
+                // `super` or `super::super`
+                parse_quote!(pub(in super::#path))
+            }
+        }
+    }
+}
@@ -1,148 +1,457 @@
 use proc_macro2::{Ident, TokenStream};
-use quote::quote;
-use syn::{Attribute, Field, Item, ItemEnum, ItemStruct, Type};
-
-use crate::shared::{self, unreachable};
-
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Attribute, Field, Fields, Generics, Item, ItemEnum, ItemStruct, Type};
+
+use crate::shared::{
+    self,
+    access::{self, Access},
+    discriminant_assigner,
+    options::BitsizeOptions,
+    reserved::{self, ReservedValue},
+    tagged::Tagged,
+    unreachable, util, BitSize, Repr,
+};
+
+pub(crate) mod builder;
 pub(crate) mod struct_gen;
+mod validity;
 
 /// Intermediate Representation, just for bundling these together
 struct ItemIr<'a> {
     attrs: &'a Vec<Attribute>,
     name: &'a Ident,
+    generics: &'a Generics,
     /// generated item (and setters, getters, constructor, impl Bitsized)
     expanded: TokenStream,
+    /// `Bitsized::VALIDITY`, if it isn't the default
+    validity: TokenStream,
+    /// `Bitsized::__BILGE_W1C_MASK`, if it isn't the default
+    w1c_mask: TokenStream,
 }
 
 pub(super) fn bitsize_internal(args: TokenStream, item: TokenStream) -> TokenStream {
-    let (item, arb_int) = parse(item, args);
+    let (item, bitsize, arb_int, options) = parse(item, args);
     let ir = match item {
         Item::Struct(ref item) => {
-            let expanded = generate_struct(item, &arb_int);
+            let expanded = generate_struct(item, &arb_int, bitsize, options);
+            let field_offsets = shared::generate_field_offsets(&item.fields, options.msb_first);
+            let validity = validity::generate_struct_validity(&item.fields, &field_offsets, bitsize, &item.generics);
+            let w1c_mask = generate_w1c_mask(&item.fields, &field_offsets, bitsize);
             let attrs = &item.attrs;
             let name = &item.ident;
-            ItemIr { attrs, name, expanded }
+            let generics = &item.generics;
+            ItemIr {
+                attrs,
+                name,
+                generics,
+                expanded,
+                validity,
+                w1c_mask,
+            }
         }
         Item::Enum(ref item) => {
-            let expanded = generate_enum(item);
+            let tagged = Tagged::analyze(item.variants.iter(), bitsize, options);
+            let expanded = generate_enum(item, bitsize, options, tagged.as_ref());
+            let validity = validity::generate_enum_validity(item, bitsize, tagged.as_ref());
             let attrs = &item.attrs;
             let name = &item.ident;
-            ItemIr { attrs, name, expanded }
+            let generics = &item.generics;
+            ItemIr {
+                attrs,
+                name,
+                generics,
+                expanded,
+                validity,
+                w1c_mask: quote!(),
+            }
         }
         _ => unreachable(()),
     };
-    generate_common(ir, &arb_int)
+    generate_common(ir, &arb_int, bitsize)
 }
 
-fn parse(item: TokenStream, args: TokenStream) -> (Item, TokenStream) {
+fn parse(item: TokenStream, args: TokenStream) -> (Item, BitSize, TokenStream, BitsizeOptions) {
     let item = syn::parse2(item).unwrap_or_else(unreachable);
-    let (_declared_bitsize, arb_int) = shared::bitsize_and_arbitrary_int_from(args);
-    (item, arb_int)
+    let (declared_bitsize, arb_int) = shared::bitsize_and_arbitrary_int_from(args.clone());
+    let options = BitsizeOptions::from_args(args);
+    (item, declared_bitsize, arb_int, options)
 }
 
-fn generate_struct(struct_data: &ItemStruct, arb_int: &TokenStream) -> TokenStream {
-    let ItemStruct { vis, ident, fields, .. } = struct_data;
-
-    let mut previous_field_sizes = vec![];
+fn generate_struct(struct_data: &ItemStruct, arb_int: &TokenStream, bitsize: BitSize, options: BitsizeOptions) -> TokenStream {
+    let ItemStruct {
+        vis,
+        ident,
+        fields,
+        generics,
+        ..
+    } = struct_data;
+    let repr = Repr::new(bitsize, generics, fields, options);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let field_offsets = shared::generate_field_offsets(fields, options.msb_first);
     type TokenVec = Vec<TokenStream>;
-    let (accessors, (constructor_args, (constructor_parts, shifted_names))): (TokenVec, (TokenVec, (TokenVec, Vec<Ident>))) = fields
+    let (accessors, (constructor_args, (mut constructor_parts, shifted_names))): (TokenVec, (TokenVec, (TokenVec, Vec<Ident>))) = fields
         .iter()
+        .zip(&field_offsets)
         .enumerate()
-        .map(|(i, field)| {
-            // offset is needed for bit-shifting
-            // struct Example { field1: u8, field2: u4, field3: u4 }
-            // previous_field_sizes = []     -> unwrap_or_else -> field_offset = 0
-            // previous_field_sizes = [8]    -> reduce         -> field_offset = 0 + 8     =  8
-            // previous_field_sizes = [8, 4] -> reduce         -> field_offset = 0 + 8 + 4 = 12
-            let field_offset = previous_field_sizes
-                .iter()
-                .cloned()
-                .reduce(|acc, next| quote!(#acc + #next))
-                .unwrap_or_else(|| quote!(0));
-            let field_size = shared::generate_type_bitsize(&field.ty);
-            previous_field_sizes.push(field_size);
-            generate_field(field, &field_offset, i)
-        })
+        .map(|(i, (field, field_offset))| generate_field(field, field_offset, i, repr))
         .unzip();
+    // `new` takes the fields in declaration order, but fills them in starting at bit 0
+    if options.msb_first {
+        constructor_parts.reverse();
+    }
+
+    // `FIELD_OFFSET` and so on, sharing their offsets with the accessors
+    let field_consts = fields
+        .iter()
+        .zip(&field_offsets)
+        .enumerate()
+        .map(|(i, (field, field_offset))| generate_field_consts(field, field_offset, i, bitsize));
+
+    let layout_impl = generate_layout_impl(struct_data, &field_offsets);
+    let reserved_const = generate_reserved_const(fields, &field_offsets, arb_int, bitsize);
+
+    let const_ = repr.const_keyword();
+
+    let init = shared::generate_struct_init(generics);
+    let new_value = shared::generate_const_new(bitsize, quote!(raw_value));
+    // wide bitfields write each field into a byte array, instead of bit-or'ing shifted values together
+    let constructor_body = if repr.wide {
+        quote! {
+            let mut value = Self::__BILGE_RESERVED;
+            let bytes = &mut value;
+            let mut offset = 0;
+            #( #constructor_parts )*
+            #init
+        }
+    } else {
+        quote! {
+            let mut offset = 0;
+            #( #constructor_parts )*
+            let raw_value = #( #shifted_names )|* | Self::__BILGE_RESERVED;
+            let value = #new_value;
+            #init
+        }
+    };
+    let aliases = if repr.is_const() {
+        quote!()
+    } else {
+        quote! {
+            type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
+            type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
+        }
+    };
 
-    let const_ = if cfg!(feature = "nightly") { quote!(const) } else { quote!() };
+    // unlike `From`, these are const. For sealed bitfields, they are the only way to reach `value`
+    let raw_accessors = quote! {
+        /// Returns the raw value of this bitfield.
+        #[allow(clippy::wrong_self_convention)]
+        pub const fn to_raw(&self) -> #arb_int {
+            self.value
+        }
+
+        /// Creates this bitfield from its raw value, without validating it.
+        ///
+        /// # Safety
+        ///
+        /// `value` needs to be valid for this bitfield, e.g. it can't contain
+        /// an enum value without a variant. Otherwise, getters will panic.
+        pub const unsafe fn from_raw_unchecked(value: #arb_int) -> Self {
+            #init
+        }
+    };
+    let bits_conversions = generate_struct_bits_conversions(generics, bitsize);
+
+    let builder = builder::generate_builder(struct_data, repr);
+
+    // type parameters only show up in the accessors, so the struct needs to hold them somewhere
+    let type_params: Vec<_> = generics.type_params().map(|param| &param.ident).collect();
+    let phantom = if type_params.is_empty() {
+        quote!()
+    } else {
+        quote!(_phantom: ::core::marker::PhantomData<fn() -> (#( #type_params, )*)>,)
+    };
 
     quote! {
-        #vis struct #ident {
+        #vis struct #ident #generics #where_clause {
             /// WARNING: modifying this value directly can break invariants
             value: #arb_int,
+            #phantom
         }
-        impl #ident {
+        impl #impl_generics #ident #ty_generics #where_clause {
             // #[inline]
             #[allow(clippy::too_many_arguments, clippy::type_complexity, missing_docs, unused_parens)]
             pub #const_ fn new(#( #constructor_args )*) -> Self {
-                type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
-                type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
-
-                let mut offset = 0;
-                #( #constructor_parts )*
-                let raw_value = #( #shifted_names )|*;
-                let value = #arb_int::new(raw_value);
-                Self { value }
+                #aliases
+                #constructor_body
             }
+            #( #field_consts )*
+            #reserved_const
+            #raw_accessors
+            #bits_conversions
             #( #accessors )*
         }
+        #builder
+        #layout_impl
+    }
+}
+
+/// `__bilge_from_bits` and `__bilge_into_bits`, which let other bitfields convert this one in their const accessors,
+/// see [`shared::generate_const_from_bits`]. Wide bitfields don't fit into an `u128`, so they can't be fields.
+fn generate_struct_bits_conversions(generics: &Generics, bitsize: BitSize) -> TokenStream {
+    if shared::is_wide(bitsize) {
+        return quote!();
+    }
+    let (base_int, _) = shared::base_int_of(bitsize);
+    let new_value = shared::generate_const_new(bitsize, quote!(bits as #base_int));
+    let value = shared::generate_const_value(bitsize, quote!(self.value));
+    let init = shared::generate_struct_init(generics);
+    quote! {
+        #[doc(hidden)]
+        #[allow(clippy::unnecessary_cast)]
+        pub const fn __bilge_from_bits(bits: u128) -> Self {
+            let value = #new_value;
+            #init
+        }
+
+        #[doc(hidden)]
+        #[allow(clippy::unnecessary_cast)]
+        pub const fn __bilge_into_bits(&self) -> u128 {
+            #value as u128
+        }
+    }
+}
+
+/// `__BILGE_RESERVED`, the raw value holding every `#[reserved(..)]` value and zeros everywhere else.
+///
+/// `new`, the builder and `DefaultBits` start from this, so `reserved` fields get their required value instead of zero.
+fn generate_reserved_const(fields: &Fields, field_offsets: &[TokenStream], arb_int: &TokenStream, bitsize: BitSize) -> TokenStream {
+    let required = fields
+        .iter()
+        .zip(field_offsets)
+        .filter_map(|(field, offset)| Some((ReservedValue::of(field)?, &field.ty, offset)))
+        .filter(|(reserved_value, ..)| !matches!(reserved_value, ReservedValue::Zero));
+
+    if shared::is_wide(bitsize) {
+        let writes = required.map(|(reserved_value, ty, offset)| {
+            let value = reserved_value.to_u128(ty);
+            quote!(::bilge::write_bits(&mut bytes, #offset, <#ty as Bitsized>::BITS, #value);)
+        });
+        let byte_count = bitsize as usize / 8;
+        return quote! {
+            const __BILGE_RESERVED: #arb_int = {
+                let mut bytes = [0u8; #byte_count];
+                #( #writes )*
+                bytes
+            };
+        };
+    }
+
+    let (base_int, _) = shared::base_int_of(bitsize);
+    let shifted = required.map(|(reserved_value, ty, offset)| {
+        let value = reserved_value.to_u128(ty);
+        quote!((#value as #base_int) << (#offset))
+    });
+    quote! {
+        const __BILGE_RESERVED: #base_int = 0 #( | #shifted )*;
+    }
+}
+
+/// `__BILGE_W1C_MASK`, the bits of every `#[access(w1c)]` field, including those of nested bitfields.
+///
+/// `mmio::Reg::modify` reads these as zero, so a read-modify-write doesn't acknowledge every pending flag.
+fn generate_w1c_mask(fields: &Fields, field_offsets: &[TokenStream], bitsize: BitSize) -> TokenStream {
+    if shared::is_wide(bitsize) {
+        return quote!();
+    }
+    let masks = fields.iter().zip(field_offsets).filter_map(|(field, offset)| {
+        let ty = &field.ty;
+        if Access::of(field) == Access::WriteOneToClear {
+            let size = shared::generate_type_bitsize(ty);
+            Some(quote!((u128::MAX >> (128 - (#size))) << (#offset)))
+        } else if let Type::Path(_) = ty {
+            Some(quote!(<#ty as Bitsized>::__BILGE_W1C_MASK << (#offset)))
+        } else {
+            None
+        }
+    });
+    quote! {
+        const __BILGE_W1C_MASK: u128 = 0 #( | #masks )*;
     }
 }
 
-fn generate_field(field: &Field, field_offset: &TokenStream, i: usize) -> (TokenStream, (TokenStream, (TokenStream, Ident))) {
-    let Field { ident, ty, .. } = field;
-    let name = if let Some(ident) = ident {
+/// The name of a field's accessors, which is `val_0` and so on for tuple structs.
+pub(crate) fn field_name(field: &Field, i: usize) -> Ident {
+    if let Some(ident) = &field.ident {
         ident.clone()
     } else {
         let name = format!("val_{i}");
         syn::parse_str(&name).unwrap_or_else(unreachable)
+    }
+}
+
+/// Reserved fields were renamed by `bitsize`, see `modify_special_field_names` in there.
+pub(crate) fn is_reserved(name: &Ident) -> bool {
+    let name = name.to_string();
+    name.contains("reserved_") || name.contains("padding_")
+}
+
+/// `impl bilge::Layout`, describing every field with the same offsets and sizes as the accessors.
+fn generate_layout_impl(item: &ItemStruct, field_offsets: &[TokenStream]) -> TokenStream {
+    let ItemStruct { ident, generics, fields, .. } = item;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let field_infos = fields.iter().zip(field_offsets).enumerate().map(|(i, (field, offset))| {
+        let name = field_name(field, i).unraw().to_string();
+        let ty = &field.ty;
+        let width = shared::generate_type_bitsize(ty);
+        let type_name = util::type_name(ty);
+        let access = Access::of(field).to_runtime();
+        let reserved = is_reserved(&field_name(field, i));
+        quote!(::bilge::FieldInfo::new(#name, #offset, #width, #type_name, #access, #reserved))
+    });
+
+    quote! {
+        impl #impl_generics ::bilge::Layout for #ident #ty_generics #where_clause {
+            const FIELDS: &'static [::bilge::FieldInfo] = &[#( #field_infos ),*];
+        }
+    }
+}
+
+/// `FIELD_OFFSET` and `FIELD_BITS`, `FIELD_MASK` in the base integer and `FIELD_STRIDE`, the size of an array's elements.
+///
+/// Wide bitfields have no base integer, so their fields don't get a mask. Reserved fields don't get any of these.
+fn generate_field_consts(field: &Field, offset: &TokenStream, i: usize, bitsize: BitSize) -> TokenStream {
+    let name = field_name(field, i);
+    if is_reserved(&name) {
+        return quote!();
+    }
+    let vis = &field.vis;
+    let prefix = name.unraw().to_string().to_uppercase();
+    let offset_name = format_ident!("{prefix}_OFFSET");
+    let bits_name = format_ident!("{prefix}_BITS");
+    let size = shared::generate_type_bitsize(&field.ty);
+
+    let offset_doc = format!(" The offset of `{name}` in bits, counting from the least significant bit.");
+    let bits_doc = format!(" The size of `{name}` in bits.");
+    let mut consts = quote! {
+        #[doc = #offset_doc]
+        #vis const #offset_name: usize = #offset;
+        #[doc = #bits_doc]
+        #vis const #bits_name: usize = #size;
     };
 
+    if !shared::is_wide(bitsize) {
+        let mask_name = format_ident!("{prefix}_MASK");
+        let (base_int, base_int_size) = shared::base_int_of(bitsize);
+        let base_int_size = base_int_size as usize;
+        let mask_doc = format!(" The bits of `{name}` within the value of this bitfield.");
+        consts.extend(quote! {
+            #[doc = #mask_doc]
+            #vis const #mask_name: #base_int = if Self::#bits_name == 0 {
+                0
+            } else {
+                (#base_int::MAX >> (#base_int_size - Self::#bits_name)) << Self::#offset_name
+            };
+        });
+    }
+
+    if let Type::Array(array) = &field.ty {
+        let stride_name = format_ident!("{prefix}_STRIDE");
+        let elem_size = shared::generate_type_bitsize(&array.elem);
+        let stride_doc = format!(" The distance between the elements of `{name}` in bits, which is the size of one element.");
+        consts.extend(quote! {
+            #[doc = #stride_doc]
+            #vis const #stride_name: usize = #elem_size;
+        });
+    }
+
+    consts
+}
+
+fn generate_field(field: &Field, field_offset: &TokenStream, i: usize, repr: Repr) -> (TokenStream, (TokenStream, (TokenStream, Ident))) {
+    let ty = &field.ty;
+    let name = field_name(field, i);
+
     // skip reserved fields in constructors and setters
-    let name_str = name.to_string();
-    if name_str.contains("reserved_") || name_str.contains("padding_") {
+    if is_reserved(&name) {
+        // their required value is only used by `new` and `TryFromBits`
+        let mut field = field.clone();
+        field
+            .attrs
+            .retain(|attr| !reserved::is_reserved_attribute(attr) && !shared::is_default_attribute(attr) && !shared::is_serde_attribute(attr));
         // needed for `DebugBits`
-        let getter = generate_getter(field, field_offset, &name);
+        let getter = generate_getter(&field, field_offset, &name, repr);
         let size = shared::generate_type_bitsize(ty);
         let accessors = quote!(#getter);
         let constructor_arg = quote!();
         let shifted_name = format!("shifted_{name}");
         let shifted_name: Ident = syn::parse_str(&shifted_name).unwrap_or_else(unreachable);
-        let constructor_part = quote! {
-            let #shifted_name = {
-                // we still need to shift by the element's size
-                offset += #size;
-                0
-            };
+        let constructor_part = if repr.wide {
+            // we still need to shift by the element's size
+            quote!(offset += #size;)
+        } else {
+            quote! {
+                let #shifted_name = {
+                    // we still need to shift by the element's size
+                    offset += #size;
+                    0
+                };
+            }
         };
         return (accessors, (constructor_arg, (constructor_part, shifted_name)));
     }
 
-    let getter = generate_getter(field, field_offset, &name);
-    let setter = generate_setter(field, field_offset, &name);
-    let (constructor_arg, constructor_part, shifted_name) = generate_constructor_stuff(ty, &name);
+    // the access policy only decides which accessors we generate, and the default and serde attributes are only used
+    // by `DefaultBits` and the serde derives, so they shouldn't end up on them
+    let access = Access::of(field);
+    let mut field = field.clone();
+    field
+        .attrs
+        .retain(|attr| !access::is_access_attribute(attr) && !shared::is_default_attribute(attr) && !shared::is_serde_attribute(attr));
+    let field = &field;
+
+    let getter = if access.is_readable() {
+        generate_getter(field, field_offset, &name, repr)
+    } else {
+        quote!()
+    };
+    let setter = if access.is_writable() {
+        generate_setter(field, field_offset, &name, repr)
+    } else {
+        quote!()
+    };
+    // the builder needs to write every field, see `builder::generate_builder`
+    let with = generate_with(field, field_offset, &name, access.is_writable(), repr);
+    let clear = if access == Access::WriteOneToClear {
+        generate_clear(field, field_offset, &name, repr)
+    } else {
+        quote!()
+    };
+    let (constructor_arg, constructor_part, shifted_name) = generate_constructor_stuff(ty, &name, repr);
 
     let accessors = quote! {
         #getter
         #setter
+        #with
+        #clear
     };
 
     (accessors, (constructor_arg, (constructor_part, shifted_name)))
 }
 
-fn generate_getter(field: &Field, offset: &TokenStream, name: &Ident) -> TokenStream {
+pub(crate) fn generate_getter(field: &Field, offset: &TokenStream, name: &Ident, repr: Repr) -> TokenStream {
     let Field { attrs, vis, ty, .. } = field;
 
-    let getter_value = struct_gen::generate_getter_value(ty, offset, false);
+    let getter_value = struct_gen::generate_getter_value(ty, offset, false, repr);
 
-    let const_ = if cfg!(feature = "nightly") { quote!(const) } else { quote!() };
+    let const_ = repr.const_keyword();
 
     let array_at = if let Type::Array(array) = ty {
         let elem_ty = &array.elem;
         let len_expr = &array.len;
         let name: Ident = syn::parse_str(&format!("{name}_at")).unwrap_or_else(unreachable);
-        let getter_value = struct_gen::generate_getter_value(elem_ty, offset, true);
+        let getter_value = struct_gen::generate_getter_value(elem_ty, offset, true, repr);
         quote! {
             // #[inline]
             #(#attrs)*
@@ -168,19 +477,19 @@ fn generate_getter(field: &Field, offset: &TokenStream, name: &Ident) -> TokenSt
     }
 }
 
-fn generate_setter(field: &Field, offset: &TokenStream, name: &Ident) -> TokenStream {
+pub(crate) fn generate_setter(field: &Field, offset: &TokenStream, name: &Ident, repr: Repr) -> TokenStream {
     let Field { attrs, vis, ty, .. } = field;
-    let setter_value = struct_gen::generate_setter_value(ty, offset, false);
+    let setter_value = struct_gen::generate_setter_value(ty, offset, false, repr);
 
     let name: Ident = syn::parse_str(&format!("set_{name}")).unwrap_or_else(unreachable);
 
-    let const_ = if cfg!(feature = "nightly") { quote!(const) } else { quote!() };
+    let const_ = repr.const_keyword();
 
     let array_at = if let Type::Array(array) = ty {
         let elem_ty = &array.elem;
         let len_expr = &array.len;
         let name: Ident = syn::parse_str(&format!("{name}_at")).unwrap_or_else(unreachable);
-        let setter_value = struct_gen::generate_setter_value(elem_ty, offset, true);
+        let setter_value = struct_gen::generate_setter_value(elem_ty, offset, true, repr);
         quote! {
             // #[inline]
             #(#attrs)*
@@ -206,7 +515,76 @@ fn generate_setter(field: &Field, offset: &TokenStream, name: &Ident) -> TokenSt
     }
 }
 
-fn generate_constructor_stuff(ty: &Type, name: &Ident) -> (TokenStream, TokenStream, Ident) {
+/// `with_field(self, value) -> Self`, the chainable version of the setter.
+///
+/// Fields without a setter only get a hidden one, which the builder uses, see [`builder::with_name`].
+fn generate_with(field: &Field, offset: &TokenStream, name: &Ident, is_writable: bool, repr: Repr) -> TokenStream {
+    let Field { attrs, vis, ty, .. } = field;
+    let setter_value = struct_gen::generate_setter_value(ty, offset, false, repr);
+
+    let const_ = repr.const_keyword();
+
+    let with_name = builder::with_name(name, is_writable);
+    let signature = quote!(#const_ fn #with_name(mut self, value: #ty) -> Self);
+    let signature = if is_writable {
+        quote! {
+            #(#attrs)*
+            #vis #signature
+        }
+    } else {
+        quote!(#signature)
+    };
+
+    quote! {
+        // #[inline]
+        #[allow(clippy::type_complexity, unused_parens)]
+        #signature {
+            #setter_value
+            self
+        }
+    }
+}
+
+/// Write-1-to-clear fields get cleared by writing ones, so instead of a setter, they get `clear_field()`.
+fn generate_clear(field: &Field, offset: &TokenStream, name: &Ident, repr: Repr) -> TokenStream {
+    let Field { attrs, vis, ty, .. } = field;
+    let clear_value = struct_gen::generate_clear_value(ty, offset, false, repr);
+
+    let name: Ident = syn::parse_str(&format!("clear_{name}")).unwrap_or_else(unreachable);
+
+    let const_ = repr.const_keyword();
+
+    let array_at = if let Type::Array(array) = ty {
+        let elem_ty = &array.elem;
+        let len_expr = &array.len;
+        let name: Ident = syn::parse_str(&format!("{name}_at")).unwrap_or_else(unreachable);
+        let clear_value = struct_gen::generate_clear_value(elem_ty, offset, true, repr);
+        quote! {
+            // #[inline]
+            #(#attrs)*
+            #[allow(clippy::type_complexity, unused_parens)]
+            #vis #const_ fn #name(&mut self, index: usize) {
+                ::core::assert!(index < #len_expr);
+                #clear_value
+            }
+        }
+    } else {
+        quote!()
+    };
+
+    quote! {
+        // #[inline]
+        #(#attrs)*
+        #[allow(clippy::type_complexity, unused_parens)]
+        #vis #const_ fn #name(&mut self) {
+            #clear_value
+        }
+
+        #array_at
+    }
+}
+
+fn generate_constructor_stuff(ty: &Type, name: &Ident, repr: Repr) -> (TokenStream, TokenStream, Ident) {
     let name = format!("arg_{name}");
     let name: Ident = syn::parse_str(&name).unwrap_or_else(unreachable);
     let constructor_arg = quote! {
@@ -215,31 +593,172 @@ fn generate_constructor_stuff(ty: &Type, name: &Ident) -> (TokenStream, TokenStr
     let shifted_name = format!("shifted_{name}");
     let shifted_name: Ident = syn::parse_str(&shifted_name).unwrap_or_else(unreachable);
 
-    let constructor_part = struct_gen::generate_constructor_part(ty, &name, &shifted_name);
+    let constructor_part = struct_gen::generate_constructor_part(ty, &name, &shifted_name, repr);
     (constructor_arg, constructor_part, shifted_name)
 }
 
-fn generate_enum(enum_data: &ItemEnum) -> TokenStream {
+fn generate_enum(enum_data: &ItemEnum, bitsize: BitSize, options: BitsizeOptions, tagged: Option<&Tagged>) -> TokenStream {
     let ItemEnum { vis, ident, variants, .. } = enum_data;
+    let bits_conversions = generate_enum_bits_conversions(enum_data, bitsize, tagged);
+    // the variants of tagged enums are selected by their tag, they don't have a single value
+    let layout_impl = if options.tag_bits.is_none() {
+        generate_enum_layout_impl(enum_data, bitsize)
+    } else {
+        quote!()
+    };
+
+    // the derives already read every value, so we can drop discriminants which rust can't read, see `keeps_discriminants`
+    let mut variants = variants.clone();
+    if !discriminant_assigner::keeps_discriminants(bitsize, variants.iter()) {
+        for variant in &mut variants {
+            variant.discriminant = None;
+        }
+    }
+
     quote! {
         #vis enum #ident {
             #variants
         }
+        impl #ident {
+            #bits_conversions
+        }
+        #layout_impl
+    }
+}
+
+/// `__bilge_from_bits` and `__bilge_into_bits`, like [`generate_struct_bits_conversions`].
+///
+/// These use the same values as `FromBits` and `TryFromBits`, but `__bilge_from_bits` only gets valid values, so it doesn't validate them.
+fn generate_enum_bits_conversions(enum_data: &ItemEnum, bitsize: BitSize, tagged: Option<&Tagged>) -> TokenStream {
+    let ItemEnum { ident, variants, .. } = enum_data;
+    let fallback = variants.iter().find(|variant| variant.attrs.iter().any(shared::is_fallback_attribute));
+    // the derives reject everything else, like variants with named fields or values which aren't `uN`
+    let fallback_value = fallback.and_then(|variant| match &variant.fields {
+        Fields::Unnamed(fields) if fields.unnamed.len() == 1 && shared::is_always_filled(&fields.unnamed[0].ty) => {
+            Some((&variant.ident, &fields.unnamed[0].ty))
+        }
+        _ => None,
+    });
+    if fallback.is_some_and(|fallback| !matches!(fallback.fields, Fields::Unit)) && fallback_value.is_none() {
+        return quote!();
+    }
+    if let Some(tagged) = tagged {
+        return tagged.generate_bits_conversions(fallback_value);
+    }
+
+    let mut assigner = discriminant_assigner::DiscriminantAssigner::new(bitsize, ident);
+    let mut from_bits_arms = vec![];
+    let mut into_bits_arms = vec![];
+    for variant in variants {
+        let name = &variant.ident;
+        let value = assigner.assign_unsuffixed(variant);
+        let is_fallback = fallback.is_some_and(|fallback| fallback.ident == *name);
+        match (&variant.fields, fallback_value) {
+            (Fields::Unit, _) => {
+                // the fallback's value is handled by the catch-all arm
+                if !is_fallback {
+                    from_bits_arms.push(quote!(#value => Self::#name,));
+                }
+                into_bits_arms.push(quote!(Self::#name => (#value) as u128,));
+            }
+            (_, Some((_, ty))) if is_fallback => {
+                let into_bits = shared::generate_const_into_bits(ty, quote!((*number)));
+                into_bits_arms.push(quote!(Self::#name(number) => #into_bits,));
+            }
+            _ => return quote!(),
+        }
+    }
+    let catch_all_arm = match (fallback, fallback_value) {
+        (_, Some((fallback_ident, ty))) => {
+            let from_bits = shared::generate_const_from_bits(ty, quote!(bits));
+            quote!(_ => Self::#fallback_ident(#from_bits),)
+        }
+        (Some(fallback), None) => {
+            let fallback_ident = &fallback.ident;
+            quote!(_ => Self::#fallback_ident,)
+        }
+        (None, None) => quote!(_ => ::core::panic!("unreachable: only valid values are converted"),),
+    };
+
+    let (base_int, _) = shared::base_int_of(bitsize);
+    quote! {
+        #[doc(hidden)]
+        #[allow(clippy::unnecessary_cast)]
+        pub const fn __bilge_from_bits(bits: u128) -> Self {
+            // const discriminants have the type of the primitive
+            match bits as #base_int {
+                #( #from_bits_arms )*
+                #catch_all_arm
+            }
+        }
+
+        #[doc(hidden)]
+        #[allow(clippy::unnecessary_cast)]
+        pub const fn __bilge_into_bits(&self) -> u128 {
+            match self {
+                #( #into_bits_arms )*
+            }
+        }
+    }
+}
+
+/// `VARIANTS` with the same values the derives use. A fallback with value has no value of its own, so it is left out.
+fn generate_enum_layout_impl(enum_data: &ItemEnum, bitsize: BitSize) -> TokenStream {
+    let ItemEnum { ident, variants, .. } = enum_data;
+    let mut assigner = discriminant_assigner::DiscriminantAssigner::new(bitsize, ident);
+    let variant_infos = variants.iter().filter(|variant| matches!(variant.fields, Fields::Unit)).map(|variant| {
+        let name = variant.ident.unraw().to_string();
+        let value = assigner.assign_unsuffixed(variant);
+        quote!(::bilge::VariantInfo::new(#name, (#value) as u128))
+    });
+
+    quote! {
+        impl ::bilge::Layout for #ident {
+            const FIELDS: &'static [::bilge::FieldInfo] = &[];
+            const VARIANTS: &'static [::bilge::VariantInfo] = &[#( #variant_infos ),*];
+        }
     }
 }
 
 /// We have _one_ `generate_common` function, which holds everything struct and enum have _in common_.
 /// Everything else has its own `generate_` functions.
-fn generate_common(ir: ItemIr, arb_int: &TokenStream) -> TokenStream {
-    let ItemIr { attrs, name, expanded } = ir;
+fn generate_common(ir: ItemIr, arb_int: &TokenStream, bitsize: BitSize) -> TokenStream {
+    let ItemIr {
+        attrs,
+        name,
+        generics,
+        expanded,
+        validity,
+        w1c_mask,
+    } = ir;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    // a byte array is not `Bitsized`, so we fill in the numbers ourselves
+    let bitsized_impl = if shared::is_wide(bitsize) {
+        let bitsize = bitsize as usize;
+        quote! {
+            const BITS: usize = #bitsize;
+            const MAX: Self::ArbitraryInt = [u8::MAX; #bitsize / 8];
+        }
+    } else {
+        quote! {
+            const BITS: usize = <Self::ArbitraryInt as Bitsized>::BITS;
+            const MAX: Self::ArbitraryInt = <Self::ArbitraryInt as Bitsized>::MAX;
+        }
+    };
+
+    let attrs = attrs
+        .iter()
+        .filter(|attr| !shared::is_reset_attribute(attr) && !shared::is_serde_bits_attribute(attr));
 
     quote! {
         #(#attrs)*
         #expanded
-        impl ::bilge::Bitsized for #name {
+        impl #impl_generics ::bilge::Bitsized for #name #ty_generics #where_clause {
             type ArbitraryInt = #arb_int;
-            const BITS: usize = <Self::ArbitraryInt as Bitsized>::BITS;
-            const MAX: Self::ArbitraryInt = <Self::ArbitraryInt as Bitsized>::MAX;
+            #bitsized_impl
+            #validity
+            #w1c_mask
         }
     }
 }
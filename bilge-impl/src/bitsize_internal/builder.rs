@@ -0,0 +1,131 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, parse_quote, GenericParam, ItemStruct};
+
+use super::{field_name, is_reserved};
+use crate::shared::{
+    self,
+    access::{self, Access},
+    Repr,
+};
+
+/// `Register` -> `RegisterBuilder`, which `bitsize` needs to know as well, see `sealed::generate_module`.
+pub(crate) fn builder_name(ident: &Ident) -> Ident {
+    format_ident!("{}Builder", ident)
+}
+
+/// `with_field` for fields with a setter, a hidden `__bilge_with_field` for all others.
+pub(crate) fn with_name(name: &Ident, is_writable: bool) -> Ident {
+    if is_writable {
+        format_ident!("with_{}", name)
+    } else {
+        format_ident!("__bilge_with_{}", name)
+    }
+}
+
+/// A builder which only allows calling `build()` once every field is set:
+/// ```ignore
+/// let register = Register::builder().header(header).footer(footer).build();
+/// ```
+/// Whether a field was set is part of the builder's type, like `RegisterBuilder<true, false>`,
+/// with one `const FIELD_IS_SET: bool` per field, in declaration order. Reserved fields are left at zero or their `#[reserved(..)]` value, like in `new`.
+///
+/// The builder wraps a bitfield holding only the reserved values, which every field method writes into through `with_field`.
+pub(super) fn generate_builder(item: &ItemStruct, repr: Repr) -> TokenStream {
+    let ItemStruct {
+        vis,
+        ident,
+        generics,
+        fields,
+        ..
+    } = item;
+    let builder = builder_name(ident);
+    let const_ = repr.const_keyword();
+
+    let fields: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| (field_name(field, i), field))
+        .filter(|(name, _)| !is_reserved(name))
+        .collect();
+    let states: Vec<Ident> = fields
+        .iter()
+        .map(|(name, _)| format_ident!("{}_IS_SET", name.unraw().to_string().to_uppercase()))
+        .collect();
+
+    // the struct's own parameters come first, followed by the states
+    let params: Vec<Ident> = generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(param) => Some(param.ident.clone()),
+            GenericParam::Const(param) => Some(param.ident.clone()),
+            // `bitsize` already rejected these
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let builder_type = |states: &[TokenStream]| quote!(#builder<#( #params, )* #( #states ),*>);
+    let mut builder_generics = generics.clone();
+    builder_generics
+        .params
+        .extend(states.iter().map(|state| -> GenericParam { parse_quote!(const #state: bool) }));
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let (builder_impl_generics, builder_ty_generics, _) = builder_generics.split_for_impl();
+    let builder_params = &builder_generics.params;
+
+    let unset = builder_type(&vec![quote!(false); states.len()]);
+    let set = builder_type(&vec![quote!(true); states.len()]);
+
+    let field_methods = fields.iter().enumerate().map(|(i, (name, field))| {
+        let ty = &field.ty;
+        let with = with_name(name, Access::of(field).is_writable());
+        let mut states: Vec<_> = states.iter().map(|state| quote!(#state)).collect();
+        states[i] = quote!(true);
+        let next = builder_type(&states);
+        // attributes like docs apply to the builder method as well, except for the access policy, the default and serde
+        let attrs = field
+            .attrs
+            .iter()
+            .filter(|attr| !access::is_access_attribute(attr) && !shared::is_default_attribute(attr) && !shared::is_serde_attribute(attr));
+        quote! {
+            #(#attrs)*
+            #[allow(clippy::type_complexity, unused_parens)]
+            pub #const_ fn #name(self, value: #ty) -> #next {
+                #builder { inner: self.inner.#with(value) }
+            }
+        }
+    });
+
+    let reserved = if repr.wide {
+        quote!(Self::__BILGE_RESERVED)
+    } else {
+        shared::generate_const_new(repr.bitsize, quote!(Self::__BILGE_RESERVED))
+    };
+    let init = shared::generate_struct_init(generics);
+    let doc = format!(" Builds a [`{ident}`] one field at a time, see [`{ident}::builder`].");
+
+    quote! {
+        #[doc = #doc]
+        #vis struct #builder<#builder_params> #where_clause {
+            inner: #ident #ty_generics,
+        }
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// Creates a builder, which can only `build()` once every field is set. Reserved fields are zero, or their required value.
+            pub #const_ fn builder() -> #unset {
+                let value = #reserved;
+                #builder { inner: #init }
+            }
+        }
+        #[allow(missing_docs)]
+        impl #builder_impl_generics #builder #builder_ty_generics #where_clause {
+            #( #field_methods )*
+        }
+        impl #impl_generics #set #where_clause {
+            /// Returns the bitfield, now that every field is set.
+            pub #const_ fn build(self) -> #ident #ty_generics {
+                self.inner
+            }
+        }
+    }
+}
@@ -28,26 +28,49 @@ use super::*;
 /// Top-level function which initializes the cursor and offsets it to what we want to read
 ///
 /// `is_array_elem_getter` allows us to generate an array_at getter more easily
-pub(crate) fn generate_getter_value(ty: &Type, offset: &TokenStream, is_array_elem_getter: bool) -> TokenStream {
+///
+/// `wide` bitfields use a bit position into their byte array as cursor, see [`shared::is_wide`]
+pub(crate) fn generate_getter_value(ty: &Type, offset: &TokenStream, is_array_elem_getter: bool, repr: Repr) -> TokenStream {
     // if we generate `fn array_at(index)`, we need to offset to the array element
-    let elem_offset = if is_array_elem_getter {
-        let size = shared::generate_type_bitsize(ty);
-        quote! {
-            let size = #size;
-            // cursor now starts at this element
-            cursor >>= size * index;
+    let elem_offset = match (is_array_elem_getter, repr.wide) {
+        (false, _) => quote!(),
+        (true, false) => {
+            let size = shared::generate_type_bitsize(ty);
+            quote! {
+                let size = #size;
+                // cursor now starts at this element
+                cursor >>= size * index;
+            }
+        }
+        (true, true) => {
+            let size = shared::generate_type_bitsize(ty);
+            quote! {
+                let size = #size;
+                // cursor now starts at this element
+                cursor += size * index;
+            }
         }
-    } else {
-        quote!()
     };
 
-    let inner = generate_getter_inner(ty, true);
+    let inner = generate_getter_inner(ty, repr);
+    let aliases = generate_aliases(repr);
+    if repr.wide {
+        return quote! {
+            #aliases
+            // the bytes we read from
+            let bytes = &self.value;
+            // cursor is the bit position we read at and starts at this field
+            let mut cursor = #offset;
+            #elem_offset
+
+            #inner
+        };
+    }
+    let struct_value = generate_struct_value(repr);
     quote! {
-        // for ease of reading
-        type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
-        type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
+        #aliases
         // cursor is the value we read from and starts at the struct's first field
-        let mut cursor = self.value.value();
+        let mut cursor = #struct_value;
         // this field's offset
         let field_offset = #offset;
         // cursor now starts at this field
@@ -62,8 +85,11 @@ pub(crate) fn generate_getter_value(ty: &Type, offset: &TokenStream, is_array_el
 /// be done in the same way as transmuting into an array [T; N1*N2].
 /// Otherwise, nested arrays would generate even more code.
 ///
-/// `is_getter` allows us to generate a try_from impl more easily
-pub(crate) fn generate_getter_inner(ty: &Type, is_getter: bool) -> TokenStream {
+/// `wide` getters expect `bytes` and a bit position `cursor` in scope, the others expect an integer `cursor`.
+///
+/// `generic` getters can't transmute arrays of unknown size and can't cast values of unknown type, see [`shared::is_generic`].
+/// All others are const, see [`Repr::is_const`].
+pub(crate) fn generate_getter_inner(ty: &Type, repr: Repr) -> TokenStream {
     use Type::*;
     match ty {
         Tuple(tuple) => {
@@ -72,20 +98,12 @@ pub(crate) fn generate_getter_inner(ty: &Type, is_getter: bool) -> TokenStream {
                 .iter()
                 .map(|elem| {
                     // for every tuple element, generate its getter code
-                    let getter = generate_getter_inner(elem, is_getter);
+                    let getter = generate_getter_inner(elem, repr);
                     // and add a scope around it
                     quote! { {#getter} }
                 })
-                .reduce(|acc, next| {
-                    // join all getter codes with:
-                    if is_getter {
-                        // comma, to later produce (val_1, val_2, ...)
-                        quote!(#acc, #next)
-                    } else {
-                        // bool-and, since for try_from we just generate bools
-                        quote!(#acc && #next)
-                    }
-                })
+                // join all getter codes with comma, to later produce (val_1, val_2, ...)
+                .reduce(|acc, next| quote!(#acc, #next))
                 // `field: (),` will be handled like this:
                 .unwrap_or_else(|| quote!());
             // add tuple braces, to produce (val_1, val_2, ...)
@@ -95,95 +113,58 @@ pub(crate) fn generate_getter_inner(ty: &Type, is_getter: bool) -> TokenStream {
             // [[T; N1]; N2] -> (N1*N2, T)
             let (len_expr, elem_ty) = length_and_type_of_nested_array(array);
             // generate the getter code for one array element
-            let array_elem = generate_getter_inner(&elem_ty, is_getter);
-            // either generate an array or only check each value
-            if is_getter {
+            let array_elem = generate_getter_inner(&elem_ty, repr);
+            let into_array = if repr.generic {
                 quote! {
-                    // constness: iter, array::from_fn, for-loop, range are not const, so we're using while loops
-                    // Modified version of the array init example in [`MaybeUninit`]:
-                    let array = {
-                        // [T; N1*N2]
-                        let mut array: [::core::mem::MaybeUninit<#elem_ty>; #len_expr] = unsafe {
-                            ::core::mem::MaybeUninit::uninit().assume_init()
-                        };
-                        let mut i = 0;
-                        while i < #len_expr {
-                            // for every element, get its value
-                            let elem_value = {
-                                #array_elem
-                            };
-                            // and write it to the output array
-                            array[i].write(elem_value);
-                            i += 1;
-                        }
-                        // [T; N1*N2] -> [[T; N1]; N2]
-                        unsafe { ::core::mem::transmute(array) }
-                    };
-                    array
+                    // [T; N1*N2] -> [[T; N1]; N2], which is the same as transmuting, but works for unknown sizes
+                    unsafe { ::core::ptr::read(&array as *const _ as *const #ty) }
                 }
             } else {
-                quote! { {
-                    let mut is_filled = true;
+                quote! {
+                    // [T; N1*N2] -> [[T; N1]; N2]
+                    unsafe { ::core::mem::transmute(array) }
+                }
+            };
+            quote! {
+                // constness: iter, array::from_fn, for-loop, range are not const, so we're using while loops
+                // Modified version of the array init example in [`MaybeUninit`]:
+                let array = {
+                    // [T; N1*N2]
+                    let mut array: [::core::mem::MaybeUninit<#elem_ty>; #len_expr] = unsafe {
+                        ::core::mem::MaybeUninit::uninit().assume_init()
+                    };
                     let mut i = 0;
-                    // TODO: this could be simplified for always-filled values
                     while i < #len_expr {
-                        // for every element, get its filled check
-                        let elem_filled = {
+                        // for every element, get its value
+                        let elem_value = {
                             #array_elem
                         };
-                        // and join it with the others
-                        is_filled = is_filled && elem_filled;
+                        // and write it to the output array (`MaybeUninit::write` is not const in our MSRV)
+                        array[i] = ::core::mem::MaybeUninit::new(elem_value);
                         i += 1;
                     }
-                    is_filled
-                } }
+                    #into_array
+                };
+                array
+            }
+        }
+        Path(_) if repr.is_const() => {
+            let elem_bits = generate_elem_bits(ty, repr);
+            // generate the real value from its bits
+            let value = shared::generate_const_from_bits(ty, quote!(bits));
+            quote! {
+                #elem_bits
+                #value
             }
         }
         Path(_) => {
-            // get the size, so we can shift to the next element's offset
-            let size = shared::generate_type_bitsize(ty);
-
-            // do all steps until conversion
-            let elem_value = quote! {
-                // the cursor starts at this element's offset, now get its value
-                let raw_value = cursor;
-                // after getting the value, we can shift by the element's size
-                // TODO: we could move this into tuple/array (and try_from, below)
-                let size = #size;
-                cursor = cursor.wrapping_shr(size as u32);
-                // cast the element value (e.g. u32 -> u8),
-                let raw_value: BaseIntOf<#ty> = raw_value as BaseIntOf<#ty>;
-                // which allows it to be used here (e.g. u4::masked_new(u8))
-                let elem_value = <#ty as Bitsized>::ArbitraryInt::masked_new(raw_value);
-            };
-
-            if is_getter {
-                // generate the real value from the arbint `elem_value`
-                quote! {
-                    #elem_value
-                    match <#ty>::try_from(elem_value) {
-                        Ok(v) => v,
-                        Err(_) => panic!("unreachable"),
-                    }
-                }
-            } else {
-                // generate only the filled check
-                if shared::is_always_filled(ty) {
-                    // skip the obviously filled values
-                    quote! {
-                        // we still need to shift by the element's size
-                        let size = #size;
-                        cursor = cursor.wrapping_shr(size as u32);
-                        true
-                    }
-                } else {
-                    // handle structs, enums - everything which can be unfilled
-                    quote! { {
-                        #elem_value
-                        // so, has try_from impl
-                        // note this is available even if the type is `From`
-                        <#ty>::try_from(elem_value).is_ok()
-                    } }
+            let elem_value = generate_elem_value(ty, repr);
+            // generate the real value from the arbint `elem_value`
+            quote! {
+                #elem_value
+                match <#ty>::try_from(elem_value) {
+                    Ok(v) => v,
+                    Err(_) => panic!("unreachable"),
                 }
             }
         }
@@ -191,10 +172,75 @@ pub(crate) fn generate_getter_inner(ty: &Type, is_getter: bool) -> TokenStream {
     }
 }
 
+/// Reads the element at the cursor as `let bits`, an `u128`, and moves the cursor past it.
+fn generate_elem_bits(ty: &Type, repr: Repr) -> TokenStream {
+    let size = shared::generate_type_bitsize(ty);
+    if repr.wide {
+        quote! {
+            let size = #size;
+            let bits = ::bilge::read_bits(bytes, cursor, size);
+            // after getting the value, we can move on by the element's size
+            cursor += size;
+        }
+    } else {
+        quote! {
+            let size = #size;
+            // the cursor starts at this element's offset, so we only need to mask off the elements after it
+            let bits = cursor as u128 & (u128::MAX >> (u128::BITS as usize - size));
+            // after getting the value, we can shift by the element's size
+            cursor = cursor.wrapping_shr(size as u32);
+        }
+    }
+}
+
+/// Reads the element at the cursor as `let elem_value`, the arbint of `ty`, and moves the cursor past it.
+///
+/// This is shared with `TryFromBits`, which checks `elem_value` instead of converting it.
+pub(crate) fn generate_elem_value(ty: &Type, repr: Repr) -> TokenStream {
+    // get the size, so we can shift to the next element's offset
+    let size = shared::generate_type_bitsize(ty);
+
+    let read_raw_value = if repr.wide {
+        quote! {
+            // the cursor points at this element's offset, now get its value
+            let size = #size;
+            let raw_value = ::bilge::read_bits(bytes, cursor, size);
+            // after getting the value, we can move on by the element's size
+            cursor += size;
+        }
+    } else {
+        quote! {
+            // the cursor starts at this element's offset, now get its value
+            let raw_value = cursor;
+            // after getting the value, we can shift by the element's size
+            // TODO: we could move this into tuple/array (and try_from, below)
+            let size = #size;
+            cursor = cursor.wrapping_shr(size as u32);
+        }
+    };
+
+    // do all steps until conversion
+    if repr.generic {
+        quote! {
+            #read_raw_value
+            // we can't cast into an unknown type, but masked_new takes any integer (e.g. u4::masked_new(u32))
+            let elem_value = <ArbIntOf<#ty> as Integer>::masked_new(raw_value);
+        }
+    } else {
+        quote! {
+            #read_raw_value
+            // cast the element value (e.g. u32 -> u8),
+            let raw_value: BaseIntOf<#ty> = raw_value as BaseIntOf<#ty>;
+            // which allows it to be used here (e.g. u4::masked_new(u8))
+            let elem_value = <#ty as Bitsized>::ArbitraryInt::masked_new(raw_value);
+        }
+    }
+}
+
 /// Top-level function which initializes the offset, masks other values and combines the final value
 ///
 /// `is_array_elem_setter` allows us to generate a set_array_at setter more easily
-pub(crate) fn generate_setter_value(ty: &Type, offset: &TokenStream, is_array_elem_setter: bool) -> TokenStream {
+pub(crate) fn generate_setter_value(ty: &Type, offset: &TokenStream, is_array_elem_setter: bool, repr: Repr) -> TokenStream {
     // if we generate `fn set_array_at(index, value)`, we need to offset to the array element
     let elem_offset = if is_array_elem_setter {
         let size = shared::generate_type_bitsize(ty);
@@ -207,12 +253,31 @@ pub(crate) fn generate_setter_value(ty: &Type, offset: &TokenStream, is_array_el
         quote!()
     };
 
-    let value_shifted = generate_setter_inner(ty);
+    let aliases = generate_aliases(repr);
+    if repr.wide {
+        // no masking needed, we overwrite exactly this field's bits
+        let written = generate_setter_inner_wide(ty, repr);
+        return quote! {
+            #aliases
+
+            // offset now starts at this field
+            let mut offset = #offset;
+            #elem_offset
+
+            // the bytes we write into
+            let bytes = &mut self.value;
+            #written
+        };
+    }
+
+    let value_shifted = generate_setter_inner(ty, repr);
     // get the mask, so we can set this field's value
-    let mask = generate_ty_mask(ty);
+    let mask = generate_ty_mask(ty, repr);
+    let base_int = generate_base_int(repr);
+    let struct_value = generate_struct_value(repr);
+    let new_struct_value = generate_new_struct_value(repr);
     quote! {
-        type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
-        type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
+        #aliases
 
         // offset now starts at this field
         let mut offset = #offset;
@@ -220,27 +285,81 @@ pub(crate) fn generate_setter_value(ty: &Type, offset: &TokenStream, is_array_el
 
         let field_mask = #mask;
         // shift the mask into place
-        let field_mask: BaseIntOf<Self> = field_mask << offset;
+        let field_mask: #base_int = field_mask << offset;
         // all other fields as a mask
-        let others_mask: BaseIntOf<Self> = !field_mask;
+        let others_mask: #base_int = !field_mask;
         // the current struct value
-        let struct_value: BaseIntOf<Self> = self.value.value();
+        let struct_value: #base_int = #struct_value;
         // mask off the field getting set
-        let others_values: BaseIntOf<Self> = struct_value & others_mask;
+        let others_values: #base_int = struct_value & others_mask;
 
         // get the new field value, shifted into place
         #value_shifted
 
         // join the values using bit-or
         let new_struct_value = others_values | value_shifted;
-        self.value = <ArbIntOf<Self>>::new(new_struct_value);
+        self.value = #new_struct_value;
+    }
+}
+
+/// Sets every bit of a field, which is how write-1-to-clear fields get cleared.
+///
+/// Like [`generate_setter_value`], but there is no value to convert, so we only need the mask.
+pub(crate) fn generate_clear_value(ty: &Type, offset: &TokenStream, is_array_elem_clear: bool, repr: Repr) -> TokenStream {
+    let size = shared::generate_type_bitsize(ty);
+    let elem_offset = if is_array_elem_clear {
+        quote! {
+            // offset now starts at this element
+            offset += (#size) * index;
+        }
+    } else {
+        quote!()
+    };
+
+    if repr.wide {
+        return quote! {
+            // offset now starts at this field
+            let mut offset = #offset;
+            #elem_offset
+
+            // arrays can be wider than 128 bits, so we write chunk by chunk
+            let mut remaining = #size;
+            while remaining > 0 {
+                let width = if remaining < 128 { remaining } else { 128 };
+                ::bilge::write_bits(&mut self.value, offset, width, u128::MAX);
+                offset += width;
+                remaining -= width;
+            }
+        };
+    }
+
+    let mask = generate_ty_mask(ty, repr);
+    let aliases = generate_aliases(repr);
+    let base_int = generate_base_int(repr);
+    let struct_value = generate_struct_value(repr);
+    let new_struct_value = generate_new_struct_value(repr);
+    quote! {
+        #aliases
+
+        // offset now starts at this field
+        let mut offset = #offset;
+        #elem_offset
+
+        let field_mask = #mask;
+        // shift the mask into place
+        let field_mask: #base_int = field_mask << offset;
+        let new_struct_value = #struct_value | field_mask;
+        self.value = #new_struct_value;
     }
 }
 
 /// We heavily rely on the fact that transmuting into a nested array [[T; N1]; N2] can
 /// be done in the same way as transmuting into an array [T; N1*N2].
 /// Otherwise, nested arrays would generate even more code.
-fn generate_setter_inner(ty: &Type) -> TokenStream {
+///
+/// `generic` setters can't transmute arrays of unknown size and can't cast values of unknown type, see [`shared::is_generic`].
+/// All others are const, see [`Repr::is_const`].
+fn generate_setter_inner(ty: &Type, repr: Repr) -> TokenStream {
     use Type::*;
     match ty {
         Tuple(tuple) => {
@@ -253,7 +372,7 @@ fn generate_setter_inner(ty: &Type) -> TokenStream {
                     let elem_name = quote!(value.#tuple_index);
                     tuple_index.index += 1;
                     // for every tuple element, generate its setter code
-                    let value_shifted = generate_setter_inner(elem);
+                    let value_shifted = generate_setter_inner(elem, repr);
                     // set the value and add a scope around it
                     quote! { {
                         let value = #elem_name;
@@ -273,17 +392,16 @@ fn generate_setter_inner(ty: &Type) -> TokenStream {
             // [[T; N1]; N2] -> (N1*N2, T)
             let (len_expr, elem_ty) = length_and_type_of_nested_array(array);
             // generate the setter code for one array element
-            let value_shifted = generate_setter_inner(&elem_ty);
+            let value_shifted = generate_setter_inner(&elem_ty, repr);
+            let (flattened, elem) = flatten_array(&len_expr, &elem_ty, repr.generic);
             quote! {
-                // [[T; N1]; N2] -> [T; N1*N2], for example: [[(u2, u2); 3]; 4] -> [(u2, u2); 12]
-                #[allow(clippy::useless_transmute)]
-                let value: [#elem_ty; #len_expr] = unsafe { ::core::mem::transmute(value) };
+                #flattened
                 // constness: iter, for-loop, range are not const, so we're using while loops
                 // [u4; 8] -> u32
                 let mut acc = 0;
                 let mut i = 0;
                 while i < #len_expr {
-                    let value = value[i];
+                    #elem
                     // for every element, shift its value into its place
                     #value_shifted
                     // and bit-or them together
@@ -296,12 +414,22 @@ fn generate_setter_inner(ty: &Type) -> TokenStream {
         Path(_) => {
             // get the size, so we can reach the next element afterwards
             let size = shared::generate_type_bitsize(ty);
+            let value = if repr.is_const() {
+                let base_int = generate_base_int(repr);
+                let bits = shared::generate_const_into_bits(ty, quote!(value));
+                quote! {
+                    // cast the element's bits (e.g. u128 -> u32),
+                    // which allows them to be combined with the struct's value later
+                    let value = #bits as #base_int;
+                }
+            } else {
+                quote! {
+                    // we can't cast from an unknown type, but masked_new takes any integer (e.g. u32::masked_new(u4))
+                    let value: BaseIntOf<Self> = <BaseIntOf<Self> as Integer>::masked_new(<ArbIntOf<#ty>>::from(value).to_unsigned());
+                }
+            };
             quote! {
-                // the element's value as it's underlying unsigned type
-                let value = (<ArbIntOf<#ty>>::from(value).value() & (<<ArbIntOf<#ty> as Integer>::UnsignedInteger as Integer>::MAX.value() as <ArbIntOf<#ty> as Integer>::UnderlyingType)) as <<ArbIntOf<#ty> as Integer>::UnsignedInteger as Integer>::UnderlyingType; // FIXME: to_bits
-                // cast the element value (e.g. u8 -> u32),
-                // which allows it to be combined with the struct's value later
-                let value: BaseIntOf<Self> = value as BaseIntOf<Self>;
+                #value
                 let value_shifted = value << offset;
                 // increase the offset to allow the next element to be read
                 offset += #size;
@@ -311,11 +439,78 @@ fn generate_setter_inner(ty: &Type) -> TokenStream {
     }
 }
 
+/// Like [`generate_setter_inner`], but writes every element straight into the `bytes` of a wide bitfield.
+fn generate_setter_inner_wide(ty: &Type, repr: Repr) -> TokenStream {
+    use Type::*;
+    match ty {
+        Tuple(tuple) => {
+            let written = tuple.elems.iter().enumerate().map(|(i, elem)| {
+                let tuple_index = syn::Index::from(i);
+                // for every tuple element, generate its setter code
+                let written = generate_setter_inner_wide(elem, repr);
+                // set the value and add a scope around it
+                quote! { {
+                    let value = value.#tuple_index;
+                    #written
+                } }
+            });
+            quote! { #( #written )* }
+        }
+        Array(array) => {
+            // [[T; N1]; N2] -> (N1*N2, T)
+            let (len_expr, elem_ty) = length_and_type_of_nested_array(array);
+            // generate the setter code for one array element
+            let written = generate_setter_inner_wide(&elem_ty, repr);
+            let (flattened, elem) = flatten_array(&len_expr, &elem_ty, repr.generic);
+            quote! {
+                #flattened
+                // constness: iter, for-loop, range are not const, so we're using while loops
+                let mut i = 0;
+                while i < #len_expr {
+                    #elem
+                    // for every element, write its value into its place
+                    #written
+                    i += 1;
+                }
+            }
+        }
+        Path(_) => {
+            // get the size, so we can reach the next element afterwards
+            let size = shared::generate_type_bitsize(ty);
+            let value = if repr.is_const() {
+                let bits = shared::generate_const_into_bits(ty, quote!(value));
+                quote! {
+                    let value = #bits;
+                }
+            } else {
+                quote! {
+                    // we can't cast from an unknown type
+                    let value = <ArbIntOf<#ty>>::from(value).to_unsigned().as_u128();
+                }
+            };
+            quote! {
+                #value
+                ::bilge::write_bits(bytes, offset, #size, value);
+                // increase the offset to allow the next element to be written
+                offset += #size;
+            }
+        }
+        _ => unreachable(()),
+    }
+}
+
 /// The constructor code just needs every field setter.
 ///
-/// [`super::generate_struct`] contains the initialization of `offset`.
-pub(crate) fn generate_constructor_part(ty: &Type, name: &Ident, shifted_name: &Ident) -> TokenStream {
-    let value_shifted = generate_setter_inner(ty);
+/// [`super::generate_struct`] contains the initialization of `offset` (and of `bytes`, for wide bitfields).
+pub(crate) fn generate_constructor_part(ty: &Type, name: &Ident, shifted_name: &Ident, repr: Repr) -> TokenStream {
+    if repr.wide {
+        let written = generate_setter_inner_wide(ty, repr);
+        return quote! { {
+            let value = #name;
+            #written
+        } };
+    }
+    let value_shifted = generate_setter_inner(ty, repr);
     // setters look like this: `fn set_field1(&mut self, value: u3)`
     // constructors like this: `fn new(field1: u3, field2: u4) -> Self`
     // so we need to rename `field1` -> `value` and put this in a scope
@@ -328,9 +523,25 @@ pub(crate) fn generate_constructor_part(ty: &Type, name: &Ident, shifted_name: &
     }
 }
 
+/// Moves the cursor past an element without reading it.
+pub(crate) fn skip_cursor(size: &TokenStream, wide: bool) -> TokenStream {
+    if wide {
+        quote! {
+            // we still need to move on by the element's size
+            cursor += #size;
+        }
+    } else {
+        quote! {
+            // we still need to shift by the element's size
+            let size = #size;
+            cursor = cursor.wrapping_shr(size as u32);
+        }
+    }
+}
+
 /// We mostly need this in [`generate_setter_value`], to mask the whole field.
 /// It basically combines a bunch of `Bitsized::MAX` values into a mask.
-fn generate_ty_mask(ty: &Type) -> TokenStream {
+fn generate_ty_mask(ty: &Type, repr: Repr) -> TokenStream {
     use Type::*;
     match ty {
         Tuple(tuple) => {
@@ -340,7 +551,7 @@ fn generate_ty_mask(ty: &Type) -> TokenStream {
                 .iter()
                 .map(|elem| {
                     // for every element, generate a mask
-                    let mask = generate_ty_mask(elem);
+                    let mask = generate_ty_mask(elem, repr);
                     // get it's size
                     let elem_size = shared::generate_type_bitsize(elem);
                     // generate it's offset from all previous sizes
@@ -362,7 +573,7 @@ fn generate_ty_mask(ty: &Type) -> TokenStream {
             let elem_ty = &array.elem;
             let len_expr = &array.len;
             // generate the mask for one array element
-            let mask = generate_ty_mask(elem_ty);
+            let mask = generate_ty_mask(elem_ty, repr);
             // and the size
             let ty_size = shared::generate_type_bitsize(elem_ty);
             quote! { {
@@ -378,15 +589,89 @@ fn generate_ty_mask(ty: &Type) -> TokenStream {
                 field_mask
             } }
         }
+        Path(_) if repr.is_const() => {
+            let size = shared::generate_type_bitsize(ty);
+            let base_int = generate_base_int(repr);
+            quote! {
+                // all bits of the type, cast to the struct's primitive
+                // (u2, u12) -> u8 << 0 | u16 << 2 -> u8 | u16 not possible
+                ((u128::MAX >> (u128::BITS as usize - #size)) as #base_int)
+            }
+        }
         Path(_) => quote! {
-            // Casting this is needed in some places, but it might not be needed in some others.
-            // (u2, u12) -> u8 << 0 | u16 << 2 -> u8 | u16 not possible
-            (<<ArbIntOf<#ty> as Integer>::UnsignedInteger as Integer>::MAX.value() as BaseIntOf<Self>) // FIXME: MASK
+            // we can't cast from an unknown type, but masked_new takes any integer
+            <BaseIntOf<Self> as Integer>::masked_new(<<ArbIntOf<#ty> as Integer>::UnsignedInteger as Integer>::MAX)
         },
         _ => unreachable(()),
     }
 }
 
+/// The aliases for reading trait-based code, which only generic bitfields still need, see [`Repr::is_const`].
+fn generate_aliases(repr: Repr) -> TokenStream {
+    if repr.is_const() {
+        return quote!();
+    }
+    quote! {
+        // for ease of reading
+        type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
+        type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
+    }
+}
+
+/// The primitive holding the struct's value, e.g. `u32` for `u20`.
+fn generate_base_int(repr: Repr) -> TokenStream {
+    if repr.is_const() {
+        let (base_int, _) = shared::base_int_of(repr.bitsize);
+        quote!(#base_int)
+    } else {
+        quote!(BaseIntOf<Self>)
+    }
+}
+
+/// `self.value` as its primitive.
+fn generate_struct_value(repr: Repr) -> TokenStream {
+    if repr.is_const() {
+        shared::generate_const_value(repr.bitsize, quote!(self.value))
+    } else {
+        quote!(self.value.value())
+    }
+}
+
+/// `new_struct_value` as the struct's `uN`.
+fn generate_new_struct_value(repr: Repr) -> TokenStream {
+    if repr.is_const() {
+        shared::generate_const_new(repr.bitsize, quote!(new_struct_value))
+    } else {
+        quote!(<ArbIntOf<Self>>::new(new_struct_value))
+    }
+}
+
+/// Turns the array `value` into a flat array, and gives the code which takes its element `i` as `value`.
+///
+/// Generic arrays can have an unknown size, which `transmute` doesn't allow, so we read them through a pointer instead.
+/// Their elements might not be `Copy`, so we move every element out exactly once and never drop the array.
+fn flatten_array(len_expr: &TokenStream, elem_ty: &Type, generic: bool) -> (TokenStream, TokenStream) {
+    if generic {
+        let flattened = quote! {
+            let value = ::core::mem::ManuallyDrop::new(value);
+            // [[T; N1]; N2] -> [T; N1*N2], for example: [[(u2, u2); 3]; 4] -> [(u2, u2); 12]
+            let value: &[#elem_ty; #len_expr] = unsafe { &*(&value as *const _ as *const [#elem_ty; #len_expr]) };
+        };
+        let elem = quote! {
+            // every element gets read once, and the array is never dropped
+            let value = unsafe { ::core::ptr::read(&value[i]) };
+        };
+        (flattened, elem)
+    } else {
+        let flattened = quote! {
+            // [[T; N1]; N2] -> [T; N1*N2], for example: [[(u2, u2); 3]; 4] -> [(u2, u2); 12]
+            #[allow(clippy::useless_transmute)]
+            let value: [#elem_ty; #len_expr] = unsafe { ::core::mem::transmute(value) };
+        };
+        (flattened, quote!(let value = value[i];))
+    }
+}
+
 /// We compute nested length here, to fold [[T; N]; M] to [T; N * M].
 fn length_and_type_of_nested_array(array: &syn::TypeArray) -> (TokenStream, Type) {
     let elem_ty = &array.elem;
@@ -0,0 +1,108 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Fields, Generics, ItemEnum, Type};
+
+use crate::shared::{self, discriminant_assigner::DiscriminantAssigner, is_fallback_attribute, reserved::ReservedValue, tagged::Tagged, BitSize};
+
+/// `Bitsized::VALIDITY` of a struct, listing every field which isn't valid for all values.
+///
+/// This is what `#[reset = ..]` gets checked against, see `default_bits`. Wide and generic structs keep the default
+/// `Validity::Unknown`, since their values don't fit into an `u128` or the validity of their fields isn't known yet.
+pub(super) fn generate_struct_validity(fields: &Fields, field_offsets: &[TokenStream], bitsize: BitSize, generics: &Generics) -> TokenStream {
+    if shared::is_wide(bitsize) || shared::is_generic(generics) {
+        return quote!();
+    }
+    let field_validities = fields
+        .iter()
+        .zip(field_offsets)
+        .flat_map(|(field, offset)| match ReservedValue::of(field) {
+            Some(reserved_value) => {
+                let ty = &field.ty;
+                let value = reserved_value.to_u128(ty);
+                vec![quote! {
+                    ::bilge::FieldValidity {
+                        offset: #offset,
+                        bits: <#ty as Bitsized>::BITS,
+                        len: 1,
+                        validity: &::bilge::Validity::OneOf(&[#value]),
+                    }
+                }]
+            }
+            None => generate_field_validities(&field.ty, offset.clone()),
+        });
+    quote! {
+        const VALIDITY: ::bilge::Validity = ::bilge::Validity::Fields(&[#( #field_validities ),*]);
+    }
+}
+
+/// Tuples are flattened into their elements, while arrays keep a single entry for all of their elements.
+fn generate_field_validities(ty: &Type, offset: TokenStream) -> Vec<TokenStream> {
+    if shared::is_always_filled(ty) {
+        return vec![];
+    }
+    match ty {
+        Type::Tuple(tuple) => {
+            let mut offset = offset;
+            let mut validities = vec![];
+            for elem in &tuple.elems {
+                validities.extend(generate_field_validities(elem, offset.clone()));
+                let elem_size = shared::generate_type_bitsize(elem);
+                offset = quote!(#offset + #elem_size);
+            }
+            validities
+        }
+        Type::Array(array) => {
+            let elem_ty = &*array.elem;
+            let elem_validities = generate_field_validities(elem_ty, quote!(0));
+            if elem_validities.is_empty() {
+                return vec![];
+            }
+            let elem_validity = match elem_ty {
+                Type::Path(_) => quote!(<#elem_ty as Bitsized>::VALIDITY),
+                _ => quote!(::bilge::Validity::Fields(&[#( #elem_validities ),*])),
+            };
+            let len = &array.len;
+            let elem_size = shared::generate_type_bitsize(elem_ty);
+            vec![quote! {
+                ::bilge::FieldValidity {
+                    offset: #offset,
+                    bits: #elem_size,
+                    len: #len,
+                    validity: &#elem_validity,
+                }
+            }]
+        }
+        _ => vec![quote! {
+            ::bilge::FieldValidity {
+                offset: #offset,
+                bits: <#ty as Bitsized>::BITS,
+                len: 1,
+                validity: &<#ty as Bitsized>::VALIDITY,
+            }
+        }],
+    }
+}
+
+/// `Bitsized::VALIDITY` of an enum: the values of its variants, unless every value has one.
+pub(super) fn generate_enum_validity(enum_data: &ItemEnum, bitsize: BitSize, tagged: Option<&Tagged>) -> TokenStream {
+    let ItemEnum { ident, variants, .. } = enum_data;
+    let has_fallback = variants.iter().flat_map(|variant| &variant.attrs).any(is_fallback_attribute);
+    if let Some(tagged) = tagged {
+        let validity = tagged.generate_validity(has_fallback);
+        return quote!(const VALIDITY: ::bilge::Validity = #validity;);
+    }
+    if has_fallback || shared::enum_fills_bitsize(bitsize, variants.len()) {
+        return quote!(
+            const VALIDITY: ::bilge::Validity = ::bilge::Validity::All;
+        );
+    }
+
+    let mut assigner = DiscriminantAssigner::new(bitsize, ident);
+    let values = variants.iter().map(|variant| {
+        let value = assigner.assign_unsuffixed(variant);
+        quote!((#value) as u128)
+    });
+    quote! {
+        const VALIDITY: ::bilge::Validity = ::bilge::Validity::OneOf(&[#( #values ),*]);
+    }
+}
@@ -3,25 +3,30 @@ use proc_macro_error2::abort_call_site;
 use quote::quote;
 use syn::{Data, Fields};
 
-use crate::shared::{self, unreachable};
+use crate::shared::{self, access, unreachable};
 
 pub(super) fn debug_bits(item: TokenStream) -> TokenStream {
     let derive_input = shared::parse_derive(item);
     let name = &derive_input.ident;
     let name_str = name.to_string();
+    let (bitsize, _) = shared::bitsize_from_internal_attr(&derive_input.attrs);
+    let options = shared::options_from_internal_attr(&derive_input.attrs);
     let struct_data = match derive_input.data {
         Data::Struct(s) => s,
         Data::Enum(_) => abort_call_site!("use derive(Debug) for enums"),
         Data::Union(_) => unreachable(()),
     };
+    let repr = shared::Repr::new(bitsize, &derive_input.generics, &struct_data.fields, options);
 
+    let field_offsets = shared::generate_field_offsets(&struct_data.fields, options.msb_first);
     let fmt_impl = match struct_data.fields {
         Fields::Named(fields) => {
-            let calls = fields.named.iter().map(|f| {
+            let calls = fields.named.iter().zip(&field_offsets).map(|(f, offset)| {
                 // We can unwrap since this is a named field
                 let call = f.ident.as_ref().unwrap();
                 let name = call.to_string();
-                quote!(.field(#name, &self.#call()))
+                let value = access::generate_field_read(f, call, offset, repr);
+                quote!(.field(#name, &#value))
             });
             quote! {
                 f.debug_struct(#name_str)
@@ -30,9 +35,10 @@ pub(super) fn debug_bits(item: TokenStream) -> TokenStream {
             }
         }
         Fields::Unnamed(fields) => {
-            let calls = fields.unnamed.iter().enumerate().map(|(i, _)| {
+            let calls = fields.unnamed.iter().zip(&field_offsets).enumerate().map(|(i, (f, offset))| {
                 let call: Ident = syn::parse_str(&format!("val_{i}")).unwrap_or_else(unreachable);
-                quote!(.field(&self.#call()))
+                let value = access::generate_field_read(f, &call, offset, repr);
+                quote!(.field(&#value))
             });
             quote! {
                 f.debug_tuple(#name_str)
@@ -43,8 +49,12 @@ pub(super) fn debug_bits(item: TokenStream) -> TokenStream {
         Fields::Unit => todo!("this is a unit struct, which is not supported right now"),
     };
 
+    // every field gets printed, so type parameters need to be `Debug` as well
+    let generics = shared::generics_with_bound(&derive_input.generics, quote!(::core::fmt::Debug));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     quote! {
-        impl ::core::fmt::Debug for #name {
+        impl #impl_generics ::core::fmt::Debug for #name #ty_generics #where_clause {
             fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
                 #fmt_impl
             }
@@ -1,41 +1,284 @@
-use proc_macro2::{Ident, TokenStream};
-use proc_macro_error2::abort_call_site;
-use quote::quote;
-use syn::{Data, DeriveInput, Fields, Type};
+use proc_macro2::{Ident, Literal, TokenStream};
+use proc_macro_error2::{abort, abort_call_site};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{spanned::Spanned, Attribute, Data, DeriveInput, Expr, Field, Generics, Index, Meta, Type, Visibility};
 
-use crate::shared::{self, fallback::Fallback, unreachable, BitSize};
+use crate::{
+    bitsize_internal::is_reserved,
+    shared::{self, fallback::Fallback, options::BitsizeOptions, reserved::ReservedValue, unreachable, BitSize},
+};
 
 pub(crate) fn default_bits(item: TokenStream) -> TokenStream {
     let derive_input = parse(item);
     //TODO: does fallback need handling?
-    let (derive_data, _, name, ..) = analyze(&derive_input);
+    let (derive_data, _, name, bitsize, ..) = analyze(&derive_input);
+    let options = shared::options_from_internal_attr(&derive_input.attrs);
 
     match derive_data {
-        Data::Struct(data) => generate_struct_default_impl(name, &data.fields),
+        Data::Struct(data) => {
+            let DeriveInput { vis, generics, attrs, .. } = &derive_input;
+            let field_defaults: Vec<_> = data.fields.iter().map(FieldDefault::of).collect();
+            if let Some(reset) = reset_value(attrs) {
+                if let Some(attr) = field_defaults.iter().find_map(|field_default| field_default.attr) {
+                    abort!(attr, "`#[reset]` already sets every field"; help = "remove either this `#[default]` or the `#[reset]`")
+                }
+                return generate_reset_impl(name, vis, generics, reset, bitsize);
+            }
+
+            // every default value gets shifted into place from bit 0 upwards
+            let mut fields: Vec<_> = data.fields.iter().zip(field_defaults).collect();
+            if options.msb_first {
+                fields.reverse();
+            }
+            // `Default::default()` isn't const, so it can only be used for fields without `#[default]` if those are `uN` or `bool`
+            let conversion = if !shared::is_generic(generics)
+                && fields
+                    .iter()
+                    .all(|(field, field_default)| is_const_convertible(field, field_default, options))
+            {
+                Conversion::Const
+            } else {
+                Conversion::From {
+                    generic: shared::is_generic(generics),
+                }
+            };
+            let body = if shared::is_wide(bitsize) {
+                generate_wide_struct_default(&fields, conversion)
+            } else {
+                generate_struct_default(&fields, conversion, bitsize)
+            };
+            let init = shared::generate_struct_init(generics);
+            let body = quote! {
+                #body
+                #init
+            };
+
+            match conversion {
+                Conversion::Const => generate_const_default_impl(name, vis, generics, body),
+                Conversion::From { .. } => generate_default_impl(name, generics, body),
+            }
+        }
         Data::Enum(_) => abort_call_site!("use derive(Default) for enums"),
         _ => unreachable(()),
     }
 }
 
-fn generate_struct_default_impl(struct_name: &Ident, fields: &Fields) -> TokenStream {
+/// How the default value of a field gets turned into bits.
+#[derive(Clone, Copy)]
+enum Conversion {
+    /// through `From`, which isn't const. `generic` bitfields can't cast values of unknown type, see [`shared::is_generic`]
+    From { generic: bool },
+    /// with `as` casts and the const conversions of `uN` and bitfields, see [`is_const_convertible`]
+    Const,
+}
+
+/// A field's `#[default = expr]`, or `Default::default()` without one.
+struct FieldDefault<'a> {
+    attr: Option<&'a Attribute>,
+    value: Option<&'a Expr>,
+}
+
+impl FieldDefault<'_> {
+    fn of(field: &Field) -> FieldDefault<'_> {
+        let Some(attr) = field.attrs.iter().find(|attr| shared::is_default_attribute(attr)) else {
+            return FieldDefault { attr: None, value: None };
+        };
+        let Meta::NameValue(name_value) = &attr.meta else {
+            abort!(attr, "default value is missing"; help = "add it like this: `#[default = 3]`")
+        };
+        if field.ident.as_ref().is_some_and(is_reserved) {
+            abort!(attr, "reserved fields can't have a default"; help = "use `#[reserved(value = ..)]` instead")
+        }
+        FieldDefault {
+            attr: Some(attr),
+            value: Some(&name_value.value),
+        }
+    }
+}
+
+/// `impl Default` calling `Default::default()` for every field without `#[default]`.
+fn generate_default_impl(struct_name: &Ident, generics: &Generics, body: TokenStream) -> TokenStream {
+    let generics = shared::generics_with_bound(generics, quote!(::core::default::Default));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::core::default::Default for #struct_name #ty_generics #where_clause {
+            #[allow(unused_parens)]
+            fn default() -> Self {
+                #body
+            }
+        }
+    }
+}
+
+/// `const DEFAULT`, which `impl Default` returns.
+fn generate_const_default_impl(struct_name: &Ident, vis: &Visibility, generics: &Generics, body: TokenStream) -> TokenStream {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            /// The same as `Default::default()`, but usable in const contexts.
+            #[allow(unused_parens)]
+            #vis const DEFAULT: Self = {
+                #body
+            };
+        }
+        impl #impl_generics ::core::default::Default for #struct_name #ty_generics #where_clause {
+            fn default() -> Self {
+                Self::DEFAULT
+            }
+        }
+    }
+}
+
+/// `#[reset = 0x1F]` on the struct, the raw value of `Default` instead of the value of every field.
+fn reset_value(attrs: &[Attribute]) -> Option<&Expr> {
+    let attr = attrs.iter().find(|attr| shared::is_reset_attribute(attr))?;
+    let Meta::NameValue(name_value) = &attr.meta else {
+        abort!(attr, "reset value is missing"; help = "add it like this: `#[reset = 0x1F]`")
+    };
+    Some(&name_value.value)
+}
+
+/// `const DEFAULT` from the raw `reset` value, which gets checked against `TryFromBits` at compile time, see `Bitsized::VALIDITY`.
+///
+/// Fields implementing `Bitsized` by hand have no known validity, so they can't be part of a struct with `#[reset]`.
+fn generate_reset_impl(struct_name: &Ident, vis: &Visibility, generics: &Generics, reset: &Expr, bitsize: BitSize) -> TokenStream {
+    if shared::is_wide(bitsize) {
+        abort!(reset, "`#[reset]` is not supported for bitfields above 128 bits"; help = "use `#[default = ..]` on the fields instead")
+    }
+    if shared::is_generic(generics) {
+        abort!(reset, "`#[reset]` is not supported on generic structs"; help = "use `#[default = ..]` on the fields instead")
+    }
+
+    let (base_int, _) = shared::base_int_of(bitsize);
+    let max = Literal::u128_unsuffixed(u128::MAX >> (shared::MAX_INT_BIT_SIZE - bitsize));
+    let checks = quote_spanned! {reset.span()=>
+        #[allow(clippy::unnecessary_cast)]
+        const _: () = {
+            let value: #base_int = #reset;
+            ::core::assert!(value as u128 <= #max, "reset value doesn't fit into the bitfield");
+            ::core::assert!(
+                <#struct_name as ::bilge::Bitsized>::VALIDITY.is_known(),
+                "reset value can't be checked, since a field implements `TryFrom` by hand"
+            );
+            ::core::assert!(
+                <#struct_name as ::bilge::Bitsized>::VALIDITY.accepts(value as u128),
+                "reset value is not accepted by `TryFrom`"
+            );
+        };
+    };
+    let new_value = shared::generate_const_new(bitsize, quote!(value));
+    let body = quote! {
+        let value: #base_int = #reset;
+        let value = #new_value;
+        Self { value }
+    };
+    let mut expanded = generate_const_default_impl(struct_name, vis, generics, body);
+    expanded.extend(checks);
+    expanded
+}
+
+/// Every default value gets shifted into place and bit-or'ed together.
+fn generate_struct_default(fields: &[(&Field, FieldDefault)], conversion: Conversion, bitsize: BitSize) -> TokenStream {
     let default_value = fields
         .iter()
-        .map(|field| generate_default_inner(&field.ty))
+        .map(
+            |(field, field_default)| match (ReservedValue::of(field), field_default.value, conversion) {
+                // `__BILGE_RESERVED` already holds the required value
+                (Some(_), ..) => skip_field(&field.ty, false),
+                // `uN` and `bool` default to zero
+                (None, None, Conversion::Const) => skip_field(&field.ty, false),
+                (None, None, _) => generate_default_inner(&field.ty, None, conversion, 0),
+                (None, Some(expr), _) => {
+                    let ty = &field.ty;
+                    let inner = generate_default_inner(ty, Some(quote!(default_value)), conversion, 0);
+                    // parenthesized, as a block followed by `|` would be read as a closure
+                    quote! {{
+                        let default_value: #ty = #expr;
+                        (#inner)
+                    }}
+                }
+            },
+        )
         .reduce(|acc, next| quote!(#acc | #next));
 
+    let new_value = match conversion {
+        Conversion::Const => shared::generate_const_new(bitsize, quote!(value)),
+        Conversion::From { .. } => quote!(<Self as Bitsized>::ArbitraryInt::new(value)),
+    };
     quote! {
-        impl ::core::default::Default for #struct_name {
-            fn default() -> Self {
-                let mut offset = 0;
-                let value = #default_value;
-                let value = <#struct_name as Bitsized>::ArbitraryInt::new(value);
-                Self { value }
+        let mut offset = 0;
+        let value = #default_value | Self::__BILGE_RESERVED;
+        let value = #new_value;
+    }
+}
+
+/// Wide bitfields can't be bit-or'ed together, so every default value gets written into the bytes instead.
+fn generate_wide_struct_default(fields: &[(&Field, FieldDefault)], conversion: Conversion) -> TokenStream {
+    let default_writes = fields.iter().map(
+        |(field, field_default)| match (ReservedValue::of(field), field_default.value, conversion) {
+            (Some(_), ..) | (None, None, Conversion::Const) => skip_field(&field.ty, true),
+            (None, None, _) => generate_default_inner_wide(&field.ty, None, conversion, 0),
+            (None, Some(expr), _) => {
+                let ty = &field.ty;
+                let inner = generate_default_inner_wide(ty, Some(quote!(default_value)), conversion, 0);
+                quote! {{
+                    let default_value: #ty = #expr;
+                    #inner
+                }}
             }
+        },
+    );
+
+    quote! {
+        let mut value = Self::__BILGE_RESERVED;
+        let bytes = &mut value;
+        let mut offset = 0;
+        #( #default_writes )*
+    }
+}
+
+/// Moves past a field which keeps the value of `__BILGE_RESERVED`, i.e. its required value or zero.
+fn skip_field(ty: &Type, wide: bool) -> TokenStream {
+    let field_size = shared::generate_type_bitsize(ty);
+    if wide {
+        quote!(offset += #field_size;)
+    } else {
+        quote! {{
+            offset += #field_size;
+            0
+        }}
+    }
+}
+
+/// Whether the field's default value can be converted in const contexts, see [`Conversion::Const`].
+///
+/// A `#[default]` can if its type can, see [`shared::has_const_types`], but without one,
+/// only `uN` and `bool` are zero without calling `Default::default()`.
+fn is_const_convertible(field: &Field, field_default: &FieldDefault, options: BitsizeOptions) -> bool {
+    fn is_const_convertible_type(ty: &Type) -> bool {
+        match ty {
+            Type::Array(array) => is_const_convertible_type(&array.elem),
+            Type::Tuple(tuple) => tuple.elems.iter().all(is_const_convertible_type),
+            _ => shared::is_always_filled(ty),
         }
     }
+    let has_const_default = field_default.value.is_some() && shared::has_const_types(std::iter::once(&field.ty), options.const_fields);
+    ReservedValue::of(field).is_some() || has_const_default || is_const_convertible_type(&field.ty)
 }
 
-fn generate_default_inner(ty: &Type) -> TokenStream {
+/// The element of `source` at the current position, or the type's `Default::default()`.
+fn leaf_value(path: &syn::TypePath, source: &Option<TokenStream>) -> TokenStream {
+    match source {
+        Some(source) => source.clone(),
+        None => quote!(<#path as ::core::default::Default>::default()),
+    }
+}
+
+/// `source` is `None` for `Default::default()` and holds the value of `#[default = ..]` otherwise,
+/// which gets indexed for arrays and tuples. `depth` names the index of nested arrays.
+fn generate_default_inner(ty: &Type, source: Option<TokenStream>, conversion: Conversion, depth: usize) -> TokenStream {
     use Type::*;
     match ty {
         // TODO?: we could optimize nested arrays here like in `struct_gen.rs`
@@ -43,28 +286,54 @@ fn generate_default_inner(ty: &Type) -> TokenStream {
         Array(array) => {
             let len_expr = &array.len;
             let elem_ty = &*array.elem;
+            let index = format_ident!("index_{depth}");
             // generate the default value code for one array element
-            let value_shifted = generate_default_inner(elem_ty);
+            let elem_source = source.map(|source| quote!(#source[#index]));
+            let value_shifted = generate_default_inner(elem_ty, elem_source, conversion, depth + 1);
             quote! {{
                 // constness: iter, array::from_fn, for-loop, range are not const, so we're using while loops
                 let mut acc = 0;
-                let mut i = 0;
-                while i < #len_expr {
+                let mut #index = 0;
+                while #index < #len_expr {
                     // for every element, shift its value into its place
                     let value_shifted = #value_shifted;
                     // and bit-or them together
                     acc |= value_shifted;
-                    i += 1;
+                    #index += 1;
                 }
                 acc
             }}
         }
         Path(path) => {
             let field_size = shared::generate_type_bitsize(ty);
-            // u2::from(HaveFun::default()).value() as u32;
+            let as_base_int = match conversion {
+                Conversion::From { generic: true } => {
+                    let value = leaf_value(path, &source);
+                    quote! {
+                        let as_int = <#path as Bitsized>::ArbitraryInt::from(#value).to_unsigned();
+                        let as_base_int = <<<Self as Bitsized>::ArbitraryInt as Integer>::UnderlyingType as Integer>::masked_new(as_int);
+                    }
+                }
+                Conversion::From { generic: false } => {
+                    let value = leaf_value(path, &source);
+                    // u2::from(HaveFun::default()).value() as u32;
+                    quote! {
+                        let as_int = <#path as Bitsized>::ArbitraryInt::from(#value).value();
+                        let as_base_int = as_int as <<Self as Bitsized>::ArbitraryInt as Integer>::UnderlyingType;
+                    }
+                }
+                Conversion::Const => {
+                    let as_int = match &source {
+                        Some(source) => shared::generate_const_into_bits(ty, source.clone()),
+                        None => quote!(0),
+                    };
+                    quote! {
+                        let as_base_int = #as_int as <<Self as Bitsized>::ArbitraryInt as Integer>::UnderlyingType;
+                    }
+                }
+            };
             quote! {{
-                let as_int = <#path as Bitsized>::ArbitraryInt::from(<#path as ::core::default::Default>::default()).value();
-                let as_base_int = as_int as <<Self as Bitsized>::ArbitraryInt as Integer>::UnderlyingType;
+                #as_base_int
                 let shifted = as_base_int << offset;
                 offset += #field_size;
                 shifted
@@ -74,7 +343,12 @@ fn generate_default_inner(ty: &Type) -> TokenStream {
             tuple
                 .elems
                 .iter()
-                .map(generate_default_inner)
+                .enumerate()
+                .map(|(i, elem)| {
+                    let i = Index::from(i);
+                    let elem_source = source.as_ref().map(|source| quote!(#source.#i));
+                    generate_default_inner(elem, elem_source, conversion, depth)
+                })
                 .reduce(|acc, next| quote!(#acc | #next))
                 // `field: (),` will be handled like this:
                 .unwrap_or_else(|| quote!(0))
@@ -83,6 +357,59 @@ fn generate_default_inner(ty: &Type) -> TokenStream {
     }
 }
 
+fn generate_default_inner_wide(ty: &Type, source: Option<TokenStream>, conversion: Conversion, depth: usize) -> TokenStream {
+    use Type::*;
+    match ty {
+        Array(array) => {
+            let len_expr = &array.len;
+            let elem_ty = &*array.elem;
+            let index = format_ident!("index_{depth}");
+            // generate the default value code for one array element
+            let elem_source = source.map(|source| quote!(#source[#index]));
+            let written = generate_default_inner_wide(elem_ty, elem_source, conversion, depth + 1);
+            quote! {{
+                let mut #index = 0;
+                while #index < #len_expr {
+                    // for every element, write its value into its place
+                    #written
+                    #index += 1;
+                }
+            }}
+        }
+        Path(path) => {
+            let field_size = shared::generate_type_bitsize(ty);
+            let as_int = match conversion {
+                Conversion::From { generic: true } => {
+                    let value = leaf_value(path, &source);
+                    quote!(<#path as Bitsized>::ArbitraryInt::from(#value).to_unsigned().as_u128())
+                }
+                Conversion::From { generic: false } => {
+                    let value = leaf_value(path, &source);
+                    quote!(<#path as Bitsized>::ArbitraryInt::from(#value).value() as u128)
+                }
+                Conversion::Const => match &source {
+                    Some(source) => shared::generate_const_into_bits(ty, source.clone()),
+                    None => quote!(0),
+                },
+            };
+            quote! {{
+                let as_int = #as_int;
+                ::bilge::write_bits(bytes, offset, #field_size, as_int);
+                offset += #field_size;
+            }}
+        }
+        Tuple(tuple) => {
+            let written = tuple.elems.iter().enumerate().map(|(i, elem)| {
+                let i = Index::from(i);
+                let elem_source = source.as_ref().map(|source| quote!(#source.#i));
+                generate_default_inner_wide(elem, elem_source, conversion, depth)
+            });
+            quote! { #( #written )* }
+        }
+        _ => unreachable(()),
+    }
+}
+
 fn parse(item: TokenStream) -> DeriveInput {
     shared::parse_derive(item)
 }
@@ -0,0 +1,285 @@
+use proc_macro2::{Ident, TokenStream};
+use proc_macro_error2::{abort, abort_call_site};
+use quote::{format_ident, quote};
+use syn::{ext::IdentExt, Data, DeriveInput, Expr, Field, Fields, Lit, Type, Visibility};
+
+use crate::{
+    bitsize_internal::is_reserved,
+    shared::{self, unreachable},
+};
+
+/// One flag, which is a `bool` field or one element of a `[bool; N]` field.
+struct Flag {
+    /// `field`, or `field[i]` for arrays
+    name: String,
+    /// the flag's bits within the raw value, as an `u128` expression
+    mask: TokenStream,
+}
+
+pub(crate) fn flags_bits(item: TokenStream) -> TokenStream {
+    let derive_input = shared::parse_derive(item);
+    let DeriveInput {
+        vis,
+        ident,
+        generics,
+        attrs,
+        data,
+        ..
+    } = &derive_input;
+    let (bitsize, _) = shared::bitsize_from_internal_attr(attrs);
+    let options = shared::options_from_internal_attr(attrs);
+    let fields = match data {
+        Data::Struct(data) => &data.fields,
+        Data::Enum(_) => abort_call_site!("FlagsBits is only supported on structs"; help = "use a struct of `bool` fields instead"),
+        Data::Union(_) => unreachable(()),
+    };
+    if !matches!(fields, Fields::Named(_)) {
+        abort_call_site!("FlagsBits needs named fields"; help = "the names of the fields are the names of the flags")
+    }
+    if shared::is_wide(bitsize) {
+        abort_call_site!("FlagsBits is not supported for bitfields above 128 bits"; help = "split the flags into multiple bitfields")
+    }
+    if shared::is_generic(generics) {
+        abort_call_site!("FlagsBits is not supported on generic structs"; help = "flags are `bool` fields, so they don't need type parameters")
+    }
+
+    let field_offsets = shared::generate_field_offsets(fields, options.msb_first);
+    let mut flags = vec![];
+    let mut field_consts = vec![];
+    for (field, offset) in fields.iter().zip(&field_offsets) {
+        let name = field.ident.as_ref().unwrap_or_else(|| unreachable(()));
+        // reserved fields aren't flags, so their bits are masked out of every operation
+        if is_reserved(name) {
+            continue;
+        }
+        check_const_name(field, name, fields);
+        let field_flags = flags_of(field, name, offset);
+        field_consts.push(generate_field_const(field, name, &field_flags));
+        flags.extend(field_flags);
+    }
+
+    let flag_names = flags.iter().map(|flag| &flag.name);
+    let flag_masks: Vec<_> = flags.iter().map(|flag| &flag.mask).collect();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let mut expanded = quote! {
+        impl #impl_generics #ident #ty_generics #where_clause {
+            /// The bits of all flags within the raw value, which leaves out reserved fields.
+            const __BILGE_FLAGS: u128 = 0 #( | #flag_masks )*;
+
+            #( #field_consts )*
+
+            /// Every flag with its name, in the order of the fields.
+            #vis const FLAGS: &'static [(&'static str, Self)] = &[
+                #( (#flag_names, Self::__bilge_from_flags(#flag_masks)), )*
+            ];
+
+            /// Sets the flags to `flags`, keeping reserved fields as they are.
+            const fn __bilge_with_flags(&self, flags: u128) -> Self {
+                let reserved = self.__bilge_into_bits() & !Self::__BILGE_FLAGS;
+                Self::__bilge_from_bits(reserved | (flags & Self::__BILGE_FLAGS))
+            }
+
+            /// `flags` with the required value of reserved fields.
+            const fn __bilge_from_flags(flags: u128) -> Self {
+                Self::__bilge_from_bits(Self::__BILGE_RESERVED as u128 | (flags & Self::__BILGE_FLAGS))
+            }
+
+            const fn __bilge_flags(&self) -> u128 {
+                self.__bilge_into_bits() & Self::__BILGE_FLAGS
+            }
+
+            /// No flags set.
+            #vis const fn empty() -> Self {
+                Self::__bilge_from_flags(0)
+            }
+
+            /// Every flag set.
+            #vis const fn all() -> Self {
+                Self::__bilge_from_flags(Self::__BILGE_FLAGS)
+            }
+
+            /// Whether no flag is set.
+            #vis const fn is_empty(&self) -> bool {
+                self.__bilge_flags() == 0
+            }
+
+            /// Whether every flag is set.
+            #vis const fn is_all(&self) -> bool {
+                self.__bilge_flags() == Self::__BILGE_FLAGS
+            }
+
+            /// Whether every flag of `other` is set in `self`.
+            #vis const fn contains(&self, other: Self) -> bool {
+                self.__bilge_flags() & other.__bilge_flags() == other.__bilge_flags()
+            }
+
+            /// Whether any flag of `other` is set in `self`.
+            #vis const fn intersects(&self, other: Self) -> bool {
+                self.__bilge_flags() & other.__bilge_flags() != 0
+            }
+
+            /// Sets the flags of `other`.
+            #vis const fn insert(&mut self, other: Self) {
+                *self = self.union(other);
+            }
+
+            /// Clears the flags of `other`.
+            #vis const fn remove(&mut self, other: Self) {
+                *self = self.difference(other);
+            }
+
+            /// Flips the flags of `other`.
+            #vis const fn toggle(&mut self, other: Self) {
+                *self = self.symmetric_difference(other);
+            }
+
+            /// The flags set in either `self` or `other`, like `self | other`.
+            #[must_use]
+            #vis const fn union(&self, other: Self) -> Self {
+                self.__bilge_with_flags(self.__bilge_flags() | other.__bilge_flags())
+            }
+
+            /// The flags set in both `self` and `other`, like `self & other`.
+            #[must_use]
+            #vis const fn intersection(&self, other: Self) -> Self {
+                self.__bilge_with_flags(self.__bilge_flags() & other.__bilge_flags())
+            }
+
+            /// The flags set in `self`, but not in `other`, like `self - other`.
+            #[must_use]
+            #vis const fn difference(&self, other: Self) -> Self {
+                self.__bilge_with_flags(self.__bilge_flags() & !other.__bilge_flags())
+            }
+
+            /// The flags set in either `self` or `other`, but not in both, like `self ^ other`.
+            #[must_use]
+            #vis const fn symmetric_difference(&self, other: Self) -> Self {
+                self.__bilge_with_flags(self.__bilge_flags() ^ other.__bilge_flags())
+            }
+
+            /// The flags not set in `self`, like `!self`.
+            #[must_use]
+            #vis const fn complement(&self) -> Self {
+                self.__bilge_with_flags(!self.__bilge_flags())
+            }
+
+            /// The names of the set flags, in the order of the fields.
+            #vis fn iter_names(&self) -> impl ::core::iter::Iterator<Item = &'static str> {
+                let flags = self.__bilge_flags();
+                Self::FLAGS
+                    .iter()
+                    .filter(move |(_, flag)| flags & flag.__bilge_flags() != 0)
+                    .map(|(name, _)| *name)
+            }
+        }
+
+        impl #impl_generics ::core::ops::Not for #ident #ty_generics #where_clause {
+            type Output = Self;
+            fn not(self) -> Self {
+                self.complement()
+            }
+        }
+    };
+
+    let ops = [
+        (quote!(BitOr), quote!(bitor), quote!(BitOrAssign), quote!(bitor_assign), quote!(union)),
+        (
+            quote!(BitAnd),
+            quote!(bitand),
+            quote!(BitAndAssign),
+            quote!(bitand_assign),
+            quote!(intersection),
+        ),
+        (
+            quote!(BitXor),
+            quote!(bitxor),
+            quote!(BitXorAssign),
+            quote!(bitxor_assign),
+            quote!(symmetric_difference),
+        ),
+        (quote!(Sub), quote!(sub), quote!(SubAssign), quote!(sub_assign), quote!(difference)),
+    ];
+    for (op, op_fn, assign_op, assign_op_fn, method) in ops {
+        expanded.extend(quote! {
+            impl #impl_generics ::core::ops::#op for #ident #ty_generics #where_clause {
+                type Output = Self;
+                fn #op_fn(self, other: Self) -> Self {
+                    self.#method(other)
+                }
+            }
+
+            impl #impl_generics ::core::ops::#assign_op for #ident #ty_generics #where_clause {
+                fn #assign_op_fn(&mut self, other: Self) {
+                    *self = self.#method(other);
+                }
+            }
+        });
+    }
+    expanded
+}
+
+/// A `bool` is one flag, a `[bool; N]` is one flag per element, starting from its lowest bit.
+fn flags_of(field: &Field, name: &Ident, offset: &TokenStream) -> Vec<Flag> {
+    let name = name.unraw().to_string();
+    let help = "FlagsBits needs every field besides reserved ones to be a `bool` or a `[bool; N]`";
+    match &field.ty {
+        ty if is_bool(ty) => vec![Flag {
+            name,
+            mask: quote!((1u128 << (#offset))),
+        }],
+        Type::Array(array) if is_bool(&array.elem) => {
+            // the names are `&'static str`, so they need to be known here
+            let Expr::Lit(syn::ExprLit { lit: Lit::Int(len), .. }) = &array.len else {
+                abort!(array.len, "FlagsBits needs array lengths to be integer literals"; help = "write the length of this array as a number")
+            };
+            let len: usize = len.base10_parse().unwrap_or_else(|_| abort!(len, "array length is invalid"));
+            (0..len)
+                .map(|i| Flag {
+                    name: format!("{name}[{i}]"),
+                    mask: quote!((1u128 << (#offset + #i))),
+                })
+                .collect()
+        }
+        ty => abort!(ty, "field type is not a flag"; help = help),
+    }
+}
+
+/// `FIELD: Self`, the field's flags set and nothing else, like the flag constants of `bitflags`.
+fn generate_field_const(field: &Field, name: &Ident, flags: &[Flag]) -> TokenStream {
+    let vis: &Visibility = &field.vis;
+    let const_name = format_ident!("{}", name.unraw().to_string().to_uppercase());
+    let masks = flags.iter().map(|flag| &flag.mask);
+    let doc = if let Type::Array(_) = field.ty {
+        format!(" Every element of `{name}` set and nothing else.")
+    } else {
+        format!(" `{name}` set and nothing else.")
+    };
+    quote! {
+        #[doc = #doc]
+        #vis const #const_name: Self = Self::__bilge_from_flags(0 #( | #masks )*);
+    }
+}
+
+/// The field's const would clash with another generated const, like `FLAGS` or `DefaultBits`' `DEFAULT`,
+/// or the `FIELD_MASK` and friends of another field, see `bitsize_internal`.
+fn check_const_name(field: &Field, name: &Ident, fields: &Fields) {
+    let const_name = name.unraw().to_string().to_uppercase();
+    let clashes_with_field_const = fields.iter().filter_map(|other| other.ident.as_ref()).any(|other| {
+        let other = other.unraw().to_string().to_uppercase();
+        ["OFFSET", "BITS", "MASK", "STRIDE"]
+            .iter()
+            .any(|suffix| const_name == format!("{other}_{suffix}"))
+    });
+    if ["FLAGS", "DEFAULT"].contains(&const_name.as_str()) || clashes_with_field_const {
+        abort!(
+            field.ident,
+            "the flag constant `{}` is already generated", const_name;
+            help = "rename this field, since FlagsBits names the constant of each flag after its field"
+        )
+    }
+}
+
+fn is_bool(ty: &Type) -> bool {
+    matches!(ty, Type::Path(path) if path.path.is_ident("bool"))
+}
@@ -1,23 +1,56 @@
 use proc_macro2::{Ident, TokenStream};
+use proc_macro_error2::abort_call_site;
 use quote::quote;
-use syn::{punctuated::Iter, Data, DeriveInput, Fields, Variant};
+use syn::{punctuated::Iter, Data, DeriveInput, Field, Generics, Variant};
 
-use crate::shared::{self, discriminant_assigner::DiscriminantAssigner, fallback::Fallback, unreachable, BitSize};
+use crate::shared::{self, discriminant_assigner::DiscriminantAssigner, fallback::Fallback, tagged::Tagged, unreachable, BitSize};
 
 pub(crate) fn binary(item: TokenStream) -> TokenStream {
     let derive_input = parse(item);
     let (derive_data, arb_int, name, bitsize, fallback) = analyze(&derive_input);
+    let options = shared::options_from_internal_attr(&derive_input.attrs);
 
     match derive_data {
-        Data::Struct(data) => generate_struct_binary_impl(name, &data.fields),
-        Data::Enum(data) => generate_enum_binary_impl(name, data.variants.iter(), arb_int, bitsize, fallback),
+        Data::Struct(data) => {
+            let fields = shared::fields_in_layout_order(&data.fields, options.msb_first);
+            generate_struct_binary_impl(name, &derive_input.generics, &fields, shared::is_wide(bitsize))
+        }
+        Data::Enum(data) => {
+            if Tagged::analyze(data.variants.iter(), bitsize, options).is_some() {
+                abort_call_site!("BinaryBits is not supported on tagged enums"; help = "format `uN::from(enum_value)` instead")
+            }
+            generate_enum_binary_impl(name, data.variants.iter(), arb_int, bitsize, fallback)
+        }
         _ => unreachable(()),
     }
 }
 
-fn generate_struct_binary_impl(struct_name: &Ident, fields: &Fields) -> TokenStream {
+/// `fields` need to be in layout order, so we can print them from most significant to least significant.
+fn generate_struct_binary_impl(struct_name: &Ident, generics: &Generics, fields: &[&Field], wide: bool) -> TokenStream {
     let write_underscore = quote! { write!(f, "_")?; };
 
+    // wide bitfields read their fields out of the bytes, the others mask them out of the value
+    let (mask, write_field) = if wide {
+        let write_field = quote! {
+            // fields of wide bitfields can be bigger than an u128, so we print them in chunks, most significant first
+            let mut remaining = field_size;
+            while remaining > 0 {
+                let chunk_size = if remaining > 128 { 128 } else { remaining };
+                remaining -= chunk_size;
+                let extracted = ::bilge::read_bits(&self.value, first_bit_pos + remaining, chunk_size);
+                write!(f, "{:0width$b}", extracted, width = chunk_size)?;
+            }
+        };
+        (quote!(), write_field)
+    } else {
+        let write_field = quote! {
+            let field_mask = mask >> (struct_size - field_size);
+            let extracted = field_mask & (self.value >> first_bit_pos);
+            write!(f, "{:0width$b}", extracted, width = field_size)?;
+        };
+        (quote!(let mask = <Self as Bitsized>::MAX;), write_field)
+    };
+
     // fields are printed from most significant to least significant, separated by an underscore
     let writes = fields
         .iter()
@@ -28,21 +61,21 @@ fn generate_struct_binary_impl(struct_name: &Ident, fields: &Fields) -> TokenStr
             // `extracted` is `field_size` bits of `value`, starting from index `first_bit_pos` (counting from LSB)
             quote! {
                 let field_size = #field_size;
-                let field_mask = mask >> (struct_size - field_size);
                 let first_bit_pos = last_bit_pos - field_size;
                 last_bit_pos -= field_size;
-                let extracted = field_mask & (self.value >> first_bit_pos);
-                write!(f, "{:0width$b}", extracted, width = field_size)?;
+                #write_field
             }
         })
         .reduce(|acc, next| quote!(#acc #write_underscore #next));
 
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     quote! {
-        impl ::core::fmt::Binary for #struct_name {
+        impl #impl_generics ::core::fmt::Binary for #struct_name #ty_generics #where_clause {
             fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
-                let struct_size = <#struct_name as Bitsized>::BITS;
+                let struct_size = <Self as Bitsized>::BITS;
                 let mut last_bit_pos = struct_size;
-                let mask = <#struct_name as Bitsized>::MAX;
+                #mask
                 #writes
                 Ok(())
             }
@@ -87,7 +120,7 @@ fn generate_to_int_match_arms(
         }
     };
 
-    let mut assigner = DiscriminantAssigner::new(bitsize);
+    let mut assigner = DiscriminantAssigner::new(bitsize, enum_name);
 
     variants
         .map(|variant| {
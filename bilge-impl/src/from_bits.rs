@@ -2,23 +2,33 @@ use itertools::Itertools;
 use proc_macro2::{Ident, TokenStream};
 use proc_macro_error2::{abort, abort_call_site};
 use quote::quote;
-use syn::{punctuated::Iter, Data, DeriveInput, Fields, Type, Variant};
+use syn::{punctuated::Iter, Data, DeriveInput, Fields, Generics, Type, Variant};
 
-use crate::shared::{self, discriminant_assigner::DiscriminantAssigner, enum_fills_bitsize, fallback::Fallback, unreachable, BitSize};
+use crate::shared::{
+    self, byte_conversions, discriminant_assigner::DiscriminantAssigner, enum_fills_bitsize, fallback::Fallback, tagged::Tagged, unreachable, BitSize,
+};
 
 pub(super) fn from_bits(item: TokenStream) -> TokenStream {
     let derive_input = parse(item);
     let (derive_data, arb_int, name, internal_bitsize, fallback) = analyze(&derive_input);
     let expanded = match &derive_data {
-        Data::Struct(struct_data) => generate_struct(arb_int, name, &struct_data.fields),
+        Data::Struct(struct_data) => generate_struct(arb_int, name, &derive_input.generics, &struct_data.fields),
         Data::Enum(enum_data) => {
-            let variants = enum_data.variants.iter();
-            let match_arms = analyze_enum(variants, name, internal_bitsize, fallback.as_ref(), &arb_int);
-            generate_enum(arb_int, name, match_arms, fallback)
+            let options = shared::options_from_internal_attr(&derive_input.attrs);
+            if let Some(tagged) = Tagged::analyze(enum_data.variants.iter(), internal_bitsize, options) {
+                analyze_tagged_enum(&tagged, fallback.as_ref());
+                tagged.generate_conversions(name, &arb_int, fallback.as_ref(), false)
+            } else {
+                let variants = enum_data.variants.iter();
+                let match_arms = analyze_enum(variants, name, internal_bitsize, fallback.as_ref(), &arb_int);
+                generate_enum(arb_int, name, match_arms, fallback)
+            }
         }
         _ => unreachable(()),
     };
-    generate_common(expanded)
+    let is_const = shared::has_const_conversions(&derive_input, internal_bitsize);
+    let byte_conversions = byte_conversions::generate_byte_conversions(name, &derive_input.generics, internal_bitsize, false, is_const);
+    generate_common(quote!(#expanded #byte_conversions))
 }
 
 fn parse(item: TokenStream) -> DeriveInput {
@@ -34,16 +44,18 @@ fn analyze_enum(
 ) -> (Vec<TokenStream>, Vec<TokenStream>) {
     validate_enum_variants(variants.clone(), fallback);
 
-    let enum_is_filled = enum_fills_bitsize(internal_bitsize, variants.len());
+    // a fallback with value reuses the values of the missing variants, instead of taking one of its own
+    let value_count = variants.len() - usize::from(matches!(fallback, Some(Fallback::WithValue(_))));
+    let enum_is_filled = enum_fills_bitsize(internal_bitsize, value_count);
     if !enum_is_filled && fallback.is_none() {
         abort_call_site!("enum doesn't fill its bitsize"; help = "you need to use `#[derive(TryFromBits)]` instead, or specify one of the variants as #[fallback]")
     }
     if enum_is_filled && fallback.is_some() {
         // NOTE: I've shortly tried pointing to `#[fallback]` here but it wasn't easy enough
-        abort_call_site!("enum already has {} variants", variants.len(); help = "remove the `#[fallback]` attribute")
+        abort_call_site!("enum already has {} variants", value_count; help = "remove the `#[fallback]` attribute")
     }
 
-    let mut assigner = DiscriminantAssigner::new(internal_bitsize);
+    let mut assigner = DiscriminantAssigner::new(internal_bitsize, name);
 
     let is_fallback = |variant_name| {
         if let Some(Fallback::Unit(name) | Fallback::WithValue(name)) = fallback {
@@ -84,6 +96,16 @@ fn analyze_enum(
         .unzip()
 }
 
+fn analyze_tagged_enum(tagged: &Tagged, fallback: Option<&Fallback>) {
+    let covers_all_tags = tagged.covers_all_tags();
+    if !covers_all_tags && fallback.is_none() {
+        abort_call_site!("enum doesn't have a variant for every tag"; help = "you need to use `#[derive(TryFromBits)]` instead, or add a fallback like `#[fallback] Unknown(uN)`")
+    }
+    if covers_all_tags && fallback.is_some() {
+        abort_call_site!("enum already has a variant for every tag"; help = "remove the `#[fallback]` attribute")
+    }
+}
+
 fn generate_enum(
     arb_int: TokenStream, enum_type: &Ident, match_arms: (Vec<TokenStream>, Vec<TokenStream>), fallback: Option<Fallback>,
 ) -> TokenStream {
@@ -124,7 +146,7 @@ fn generate_enum(
 /// such a type can then safely implement `From<uN>`.
 /// a filled type automatically implements the trait `Filled` thanks to a blanket impl.
 /// the check generated by this function will prevent compilation if `ty` is not `Filled`.
-fn generate_filled_check_for(ty: &Type, vec: &mut Vec<TokenStream>) {
+pub(crate) fn generate_filled_check_for(ty: &Type, vec: &mut Vec<TokenStream>) {
     use Type::*;
     match ty {
         Path(_) => {
@@ -141,7 +163,7 @@ fn generate_filled_check_for(ty: &Type, vec: &mut Vec<TokenStream>) {
     }
 }
 
-fn generate_struct(arb_int: TokenStream, struct_type: &Ident, fields: &Fields) -> TokenStream {
+fn generate_struct(arb_int: TokenStream, struct_type: &Ident, generics: &Generics, fields: &Fields) -> TokenStream {
     let const_ = if cfg!(feature = "nightly") { quote!(const) } else { quote!() };
 
     let mut assumes = Vec::new();
@@ -152,15 +174,27 @@ fn generate_struct(arb_int: TokenStream, struct_type: &Ident, fields: &Fields) -
     // a single check per type is enough, so the checks can be deduped
     let assumes = assumes.into_iter().unique_by(TokenStream::to_string);
 
+    let init = shared::generate_struct_init(generics);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // a generic bitfield is only filled if its type parameters are
+    let filled_generics = shared::generics_with_bound(generics, quote!(::bilge::Filled));
+    let (filled_impl_generics, _, filled_where_clause) = filled_generics.split_for_impl();
+
     quote! {
-        impl #const_ ::core::convert::From<#arb_int> for #struct_type {
-            fn from(value: #arb_int) -> Self {
+        impl #filled_impl_generics #struct_type #ty_generics #filled_where_clause {
+            /// Creates this bitfield from its raw value, like `From`, but usable in const contexts.
+            pub const fn from_raw(value: #arb_int) -> Self {
                 #( #assumes )*
-                Self { value }
+                #init
+            }
+        }
+        impl #filled_impl_generics #const_ ::core::convert::From<#arb_int> for #struct_type #ty_generics #filled_where_clause {
+            fn from(value: #arb_int) -> Self {
+                Self::from_raw(value)
             }
         }
-        impl #const_ ::core::convert::From<#struct_type> for #arb_int {
-            fn from(value: #struct_type) -> Self {
+        impl #impl_generics #const_ ::core::convert::From<#struct_type #ty_generics> for #arb_int #where_clause {
+            fn from(value: #struct_type #ty_generics) -> Self {
                 value.value
             }
         }
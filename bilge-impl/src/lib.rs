@@ -5,8 +5,10 @@ mod bitsize;
 mod bitsize_internal;
 mod debug_bits;
 mod default_bits;
+mod flags_bits;
 mod fmt_bits;
 mod from_bits;
+mod register_block;
 #[cfg(feature = "serde")]
 #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
 mod serde_bits;
@@ -17,15 +19,58 @@ mod shared;
 /// Defines the bitsize of a struct or an enum.
 ///
 /// e.g. `#[bitsize(4)]` represents the item as a u4, which is UInt<u8, 4> underneath.
-/// The size of structs is currently limited to 128 bits.
-/// The size of enums is limited to 64 bits.
+/// Structs above 128 bits are represented as a byte array instead, e.g. `#[bitsize(256)]` as `[u8; 32]`,
+/// so their size needs to be a multiple of 8. The size of structs is currently limited to 4096 bits.
+/// The size of enums is limited to 128 bits. Enums above 64 bits lose their explicit discriminants,
+/// so their values are only available through `uN::from(enum_value)`, not through `as` casts.
+/// Discriminants can be constant expressions like `BASE + 3` or negative, like `-2` (stored as two's complement).
+/// Enums using constant expressions lose their explicit discriminants as well.
 /// Please open an issue if you have a usecase for bigger bitfields.
+///
+/// Structs get a constructor `new`, a getter `field()`, a setter `set_field(value)` and a chainable `with_field(value)` per field,
+/// and a `builder()` whose `build()` can only be called once every field is set.
+/// Their position is described by the constants `FIELD_OFFSET`, `FIELD_BITS`, `FIELD_MASK` and, for arrays, `FIELD_STRIDE`.
+///
+/// Struct fields can optionally be given their bit range, like `#[bits(4..=7)]` or `#[bit(3)]`.
+/// Any gaps between these ranges are filled with `reserved` fields.
+///
+/// Struct fields marked with `#[alias(of = "field")]` are alternative views over the bits starting at `field`.
+///
+/// Struct fields can be given an access policy, `#[access(rw|ro|wo|w1c|rc)]`, deciding which accessors they get.
+/// Write-1-to-clear fields get `clear_field()` instead of a setter.
+///
+/// `#[bitsize(8, sealed)]` makes the inner value of a struct unreachable from outside the generated code,
+/// leaving only `to_raw()` and `unsafe from_raw_unchecked()` for raw access.
+///
+/// `#[bitsize(32, msb_first)]` lays the fields out from the most significant bit down, like network protocol diagrams.
+///
+/// `#[bitsize(32, const_fields)]` promises that every field type besides integers and `bool` is a `#[bitsize]` item,
+/// so the accessors can be `const fn` without going through `From`. Without it, only bitfields of integers and `bool` get those:
+/// a field holding a nested bitfield or an enum makes every accessor non-const, even if that type uses `#[bitsize]` itself,
+/// since it can't be told apart from a type implementing `Bitsized` by hand.
+///
+/// `#[bitsize(32, tag_bits = 4)]` on an enum makes it a tagged enum, whose variants are selected by
+/// a `#[tag = 0b0011]` and can hold a bitfield as payload in the remaining bits, like `Load(LoadFields)`.
+///
+/// Structs can have type and const parameters, like `struct Entry<T> { flag: bool, payload: T }`.
+/// Their size is checked once they are instantiated, e.g. `Entry::<u4>::new(..)` with `#[bitsize(5)]`.
 #[proc_macro_error]
 #[proc_macro_attribute]
 pub fn bitsize(args: TokenStream, item: TokenStream) -> TokenStream {
     bitsize::bitsize(args.into(), item.into()).into()
 }
 
+/// Lays out a peripheral's registers at their byte offsets, like `#[offset(0x10)] status: RoReg<Status>`.
+///
+/// Registers are `bilge::mmio::{Reg, RoReg, WoReg}`. The struct becomes `#[repr(C)]`, with padding between them,
+/// and gets an accessor per register, like `status() -> RoReg<Status>`, and `unsafe from_ptr(base)`.
+/// Overlapping and misaligned registers are rejected at compile time.
+#[proc_macro_error]
+#[proc_macro_attribute]
+pub fn register_block(args: TokenStream, item: TokenStream) -> TokenStream {
+    register_block::register_block(args.into(), item.into()).into()
+}
+
 /// This is internally used, not to be used by anything besides `bitsize`.
 /// No guarantees are given.
 #[proc_macro_error]
@@ -38,8 +83,11 @@ pub fn bitsize_internal(args: TokenStream, item: TokenStream) -> TokenStream {
 ///
 /// This should be used when your enum or enums nested in
 /// a struct don't fill their given `bitsize`.
+///
+/// Also generates fallible `from_le_bytes`/`from_be_bytes`, `to_le_bytes`/`to_be_bytes`
+/// and `try_from_le_slice`/`try_from_be_slice`.
 #[proc_macro_error]
-#[proc_macro_derive(TryFromBits, attributes(bitsize_internal, fallback))]
+#[proc_macro_derive(TryFromBits, attributes(bitsize_internal, fallback, tag))]
 pub fn derive_try_from_bits(item: TokenStream) -> TokenStream {
     try_from_bits::try_from_bits(item.into()).into()
 }
@@ -49,8 +97,11 @@ pub fn derive_try_from_bits(item: TokenStream) -> TokenStream {
 /// This should be used when your enum or enums nested in
 /// a struct fill their given `bitsize` or if you're not
 /// using enums.
+///
+/// Also generates `from_le_bytes`/`from_be_bytes`, `to_le_bytes`/`to_be_bytes`
+/// and `try_from_le_slice`/`try_from_be_slice`.
 #[proc_macro_error]
-#[proc_macro_derive(FromBits, attributes(bitsize_internal, fallback))]
+#[proc_macro_derive(FromBits, attributes(bitsize_internal, fallback, tag))]
 pub fn derive_from_bits(item: TokenStream) -> TokenStream {
     from_bits::from_bits(item.into()).into()
 }
@@ -72,28 +123,50 @@ pub fn derive_binary_bits(item: TokenStream) -> TokenStream {
 }
 
 /// Generate an `impl core::default::Default` for bitfield structs.
+///
+/// `#[reset = ..]` on the struct is checked against `TryFrom` at compile time, which needs every field to be made with
+/// `#[bitsize]`, an integer or a `bool`.
 #[proc_macro_error]
 #[proc_macro_derive(DefaultBits)]
 pub fn derive_default_bits(item: TokenStream) -> TokenStream {
     default_bits::default_bits(item.into()).into()
 }
 
+/// Generate `bitflags`-like set operations for bitfield structs made of `bool` and `[bool; N]` fields.
+///
+/// This includes `|`, `&`, `^`, `-`, `!`, `contains`, `insert`, `remove`, `toggle`, `empty()`, `all()`,
+/// a constant per field like `READ`, and `iter_names()` over the names of the set flags.
+/// Reserved fields aren't flags, so they keep their value.
+#[proc_macro_error]
+#[proc_macro_derive(FlagsBits)]
+pub fn derive_flags_bits(item: TokenStream) -> TokenStream {
+    flags_bits::flags_bits(item.into()).into()
+}
+
 /// Generate an `impl serde::Serialize` for bitfield structs.
 ///
+/// `#[serde_bits(repr = "int")]` serializes the raw value instead of the fields,
+/// and `#[serde_bits(repr = "bits")]` a binary string of it, like `"0b1010_0011"`.
+///
+/// Fields understand `#[serde(rename = "..", skip, default)]`, and the struct `#[serde(rename = "..", rename_all = "..", default)]`.
+///
 /// Please use normal #[derive(Serialize)] for enums.
 #[cfg(feature = "serde")]
 #[proc_macro_error]
-#[proc_macro_derive(SerializeBits, attributes(bitsize_internal))]
+#[proc_macro_derive(SerializeBits, attributes(bitsize_internal, serde_bits, serde))]
 pub fn serialize_bits(item: TokenStream) -> TokenStream {
     serde_bits::serialize_bits(item.into()).into()
 }
 
 /// Generate an `impl serde::Deserialize` for bitfield structs.
 ///
+/// With `#[serde_bits(repr = "int")]` or `#[serde_bits(repr = "bits")]`, the raw value is converted with
+/// `From` or `TryFrom`, so invalid values are rejected with a serde error.
+///
 /// Please use normal #[derive(Deserialize)] for enums.
 #[cfg(feature = "serde")]
 #[proc_macro_error]
-#[proc_macro_derive(DeserializeBits, attributes(bitsize_internal))]
+#[proc_macro_derive(DeserializeBits, attributes(bitsize_internal, serde_bits, serde))]
 pub fn deserialize_bits(item: TokenStream) -> TokenStream {
     serde_bits::deserialize_bits(item.into()).into()
 }
@@ -0,0 +1,160 @@
+use proc_macro2::{Ident, Literal, TokenStream};
+use proc_macro_error2::{abort, abort_call_site};
+use quote::{format_ident, quote, quote_spanned};
+use syn::{spanned::Spanned, Attribute, Fields, ItemStruct, Meta, Type, Visibility};
+
+use crate::shared::unreachable;
+
+/// A register at `#[offset(0x10)]` within its block.
+struct Register<'a> {
+    attrs: Vec<&'a Attribute>,
+    vis: &'a Visibility,
+    name: &'a Ident,
+    ty: &'a Type,
+    offset: usize,
+}
+
+/// Turns
+/// ```ignore
+/// #[register_block]
+/// struct Uart {
+///     #[offset(0x0)]
+///     control: Reg<Control>,
+///     #[offset(0x8)]
+///     status: RoReg<Status>,
+/// }
+/// ```
+/// into a `#[repr(C)]` struct storing each register's raw integer at its offset, with padding in between,
+/// and an accessor per register, like `control() -> Reg<Control>`.
+pub(super) fn register_block(args: TokenStream, item: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        abort!(args, "`register_block` doesn't take any arguments")
+    }
+    let item = parse(item);
+    let registers = analyze(&item);
+    generate(&item, &registers)
+}
+
+fn parse(item: TokenStream) -> ItemStruct {
+    syn::parse2(item).unwrap_or_else(|_| abort_call_site!("`register_block` can only be used on structs"))
+}
+
+fn analyze(item: &ItemStruct) -> Vec<Register<'_>> {
+    if !item.generics.params.is_empty() {
+        abort!(item.generics, "register blocks can't be generic")
+    }
+    let Fields::Named(fields) = &item.fields else {
+        abort!(item, "register blocks need named fields"; help = "name every register, like `control: Reg<Control>`")
+    };
+    if fields.named.is_empty() {
+        abort!(item, "register blocks need at least one register")
+    }
+
+    let mut registers: Vec<Register> = fields
+        .named
+        .iter()
+        .map(|field| {
+            let mut offset_attrs = field.attrs.iter().filter(|attr| is_offset_attribute(attr));
+            let Some(attr) = offset_attrs.next() else {
+                abort!(field, "register has no offset"; help = "add one like this: `#[offset(0x10)]`")
+            };
+            if let Some(duplicate) = offset_attrs.next() {
+                abort!(duplicate, "only one offset per register is allowed")
+            }
+            Register {
+                attrs: field.attrs.iter().filter(|attr| !is_offset_attribute(attr)).collect(),
+                vis: &field.vis,
+                name: field.ident.as_ref().unwrap_or_else(|| unreachable(())),
+                ty: &field.ty,
+                offset: offset_value(attr),
+            }
+        })
+        .collect();
+
+    // the struct stores registers in memory order
+    registers.sort_by_key(|register| register.offset);
+    for pair in registers.windows(2) {
+        if pair[0].offset == pair[1].offset {
+            abort!(pair[1].name, "`{}` has the same offset as `{}`", pair[1].name, pair[0].name)
+        }
+    }
+    registers
+}
+
+fn generate(item: &ItemStruct, registers: &[Register]) -> TokenStream {
+    let ItemStruct { attrs, vis, ident, .. } = item;
+    let raw_type = |ty: &Type| quote!(::bilge::mmio::RawOf<<#ty as ::bilge::mmio::Register>::Bitfield>);
+
+    let mut fields = vec![];
+    let mut checks = vec![];
+    // where the previous register ends, as a const expression
+    let mut end = quote!(0);
+    for (i, Register { name, ty, offset, .. }) in registers.iter().enumerate() {
+        let raw = raw_type(ty);
+        let offset_lit = Literal::usize_unsuffixed(*offset);
+
+        if let Some(previous) = i.checked_sub(1).map(|i| &registers[i]) {
+            let message = format!("`{}` overlaps `{}` at offset {:#x}", previous.name, name, offset);
+            checks.push(quote_spanned! {ty.span()=> ::core::assert!(#end <= #offset_lit, #message); });
+        }
+        let message = format!("`{}` at offset {:#x} isn't aligned to the size of its register", name, offset);
+        checks.push(quote_spanned! {ty.span()=> ::core::assert!(#offset_lit % ::core::mem::align_of::<#raw>() == 0, #message); });
+
+        // overlaps are reported by the check above, so the padding only needs to avoid underflowing
+        let padding = format_ident!("__reserved_{}", i);
+        fields.push(quote!(#[allow(dead_code)] #padding: [u8; (#offset_lit as usize).saturating_sub(#end)],));
+        fields.push(quote!(#name: ::core::cell::UnsafeCell<#raw>,));
+        end = quote!(#offset_lit + ::core::mem::size_of::<#raw>());
+    }
+
+    let accessors = registers.iter().map(|Register { attrs, vis, name, ty, .. }| {
+        quote! {
+            #(#attrs)*
+            #vis const fn #name(&self) -> #ty {
+                // SAFETY: `from_ptr` guarantees that the whole block is valid
+                unsafe { <#ty>::new(self.#name.get()) }
+            }
+        }
+    });
+
+    quote! {
+        #(#attrs)*
+        #[repr(C)]
+        #vis struct #ident {
+            #( #fields )*
+        }
+
+        const _: () = {
+            #( #checks )*
+        };
+
+        impl #ident {
+            /// Returns the register block at `ptr`, which can also point to plain memory.
+            ///
+            /// # Safety
+            ///
+            /// `ptr` needs to be valid for volatile reads and writes of the whole block, and aligned, for as long as it is used.
+            pub const unsafe fn from_ptr<'a>(ptr: *mut Self) -> &'a Self {
+                unsafe { &*ptr }
+            }
+
+            #( #accessors )*
+        }
+    }
+}
+
+fn is_offset_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("offset")
+}
+
+/// Reads `#[offset(0x10)]`.
+fn offset_value(attr: &Attribute) -> usize {
+    let help = "offsets need to be integer literals, like `#[offset(0x10)]`";
+    let Meta::List(list) = &attr.meta else {
+        abort!(attr, "offset is missing its value"; help = help)
+    };
+    let offset: syn::LitInt = syn::parse2(list.tokens.clone()).unwrap_or_else(|_| abort!(list.tokens, "offset is not a number"; help = help));
+    offset
+        .base10_parse()
+        .unwrap_or_else(|_| abort!(offset, "offset is not a valid number"; help = help))
+}
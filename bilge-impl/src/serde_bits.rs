@@ -1,35 +1,100 @@
 use itertools::MultiUnzip;
 use proc_macro2::{Ident, TokenStream};
-use proc_macro_error2::abort_call_site;
+use proc_macro_error2::{abort, abort_call_site};
 use quote::quote;
-use syn::{Data, Field, Fields};
+use syn::{parse_quote, Data, DeriveInput, Field, Fields, LitStr};
 
-use crate::shared::{self, unreachable};
+mod attrs;
+
+use attrs::{SerdeContainer, SerdeField};
+
+use crate::{
+    bitsize_internal::field_name,
+    shared::{
+        self,
+        access::{self, Access},
+        unreachable,
+    },
+};
 
 fn filter_not_reserved_or_padding(field: &&Field) -> bool {
     let field_name_string = field.ident.as_ref().unwrap().to_string();
     !field_name_string.starts_with("reserved_") && !field_name_string.starts_with("padding_")
 }
 
+/// How a bitfield is represented in serde, given by `#[serde_bits(repr = "..")]` on the struct.
+#[derive(Clone, Copy, PartialEq)]
+enum SerdeRepr {
+    /// `fields`: a struct or tuple of the field values, the default
+    Fields,
+    /// `int`: the raw value as an integer
+    Int,
+    /// `bits`: the raw value as a binary string, like `"0b1010_0011"`
+    Bits,
+}
+
+impl SerdeRepr {
+    fn of(derive_input: &DeriveInput) -> SerdeRepr {
+        let Some(attr) = derive_input.attrs.iter().find(|attr| shared::is_serde_bits_attribute(attr)) else {
+            return SerdeRepr::Fields;
+        };
+        let help = r#"use one of `#[serde_bits(repr = "int")]`, `#[serde_bits(repr = "bits")]` or `#[serde_bits(repr = "fields")]`"#;
+        let mut serde_repr = None;
+        attr.parse_nested_meta(|meta| {
+            if !meta.path.is_ident("repr") {
+                return Err(meta.error("unknown serde_bits option"));
+            }
+            let value: LitStr = meta.value()?.parse()?;
+            serde_repr = match value.value().as_str() {
+                "fields" => Some(SerdeRepr::Fields),
+                "int" => Some(SerdeRepr::Int),
+                "bits" => Some(SerdeRepr::Bits),
+                _ => abort!(value, "serde representation is invalid"; help = help),
+            };
+            Ok(())
+        })
+        .unwrap_or_else(|err| abort!(err.span(), "{}", err; help = help));
+        let serde_repr = serde_repr.unwrap_or_else(|| abort!(attr, "serde representation is missing"; help = help));
+
+        let (bitsize, _) = shared::bitsize_from_internal_attr(&derive_input.attrs);
+        if serde_repr != SerdeRepr::Fields && shared::is_wide(bitsize) {
+            abort!(attr, "raw serde representations are not supported for bitfields above 128 bits"; help = r#"use `#[serde_bits(repr = "fields")]` instead"#)
+        }
+        serde_repr
+    }
+}
+
 pub(super) fn serialize_bits(item: TokenStream) -> TokenStream {
     let derive_input = shared::parse_derive(item);
+    match SerdeRepr::of(&derive_input) {
+        SerdeRepr::Fields => {}
+        SerdeRepr::Int => return generate_serialize_int(&derive_input),
+        SerdeRepr::Bits => return generate_serialize_binary_string(&derive_input),
+    }
     let name = &derive_input.ident;
-    let name_str = name.to_string();
-    let struct_data = match derive_input.data {
+    let container = SerdeContainer::of(&derive_input.attrs);
+    let name_str = container.rename.clone().unwrap_or_else(|| name.to_string());
+    let (bitsize, _) = shared::bitsize_from_internal_attr(&derive_input.attrs);
+    let options = shared::options_from_internal_attr(&derive_input.attrs);
+    let struct_data = match &derive_input.data {
         Data::Struct(s) => s,
         Data::Enum(_) => abort_call_site!("use derive(Serialize) for enums"),
         Data::Union(_) => unreachable(()),
     };
+    let repr = shared::Repr::new(bitsize, &derive_input.generics, &struct_data.fields, options);
 
+    let field_offsets = shared::generate_field_offsets(&struct_data.fields, options.msb_first);
+    let fields = serde_fields(&struct_data.fields, &field_offsets, &container);
+    // skipped fields are left out completely
+    let fields: Vec<_> = fields.iter().filter(|f| !f.serde.skip).collect();
+    let len = fields.len();
     let serialize_impl = match struct_data.fields {
-        Fields::Named(fields) => {
-            let calls = fields.named.iter().filter(filter_not_reserved_or_padding).map(|f| {
-                // We can unwrap since this is a named field
-                let call = f.ident.as_ref().unwrap();
-                let name = call.to_string();
-                quote!(state.serialize_field(#name, &self.#call())?;)
+        Fields::Named(_) => {
+            let calls = fields.iter().map(|f| {
+                let key = &f.key;
+                let value = access::generate_field_read(f.field, &f.call, f.offset, repr);
+                quote!(state.serialize_field(#key, &#value)?;)
             });
-            let len = fields.named.iter().filter(filter_not_reserved_or_padding).count();
             quote! {
                 use ::serde::ser::SerializeStruct;
                 let mut state = serializer.serialize_struct(#name_str, #len)?;
@@ -38,12 +103,11 @@ pub(super) fn serialize_bits(item: TokenStream) -> TokenStream {
                 state.end()
             }
         }
-        Fields::Unnamed(fields) => {
-            let calls = fields.unnamed.iter().enumerate().map(|(i, _)| {
-                let call: Ident = syn::parse_str(&format!("val_{}", i)).unwrap_or_else(unreachable);
-                quote!(state.serialize_field(&self.#call())?;)
+        Fields::Unnamed(_) => {
+            let calls = fields.iter().map(|f| {
+                let value = access::generate_field_read(f.field, &f.call, f.offset, repr);
+                quote!(state.serialize_field(&#value)?;)
             });
-            let len = fields.unnamed.len();
             quote! {
                 use serde::ser::SerializeTupleStruct;
                 let mut state = serializer.serialize_tuple_struct(#name_str, #len)?;
@@ -55,8 +119,11 @@ pub(super) fn serialize_bits(item: TokenStream) -> TokenStream {
         Fields::Unit => todo!("this is a unit struct, which is not supported right now"),
     };
 
+    let generics = shared::generics_with_bound(&derive_input.generics, quote!(::serde::Serialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     quote! {
-        impl ::serde::Serialize for #name {
+        impl #impl_generics ::serde::Serialize for #name #ty_generics #where_clause {
             fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
             where
                 S: ::serde::Serializer,
@@ -67,8 +134,58 @@ pub(super) fn serialize_bits(item: TokenStream) -> TokenStream {
     }
 }
 
+/// A field as serde sees it, with its accessor name `call` and its serialized name `key`.
+struct SerdeFieldInfo<'a> {
+    field: &'a Field,
+    offset: &'a TokenStream,
+    call: Ident,
+    key: String,
+    serde: SerdeField,
+}
+
+impl SerdeFieldInfo<'_> {
+    /// The value of a skipped or missing field: its own default, the one of the struct or, for skipped fields, `Default`.
+    ///
+    /// The default of the struct is `__default`, see `deserialize_bits`.
+    fn generate_default(&self, container: &SerdeContainer) -> Option<TokenStream> {
+        if let Some(default) = &self.serde.default {
+            return Some(default.generate_value());
+        }
+        if container.default.is_some() {
+            if !Access::of(self.field).is_readable() {
+                abort!(self.field, "`#[serde(default)]` on the struct needs to read this field"; help = "add `#[serde(default)]` to this field as well")
+            }
+            let call = &self.call;
+            return Some(quote!(__default.#call()));
+        }
+        self.serde.skip.then(|| quote!(::core::default::Default::default()))
+    }
+}
+
+/// Every field besides `reserved` and `padding` fields, which aren't (de)serialized.
+fn serde_fields<'a>(fields: &'a Fields, field_offsets: &'a [TokenStream], container: &SerdeContainer) -> Vec<SerdeFieldInfo<'a>> {
+    fields
+        .iter()
+        .zip(field_offsets)
+        .enumerate()
+        .filter(|(_, (field, _))| field.ident.is_none() || filter_not_reserved_or_padding(field))
+        .map(|(i, (field, offset))| {
+            let serde = SerdeField::of(field);
+            let call = field_name(field, i);
+            let key = serde.key(&call.to_string(), container);
+            SerdeFieldInfo {
+                field,
+                offset,
+                call,
+                key,
+                serde,
+            }
+        })
+        .collect()
+}
+
 fn deserialize_field_parts(
-    i: usize, field_ident: &Ident,
+    i: usize, f: &SerdeFieldInfo, container: &SerdeContainer,
 ) -> (
     TokenStream,
     TokenStream,
@@ -79,12 +196,21 @@ fn deserialize_field_parts(
     TokenStream,
     String,
 ) {
-    let field_name_string = field_ident.to_string();
+    let field_ident = &f.call;
+    let field_name_string = &f.key;
+    // missing fields with a default get that one instead of an error
+    let (or_invalid_length, or_missing) = match f.generate_default(container) {
+        Some(default) => (default.clone(), default),
+        None => (
+            quote!(return Err(::serde::de::Error::invalid_length(#i, &self))),
+            quote!(return Err(::serde::de::Error::missing_field(#field_name_string))),
+        ),
+    };
     (
         quote!(#field_ident,),
         quote!(#field_name_string => Ok(Field::#field_ident),),
         quote!(#field_name_string,),
-        quote!(let #field_ident = seq.next_element()?.ok_or_else(|| ::serde::de::Error::invalid_length(#i, &self))?;),
+        quote!(let #field_ident = match seq.next_element()? { Some(value) => value, None => #or_invalid_length };),
         quote!(let mut #field_ident = None;),
         quote!(Field::#field_ident => {
             if #field_ident.is_some() {
@@ -92,17 +218,31 @@ fn deserialize_field_parts(
             }
             #field_ident = Some(map.next_value()?);
         }),
-        quote!(let #field_ident = #field_ident.ok_or_else(|| ::serde::de::Error::missing_field(#field_name_string))?;),
+        quote!(let #field_ident = match #field_ident { Some(value) => value, None => #or_missing };),
         format!("`{}`", field_name_string),
     )
 }
 
 pub(super) fn deserialize_bits(item: TokenStream) -> TokenStream {
     let derive_input = shared::parse_derive(item);
+    match SerdeRepr::of(&derive_input) {
+        SerdeRepr::Fields => {}
+        SerdeRepr::Int => return generate_deserialize_int(&derive_input),
+        SerdeRepr::Bits => return generate_deserialize_binary_string(&derive_input),
+    }
     let name = &derive_input.ident;
-    let name_str = name.to_string();
+    let container = SerdeContainer::of(&derive_input.attrs);
+    let name_str = container.rename.clone().unwrap_or_else(|| name.to_string());
     let struct_name_str = format!("struct {}", name_str);
-    let struct_data = match derive_input.data {
+
+    // the visitor needs the same generics as the struct it creates, and `'de` on top of them
+    let generics = &derive_input.generics;
+    let (_, ty_generics, where_clause) = generics.split_for_impl();
+    let mut de_generics = shared::generics_with_bound(generics, quote!(::serde::Deserialize<'de>));
+    de_generics.params.insert(0, parse_quote!('de));
+    let (de_impl_generics, _, de_where_clause) = de_generics.split_for_impl();
+
+    let struct_data = match &derive_input.data {
         Data::Struct(s) => s,
         Data::Enum(_) => abort_call_site!("use derive(Serialize) for enums"),
         Data::Union(_) => unreachable(()),
@@ -110,6 +250,25 @@ pub(super) fn deserialize_bits(item: TokenStream) -> TokenStream {
 
     let should_have_visit_map = matches!(struct_data.fields, Fields::Named(_));
 
+    if let Fields::Unit = struct_data.fields {
+        todo!("this is a unit struct, which is not supported right now")
+    }
+    let options = shared::options_from_internal_attr(&derive_input.attrs);
+    let field_offsets = shared::generate_field_offsets(&struct_data.fields, options.msb_first);
+    let fields = serde_fields(&struct_data.fields, &field_offsets, &container);
+    // `new` takes every field, including skipped ones
+    let new_args: Vec<_> = fields.iter().map(|f| &f.call).collect();
+    let skipped_fields = fields.iter().filter(|f| f.serde.skip).map(|f| {
+        let call = &f.call;
+        let default = f.generate_default(&container).unwrap_or_else(|| unreachable(()));
+        quote!(let #call = #default;)
+    });
+    let skipped_fields = quote!(#( #skipped_fields )*);
+    let container_default = container.default.as_ref().map(|default| {
+        let value = default.generate_value();
+        quote!(let __default: Self::Value = #value;)
+    });
+
     let (
         field_names,
         field_deserialize,
@@ -119,22 +278,12 @@ pub(super) fn deserialize_bits(item: TokenStream) -> TokenStream {
         field_visit_map_match,
         field_visit_map_check,
         mut field_expecting,
-    ): (Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>) = match struct_data.fields {
-        Fields::Named(fields) => fields
-            .named
-            .iter()
-            .filter(filter_not_reserved_or_padding)
-            .enumerate()
-            .map(|(i, f)| deserialize_field_parts(i, f.ident.as_ref().unwrap()))
-            .multiunzip(),
-        Fields::Unnamed(fields) => fields
-            .unnamed
-            .iter()
-            .enumerate()
-            .map(|(i, _)| deserialize_field_parts(i, &syn::parse_str(&format!("val_{}", i)).unwrap_or_else(unreachable)))
-            .multiunzip(),
-        Fields::Unit => todo!("this is a unit struct, which is not supported right now"),
-    };
+    ): (Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>, Vec<_>) = fields
+        .iter()
+        .filter(|f| !f.serde.skip)
+        .enumerate()
+        .map(|(i, f)| deserialize_field_parts(i, f, &container))
+        .multiunzip();
 
     if field_expecting.len() > 1 {
         field_expecting.last_mut().unwrap().insert_str(0, "or ");
@@ -146,6 +295,7 @@ pub(super) fn deserialize_bits(item: TokenStream) -> TokenStream {
         where
             V: ::serde::de::MapAccess<'de>,
         {
+            #container_default
             #(#field_visit_map_init)*
             while let Some(key) = map.next_key()? {
                 match key {
@@ -153,14 +303,15 @@ pub(super) fn deserialize_bits(item: TokenStream) -> TokenStream {
                 }
             }
             #(#field_visit_map_check)*
-            Ok(#name::new(#(#field_names)*))
+            #skipped_fields
+            Ok(Self::Value::new(#(#new_args),*))
         })
     } else {
         quote!()
     };
 
     quote! {
-        impl<'de> ::serde::Deserialize<'de> for #name {
+        impl #de_impl_generics ::serde::Deserialize<'de> for #name #ty_generics #de_where_clause {
             fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
             where
                 D: ::serde::Deserializer<'de>,
@@ -196,10 +347,10 @@ pub(super) fn deserialize_bits(item: TokenStream) -> TokenStream {
                     }
                 }
 
-                struct Visitor;
+                struct Visitor #generics (::core::marker::PhantomData<fn() -> #name #ty_generics>) #where_clause;
 
-                impl<'de> ::serde::de::Visitor<'de> for Visitor {
-                    type Value = #name;
+                impl #de_impl_generics ::serde::de::Visitor<'de> for Visitor #ty_generics #de_where_clause {
+                    type Value = #name #ty_generics;
 
                     fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
                         formatter.write_str(#struct_name_str)
@@ -209,15 +360,190 @@ pub(super) fn deserialize_bits(item: TokenStream) -> TokenStream {
                     where
                         V: ::serde::de::SeqAccess<'de>,
                     {
+                        #container_default
                         #(#field_visit_seq)*
-                        Ok(Self::Value::new(#(#field_names)*))
+                        #skipped_fields
+                        Ok(Self::Value::new(#(#new_args),*))
                     }
 
                     #visit_map
                 }
 
                 const FIELDS: &'static [&'static str] = &[#(#field_name_strings)*];
-                deserializer.deserialize_struct(#name_str, FIELDS, Visitor)
+                deserializer.deserialize_struct(#name_str, FIELDS, Visitor(::core::marker::PhantomData))
+            }
+        }
+    }
+}
+
+/// `repr = "int"` serializes the raw value as the base integer, e.g. `u16` for a `u13` bitfield.
+fn generate_serialize_int(derive_input: &DeriveInput) -> TokenStream {
+    let DeriveInput { ident, generics, attrs, .. } = derive_input;
+    let (bitsize, _) = shared::bitsize_from_internal_attr(attrs);
+    let (base_int, _) = shared::base_int_of(bitsize);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::serde::Serialize for #ident #ty_generics #where_clause {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                #[allow(clippy::unnecessary_cast)]
+                let value = self.__bilge_into_bits() as #base_int;
+                ::serde::Serialize::serialize(&value, serializer)
+            }
+        }
+    }
+}
+
+/// `repr = "bits"` serializes the raw value as its binary digits, with an underscore between every 4 of them.
+fn generate_serialize_binary_string(derive_input: &DeriveInput) -> TokenStream {
+    let DeriveInput { ident, generics, attrs, .. } = derive_input;
+    let (bitsize, _) = shared::bitsize_from_internal_attr(attrs);
+    let bits = bitsize as usize;
+    // "0b", the digits and the underscores
+    let len = 2 + bits + (bits - 1) / 4;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::serde::Serialize for #ident #ty_generics #where_clause {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                let value = self.__bilge_into_bits();
+                // no allocations, so this works without `alloc` as well
+                let mut string = [b'_'; #len];
+                string[..2].copy_from_slice(b"0b");
+                let mut i = 2;
+                for bit in (0..#bits).rev() {
+                    string[i] = if value >> bit & 1 == 1 { b'1' } else { b'0' };
+                    i += 1;
+                    // skip the underscore
+                    if bit % 4 == 0 && bit != 0 {
+                        i += 1;
+                    }
+                }
+                let string = ::core::str::from_utf8(&string).unwrap_or_else(|_| unreachable!());
+                serializer.serialize_str(string)
+            }
+        }
+    }
+}
+
+/// The raw value goes through `TryFrom`, which `From` implements as well, so invalid values become a serde error.
+///
+/// Returns the generics of `impl Deserialize` with a bound on that, and the conversion of `value`, which is an `u128`.
+fn generate_raw_conversion(derive_input: &DeriveInput) -> (syn::Generics, TokenStream) {
+    let DeriveInput { ident, generics, attrs, .. } = derive_input;
+    let (bitsize, arb_int) = shared::bitsize_from_internal_attr(attrs);
+    let (base_int, _) = shared::base_int_of(bitsize);
+    let (_, ty_generics, _) = generics.split_for_impl();
+
+    let mut de_generics = generics.clone();
+    de_generics.make_where_clause().predicates.extend::<[syn::WherePredicate; 2]>([
+        parse_quote!(#ident #ty_generics: ::core::convert::TryFrom<#arb_int>),
+        parse_quote!(<#ident #ty_generics as ::core::convert::TryFrom<#arb_int>>::Error: ::core::fmt::Display),
+    ]);
+    de_generics.params.insert(0, parse_quote!('de));
+
+    let raw_value = shared::generate_const_new(bitsize, quote!(value as #base_int));
+    let conversion = quote! {
+        #[allow(clippy::unnecessary_cast)]
+        let raw_value = #raw_value;
+        <#ident #ty_generics as ::core::convert::TryFrom<#arb_int>>::try_from(raw_value).map_err(::serde::de::Error::custom)
+    };
+    (de_generics, conversion)
+}
+
+/// `repr = "int"` deserializes the base integer, rejecting values above the bitsize.
+fn generate_deserialize_int(derive_input: &DeriveInput) -> TokenStream {
+    let DeriveInput { ident, generics, attrs, .. } = derive_input;
+    let (bitsize, _) = shared::bitsize_from_internal_attr(attrs);
+    let (base_int, _) = shared::base_int_of(bitsize);
+    let bits = bitsize as usize;
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let (de_generics, conversion) = generate_raw_conversion(derive_input);
+    let (de_impl_generics, _, de_where_clause) = de_generics.split_for_impl();
+
+    quote! {
+        impl #de_impl_generics ::serde::Deserialize<'de> for #ident #ty_generics #de_where_clause {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                #[allow(clippy::unnecessary_cast)]
+                let value = <#base_int as ::serde::Deserialize>::deserialize(deserializer)? as u128;
+                if value > u128::MAX >> (128 - #bits) {
+                    return Err(::serde::de::Error::custom(::core::format_args!(
+                        "invalid value: integer `{}`, expected an integer of {} bits",
+                        value,
+                        #bits
+                    )));
+                }
+                #conversion
+            }
+        }
+    }
+}
+
+/// `repr = "bits"` deserializes a binary string like `"0b1010_0011"`, where leading zeros and underscores are optional.
+fn generate_deserialize_binary_string(derive_input: &DeriveInput) -> TokenStream {
+    let DeriveInput { ident, generics, attrs, .. } = derive_input;
+    let (bitsize, _) = shared::bitsize_from_internal_attr(attrs);
+    let bits = bitsize as usize;
+    let expecting = format!("a binary string like `0b1010` of {bits} bits");
+    let (_, ty_generics, _) = generics.split_for_impl();
+    let (de_generics, conversion) = generate_raw_conversion(derive_input);
+    let (de_impl_generics, _, de_where_clause) = de_generics.split_for_impl();
+
+    quote! {
+        impl #de_impl_generics ::serde::Deserialize<'de> for #ident #ty_generics #de_where_clause {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> ::serde::de::Visitor<'de> for Visitor {
+                    type Value = u128;
+
+                    fn expecting(&self, formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+                        formatter.write_str(#expecting)
+                    }
+
+                    fn visit_str<E>(self, string: &str) -> Result<u128, E>
+                    where
+                        E: ::serde::de::Error,
+                    {
+                        let invalid = || E::invalid_value(::serde::de::Unexpected::Str(string), &self);
+                        let digits = string.strip_prefix("0b").ok_or_else(invalid)?;
+                        let mut value = 0u128;
+                        let mut has_digits = false;
+                        for digit in digits.chars() {
+                            let bit = match digit {
+                                '0' => 0,
+                                '1' => 1,
+                                '_' => continue,
+                                _ => return Err(invalid()),
+                            };
+                            // the next digit would push a one out of the bitsize
+                            if value >> (#bits - 1) != 0 {
+                                return Err(invalid());
+                            }
+                            value = value << 1 | bit;
+                            has_digits = true;
+                        }
+                        if !has_digits {
+                            return Err(invalid());
+                        }
+                        Ok(value)
+                    }
+                }
+
+                let value = deserializer.deserialize_str(Visitor)?;
+                #conversion
             }
         }
     }
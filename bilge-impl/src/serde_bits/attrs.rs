@@ -0,0 +1,171 @@
+use proc_macro2::TokenStream;
+use proc_macro_error2::abort;
+use quote::quote;
+use syn::{meta::ParseNestedMeta, Attribute, ExprPath, Field, LitStr};
+
+use crate::shared;
+
+/// `#[serde(default)]` or `#[serde(default = "path")]`.
+pub(super) enum SerdeDefault {
+    Trait,
+    Path(ExprPath),
+}
+
+impl SerdeDefault {
+    fn parse(meta: &ParseNestedMeta) -> syn::Result<SerdeDefault> {
+        if meta.input.peek(syn::Token![=]) {
+            let path: LitStr = meta.value()?.parse()?;
+            Ok(SerdeDefault::Path(path.parse()?))
+        } else {
+            Ok(SerdeDefault::Trait)
+        }
+    }
+
+    pub fn generate_value(&self) -> TokenStream {
+        match self {
+            SerdeDefault::Trait => quote!(::core::default::Default::default()),
+            SerdeDefault::Path(path) => quote!(#path()),
+        }
+    }
+}
+
+/// `#[serde(rename_all = "..")]`, with the same rules as serde.
+#[derive(Clone, Copy)]
+pub(super) enum RenameRule {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl RenameRule {
+    fn parse(rule: &LitStr) -> RenameRule {
+        match rule.value().as_str() {
+            "lowercase" => RenameRule::Lower,
+            "UPPERCASE" => RenameRule::Upper,
+            "PascalCase" => RenameRule::Pascal,
+            "camelCase" => RenameRule::Camel,
+            "snake_case" => RenameRule::Snake,
+            "SCREAMING_SNAKE_CASE" => RenameRule::ScreamingSnake,
+            "kebab-case" => RenameRule::Kebab,
+            "SCREAMING-KEBAB-CASE" => RenameRule::ScreamingKebab,
+            _ => {
+                abort!(rule, "rename rule is invalid"; help = "use one of \"lowercase\", \"UPPERCASE\", \"PascalCase\", \"camelCase\", \"snake_case\", \"SCREAMING_SNAKE_CASE\", \"kebab-case\" or \"SCREAMING-KEBAB-CASE\"")
+            }
+        }
+    }
+
+    /// Field names are snake_case already, so `lowercase` and `snake_case` keep them as they are.
+    fn apply(self, field: &str) -> String {
+        match self {
+            RenameRule::Lower | RenameRule::Snake => field.to_owned(),
+            RenameRule::Upper | RenameRule::ScreamingSnake => field.to_ascii_uppercase(),
+            RenameRule::Pascal => {
+                let mut pascal = String::new();
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            RenameRule::Camel => {
+                let pascal = RenameRule::Pascal.apply(field);
+                let mut chars = pascal.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_ascii_lowercase().to_string() + chars.as_str())
+                    .unwrap_or_default()
+            }
+            RenameRule::Kebab => field.replace('_', "-"),
+            RenameRule::ScreamingKebab => field.to_ascii_uppercase().replace('_', "-"),
+        }
+    }
+}
+
+/// `#[serde(..)]` on the struct: `rename`, `rename_all` and `default`.
+#[derive(Default)]
+pub(super) struct SerdeContainer {
+    pub rename: Option<String>,
+    pub rename_all: Option<RenameRule>,
+    /// missing fields are taken from this value of the whole struct
+    pub default: Option<SerdeDefault>,
+}
+
+impl SerdeContainer {
+    pub fn of(attrs: &[Attribute]) -> SerdeContainer {
+        let mut container = SerdeContainer::default();
+        for attr in attrs.iter().filter(|attr| shared::is_serde_attribute(attr)) {
+            parse_serde_attribute(attr, |meta| {
+                if meta.path.is_ident("rename") {
+                    container.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("rename_all") {
+                    container.rename_all = Some(RenameRule::parse(&meta.value()?.parse()?));
+                } else if meta.path.is_ident("default") {
+                    container.default = Some(SerdeDefault::parse(&meta)?);
+                } else {
+                    return Err(meta.error("serde attribute is not supported on bitfields"));
+                }
+                Ok(())
+            });
+        }
+        container
+    }
+}
+
+/// `#[serde(..)]` on a field: `rename`, `skip` and `default`.
+#[derive(Default)]
+pub(super) struct SerdeField {
+    pub rename: Option<String>,
+    /// not serialized, and filled from the default when deserializing
+    pub skip: bool,
+    pub default: Option<SerdeDefault>,
+}
+
+impl SerdeField {
+    pub fn of(field: &Field) -> SerdeField {
+        let mut serde_field = SerdeField::default();
+        for attr in field.attrs.iter().filter(|attr| shared::is_serde_attribute(attr)) {
+            parse_serde_attribute(attr, |meta| {
+                if meta.path.is_ident("rename") {
+                    if field.ident.is_none() {
+                        return Err(meta.error("fields of tuple structs have no name to rename"));
+                    }
+                    serde_field.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") {
+                    serde_field.skip = true;
+                } else if meta.path.is_ident("default") {
+                    serde_field.default = Some(SerdeDefault::parse(&meta)?);
+                } else {
+                    return Err(meta.error("serde attribute is not supported on bitfield fields"));
+                }
+                Ok(())
+            });
+        }
+        serde_field
+    }
+
+    /// The name in the serialized struct, which is `name` unless renamed.
+    pub fn key(&self, name: &str, container: &SerdeContainer) -> String {
+        match (&self.rename, container.rename_all) {
+            (Some(rename), _) => rename.clone(),
+            (None, Some(rule)) => rule.apply(name),
+            (None, None) => name.to_owned(),
+        }
+    }
+}
+
+fn parse_serde_attribute(attr: &Attribute, logic: impl FnMut(ParseNestedMeta) -> syn::Result<()>) {
+    attr.parse_nested_meta(logic)
+        .unwrap_or_else(|err| abort!(err.span(), "{}", err; help = "bitfields support `rename`, `rename_all`, `skip` and `default`"))
+}
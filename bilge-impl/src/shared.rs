@@ -1,21 +1,36 @@
+pub mod access;
+pub mod byte_conversions;
 pub mod discriminant_assigner;
 pub mod fallback;
+pub mod options;
+pub mod reserved;
+pub mod tagged;
 pub mod util;
 
 use fallback::{fallback_variant, Fallback};
-use proc_macro2::{Ident, Literal, TokenStream};
+use options::BitsizeOptions;
+use proc_macro2::{Ident, TokenStream};
 use proc_macro_error2::{abort, abort_call_site};
 use quote::quote;
-use syn::{Attribute, DeriveInput, LitInt, Meta, Type};
+use syn::{parse_quote, Attribute, DeriveInput, Field, Fields, Generics, LitInt, Meta, Type};
 use util::PathExt;
 
 /// As arbitrary_int is limited to basic rust primitives, the maximum is u128.
-/// Is there a true usecase for bitfields above this size?
+/// Bitfields up to this size are backed by a single arbitrary_int.
 /// This would also be change-worthy when rust starts supporting LLVM's arbitrary integers.
-pub const MAX_STRUCT_BIT_SIZE: BitSize = 128;
-/// As `#[repr(u128)]` is unstable and currently no real usecase for higher sizes exists, the maximum is u64.
-pub const MAX_ENUM_BIT_SIZE: BitSize = 64;
-pub type BitSize = u8;
+pub const MAX_INT_BIT_SIZE: BitSize = 128;
+/// Bitfields above `MAX_INT_BIT_SIZE` are "wide" and backed by a byte array instead, see [`is_wide`].
+/// This limit is arbitrary, it just keeps the generated code and compile times sane.
+pub const MAX_STRUCT_BIT_SIZE: BitSize = 4096;
+/// Enums are matched by their `u128` value, so unlike structs, they can't be wide.
+///
+/// `#[repr(u128)]` is above our MSRV, so enums above `MAX_REPR_ENUM_BIT_SIZE` lose their explicit discriminants,
+/// see `discriminant_assigner::keeps_discriminants`. Their values are still available through `uN::from(enum_value)`.
+pub const MAX_ENUM_BIT_SIZE: BitSize = MAX_INT_BIT_SIZE;
+/// Enums up to this size keep their explicit discriminants, since those fit into the default `isize` representation
+/// (at least on 64-bit targets).
+pub const MAX_REPR_ENUM_BIT_SIZE: BitSize = 64;
+pub type BitSize = u16;
 
 pub(crate) fn parse_derive(item: TokenStream) -> DeriveInput {
     syn::parse2(item).unwrap_or_else(unreachable)
@@ -45,12 +60,7 @@ pub(crate) fn analyze_derive(derive_input: &DeriveInput, try_from: bool) -> (&sy
         }
     }
 
-    // parsing the #[bitsize_internal(num)] attribute macro
-    let args = attrs
-        .iter()
-        .find_map(bitsize_internal_arg)
-        .unwrap_or_else(|| abort_call_site!("add #[bitsize] attribute above your derive attribute"));
-    let (bitsize, arb_int) = bitsize_and_arbitrary_int_from(args);
+    let (bitsize, arb_int) = bitsize_from_internal_attr(attrs);
 
     let fallback = fallback_variant(data, bitsize);
     if fallback.is_some() && try_from {
@@ -60,8 +70,40 @@ pub(crate) fn analyze_derive(derive_input: &DeriveInput, try_from: bool) -> (&sy
     (data, arb_int, ident, bitsize, fallback)
 }
 
+/// parsing the #[bitsize_internal(num)] attribute macro, which is all derives know of `#[bitsize]`
+pub(crate) fn bitsize_from_internal_attr(attrs: &[Attribute]) -> (BitSize, TokenStream) {
+    let args = attrs
+        .iter()
+        .find_map(bitsize_internal_arg)
+        .unwrap_or_else(|| abort_call_site!("add #[bitsize] attribute above your derive attribute"));
+    bitsize_and_arbitrary_int_from(args)
+}
+
+/// parsing the options of #[bitsize_internal(num, options)], which derives need for e.g. `msb_first`
+pub(crate) fn options_from_internal_attr(attrs: &[Attribute]) -> BitsizeOptions {
+    let args = attrs
+        .iter()
+        .find_map(bitsize_internal_arg)
+        .unwrap_or_else(|| abort_call_site!("add #[bitsize] attribute above your derive attribute"));
+    BitsizeOptions::from_args(args)
+}
+
+/// Whether `bitsize_internal` generated the const `__bilge_from_bits` and `__bilge_into_bits` the byte conversions use.
+///
+/// Generic bitfields don't have them, and neither do tagged enums without [`tagged::Tagged::has_const_payloads`].
+pub(crate) fn has_const_conversions(derive_input: &DeriveInput, bitsize: BitSize) -> bool {
+    let options = options_from_internal_attr(&derive_input.attrs);
+    match &derive_input.data {
+        syn::Data::Enum(data) => tagged::Tagged::analyze(data.variants.iter(), bitsize, options).is_none_or(|tagged| tagged.has_const_payloads()),
+        _ => !is_generic(&derive_input.generics),
+    }
+}
+
 // If we want to support bitsize(u4) besides bitsize(4), do that here.
+// allow since `is_multiple_of` is above our MSRV
+#[allow(clippy::manual_is_multiple_of)]
 pub fn bitsize_and_arbitrary_int_from(bitsize_arg: TokenStream) -> (BitSize, TokenStream) {
+    let (bitsize_arg, _) = options::split_bitsize_args(bitsize_arg);
     let bitsize: LitInt = syn::parse2(bitsize_arg.clone())
         .unwrap_or_else(|_| abort!(bitsize_arg, "attribute value is not a number"; help = "you need to define the size like this: `#[bitsize(32)]`"));
     // without postfix
@@ -70,10 +112,220 @@ pub fn bitsize_and_arbitrary_int_from(bitsize_arg: TokenStream) -> (BitSize, Tok
         .ok()
         .filter(|&n| n != 0 && n <= MAX_STRUCT_BIT_SIZE)
         .unwrap_or_else(|| abort!(bitsize_arg, "attribute value is not a valid number"; help = "currently, numbers from 1 to {} are allowed", MAX_STRUCT_BIT_SIZE));
-    let arb_int = syn::parse_str(&format!("u{bitsize}")).unwrap_or_else(unreachable);
+    let arb_int = if is_wide(bitsize) {
+        if bitsize % 8 != 0 {
+            abort!(bitsize_arg, "attribute value is not a multiple of 8"; help = "bitfields above {} bits are backed by a byte array, so their size needs to be a multiple of 8", MAX_INT_BIT_SIZE)
+        }
+        let byte_count = bitsize as usize / 8;
+        quote!([u8; #byte_count])
+    } else {
+        syn::parse_str(&format!("u{bitsize}")).unwrap_or_else(unreachable)
+    };
     (bitsize, arb_int)
 }
 
+/// Bitfields above `MAX_INT_BIT_SIZE` can't be backed by an arbitrary_int, so we use a `[u8; N]` instead.
+///
+/// Byte `0` holds the bits `0..8`, byte `1` holds `8..16` and so on.
+/// Fields are read and written through `bilge::read_bits` and `bilge::write_bits`.
+pub fn is_wide(bitsize: BitSize) -> bool {
+    bitsize > MAX_INT_BIT_SIZE
+}
+
+/// The primitive behind the `uN` of a bitfield which isn't wide, e.g. `u8` for `u5`,
+/// which is the `UnderlyingType` returned by `value()`. Also returns the primitive's size.
+pub fn base_int_of(bitsize: BitSize) -> (Ident, BitSize) {
+    let size = bitsize.next_power_of_two().max(8);
+    (quote::format_ident!("u{}", size), size)
+}
+
+/// Generic bitfields have type or const parameters, like `struct Entry<T> { flag: bool, payload: T }`.
+///
+/// Their field types might not be known, so we can't use `as` casts or `transmute` on them
+/// and their size can only be checked once they get instantiated.
+pub fn is_generic(generics: &Generics) -> bool {
+    generics.type_params().next().is_some() || generics.const_params().next().is_some()
+}
+
+/// Everything deciding how the fields of a bitfield are read and written.
+#[derive(Clone, Copy)]
+pub struct Repr {
+    /// see [`is_wide`]
+    pub wide: bool,
+    /// see [`is_generic`]
+    pub generic: bool,
+    pub bitsize: BitSize,
+    /// whether every field type can be converted in const fns, see [`has_const_types`]
+    pub const_fields: bool,
+}
+
+impl Repr {
+    pub fn new(bitsize: BitSize, generics: &Generics, fields: &Fields, options: BitsizeOptions) -> Repr {
+        Repr {
+            wide: is_wide(bitsize),
+            generic: is_generic(generics),
+            bitsize,
+            const_fields: has_const_types(fields.iter().map(|field| &field.ty), options.const_fields),
+        }
+    }
+
+    /// Whether the accessors can be `const fn` on stable.
+    ///
+    /// Trait methods like `From::from` can't be called in const contexts, so the accessors use `as` casts and
+    /// the hidden `__bilge_from_bits` and `__bilge_into_bits`, see [`generate_const_from_bits`].
+    /// Generic bitfields don't know their field types, and other field types might not have these, so both still need the traits.
+    pub fn is_const(self) -> bool {
+        !self.generic && self.const_fields
+    }
+
+    /// `const` for the accessors, if they can be const, see [`Repr::is_const`]. Generic ones only are with the `nightly` feature.
+    pub fn const_keyword(self) -> TokenStream {
+        if self.is_const() || cfg!(feature = "nightly") {
+            quote!(const)
+        } else {
+            quote!()
+        }
+    }
+}
+
+/// `value()` of the `uN` holding a bitfield which isn't wide, which is only const for arbitrary_ints,
+/// not for primitives like `u32`. Those are their own value, so we use them as they are.
+pub(crate) fn generate_const_value(bitsize: BitSize, arb_int_value: TokenStream) -> TokenStream {
+    if is_primitive(bitsize) {
+        arb_int_value
+    } else {
+        quote!(#arb_int_value.value())
+    }
+}
+
+/// `uN::new(value)` for the `uN` holding a bitfield which isn't wide, which is only const for arbitrary_ints, like [`generate_const_value`].
+pub(crate) fn generate_const_new(bitsize: BitSize, value: TokenStream) -> TokenStream {
+    if is_primitive(bitsize) {
+        value
+    } else {
+        let arb_int = quote::format_ident!("u{}", bitsize);
+        quote!(#arb_int::new(#value))
+    }
+}
+
+/// `u8`, `u16`, `u32`, `u64` and `u128` aren't arbitrary_ints.
+fn is_primitive(bitsize: BitSize) -> bool {
+    matches!(bitsize, 8 | 16 | 32 | 64 | 128)
+}
+
+/// Converts the `u128` expression `bits`, which holds a valid value of the field type `ty` and nothing else, into `ty`.
+///
+/// This is const, unlike going through `From` or `TryFrom`: `bool` and primitives are cast, arbitrary_ints use their const `new`
+/// or `from_bits`, and every other type is expected to be a bitfield, which has a hidden `__bilge_from_bits`, see `bitsize_internal`.
+/// Only use this for types accepted by [`has_const_types`].
+pub(crate) fn generate_const_from_bits(ty: &Type, bits: TokenStream) -> TokenStream {
+    match const_leaf_of(ty) {
+        Some(ConstLeaf::Bool) => quote!((#bits != 0)),
+        Some(ConstLeaf::Primitive) => quote!((#bits as #ty)),
+        Some(ConstLeaf::ArbitraryInt(base_int)) => quote!(<#ty>::new(#bits as #base_int)),
+        // the lowest bits are the same for signed and unsigned
+        Some(ConstLeaf::SignedPrimitive(_)) => quote!((#bits as #ty)),
+        Some(ConstLeaf::SignedArbitraryInt(unsigned_base_int)) => quote!(<#ty>::from_bits(#bits as #unsigned_base_int)),
+        None => quote!(<#ty>::__bilge_from_bits(#bits)),
+    }
+}
+
+/// Converts `value` of the field type `ty` into an `u128`, the other direction of [`generate_const_from_bits`].
+///
+/// `value` needs to be a place or a parenthesized expression, since methods get called on it.
+pub(crate) fn generate_const_into_bits(ty: &Type, value: TokenStream) -> TokenStream {
+    match const_leaf_of(ty) {
+        Some(ConstLeaf::Bool | ConstLeaf::Primitive) => quote!((#value as u128)),
+        Some(ConstLeaf::ArbitraryInt(_)) => quote!((#value.value() as u128)),
+        // casting to the unsigned type first, so the sign doesn't get extended
+        Some(ConstLeaf::SignedPrimitive(unsigned)) => quote!((#value as #unsigned as u128)),
+        Some(ConstLeaf::SignedArbitraryInt(_)) => quote!((#value.to_bits() as u128)),
+        None => quote!(#value.__bilge_into_bits()),
+    }
+}
+
+/// Whether all of `types` can be converted in const fns, see [`generate_const_from_bits`].
+///
+/// Integers and `bool` always can, `#[bitsize]` items can through their hidden `__bilge_from_bits` and `__bilge_into_bits`.
+/// Types implementing `Bitsized` by hand don't have these, and we can't tell them apart from `#[bitsize]` items,
+/// so other types only count with `#[bitsize(N, const_fields)]`.
+pub(crate) fn has_const_types<'a>(mut types: impl Iterator<Item = &'a Type>, const_fields: bool) -> bool {
+    const_fields || types.all(is_const_type)
+}
+
+fn is_const_type(ty: &Type) -> bool {
+    match ty {
+        Type::Array(array) => is_const_type(&array.elem),
+        Type::Tuple(tuple) => tuple.elems.iter().all(is_const_type),
+        _ => const_leaf_of(ty).is_some(),
+    }
+}
+
+/// The integer types and `bool`, which get converted without any bitfield's help.
+enum ConstLeaf {
+    Bool,
+    /// `u8`, `u16`, ...
+    Primitive,
+    /// `u1`, `u2`, ... with the primitive their value is stored in
+    ArbitraryInt(Ident),
+    /// `i8`, `i16`, ... with their unsigned counterpart
+    SignedPrimitive(Ident),
+    /// `i1`, `i2`, ... with the unsigned counterpart of the primitive their value is stored in
+    SignedArbitraryInt(Ident),
+}
+
+fn const_leaf_of(ty: &Type) -> Option<ConstLeaf> {
+    let ident = last_ident_of_path(ty)?;
+    if let Some(bitsize) = bitsize_from_type_ident(ident) {
+        return Some(if ident == "bool" {
+            ConstLeaf::Bool
+        } else if is_primitive(bitsize) {
+            ConstLeaf::Primitive
+        } else {
+            ConstLeaf::ArbitraryInt(base_int_of(bitsize).0)
+        });
+    }
+    let bitsize: BitSize = ident.to_string().strip_prefix('i')?.parse().ok()?;
+    let (unsigned_base_int, _) = base_int_of(bitsize);
+    match bitsize {
+        1..=MAX_INT_BIT_SIZE if is_primitive(bitsize) => Some(ConstLeaf::SignedPrimitive(unsigned_base_int)),
+        1..=MAX_INT_BIT_SIZE => Some(ConstLeaf::SignedArbitraryInt(unsigned_base_int)),
+        _ => None,
+    }
+}
+
+/// `Self { value }`, which generic bitfields extend by their marker and their size check.
+///
+/// Type parameters are only used by the getters and setters, so the compressed struct
+/// needs a `PhantomData` to hold them, see `bitsize_internal`.
+/// The size check is generated by `bitsize`, see `generate_struct` in there.
+pub(crate) fn generate_struct_init(generics: &Generics) -> TokenStream {
+    let phantom = if generics.type_params().next().is_some() {
+        quote!(, _phantom: ::core::marker::PhantomData)
+    } else {
+        quote!()
+    };
+    if is_generic(generics) {
+        quote! { {
+            let () = Self::__BILGE_BITSIZE_CHECK;
+            Self { value #phantom }
+        } }
+    } else {
+        quote!(Self { value })
+    }
+}
+
+/// Derives like `DebugBits` need their trait on every field, which for type parameters means we need to add a bound.
+pub(crate) fn generics_with_bound(generics: &Generics, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<_> = generics.type_params().map(|param| param.ident.clone()).collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
 pub fn generate_type_bitsize(ty: &Type) -> TokenStream {
     use Type::*;
     match ty {
@@ -98,6 +350,48 @@ pub fn generate_type_bitsize(ty: &Type) -> TokenStream {
     }
 }
 
+/// The fields in the order they are laid out in, starting at bit 0.
+///
+/// Usually, that's the order they are declared in. `msb_first` bitfields are declared
+/// from the most significant bit down, like network protocol diagrams, so they are reversed.
+pub fn fields_in_layout_order<'a>(fields: impl IntoIterator<Item = &'a Field>, msb_first: bool) -> Vec<&'a Field> {
+    let mut fields: Vec<_> = fields.into_iter().collect();
+    if msb_first {
+        fields.reverse();
+    }
+    fields
+}
+
+/// The offset of every field, in declaration order, which is needed for bit-shifting.
+///
+/// ```ignore
+/// struct Example { field1: u8, field2: u4, field3: u4 }
+/// ```
+/// generates `[0, 8, 8 + 4]`, with the sizes being `Bitsized::BITS` of the field types.
+/// With `msb_first`, this would be `[4 + 4, 4, 0]` instead.
+pub fn generate_field_offsets<'a>(fields: impl IntoIterator<Item = &'a Field>, msb_first: bool) -> Vec<TokenStream> {
+    let mut previous_field_sizes = vec![];
+    let mut offsets: Vec<_> = fields_in_layout_order(fields, msb_first)
+        .into_iter()
+        .map(|field| {
+            // previous_field_sizes = []     -> unwrap_or_else -> field_offset = 0
+            // previous_field_sizes = [8]    -> reduce         -> field_offset = 8
+            // previous_field_sizes = [8, 4] -> reduce         -> field_offset = 8 + 4 = 12
+            let field_offset = previous_field_sizes
+                .iter()
+                .cloned()
+                .reduce(|acc, next| quote!(#acc + #next))
+                .unwrap_or_else(|| quote!(0));
+            previous_field_sizes.push(generate_type_bitsize(&field.ty));
+            field_offset
+        })
+        .collect();
+    if msb_first {
+        offsets.reverse();
+    }
+    offsets
+}
+
 pub(crate) fn generate_from_enum_impl(
     arb_int: &TokenStream, enum_type: &Ident, to_int_match_arms: Vec<TokenStream>, const_: &TokenStream,
 ) -> TokenStream {
@@ -130,10 +424,12 @@ pub fn last_ident_of_path(ty: &Type) -> Option<&Ident> {
     }
 }
 
-/// in enums, internal_bitsize <= 64; u64::MAX + 1 = u128
-/// therefore the bitshift would not overflow.
-pub fn enum_fills_bitsize(bitsize: u8, variants_count: usize) -> bool {
-    let max_variants_count = 1u128 << bitsize;
+/// in enums, internal_bitsize <= 128, so the bitshift might overflow.
+/// no enum can have 2^128 variants though, so these can't be filled.
+pub fn enum_fills_bitsize(bitsize: BitSize, variants_count: usize) -> bool {
+    let Some(max_variants_count) = 1u128.checked_shl(bitsize as u32) else {
+        return false;
+    };
     if variants_count as u128 > max_variants_count {
         abort_call_site!("enum overflows its bitsize"; help = "there should only be at most {} variants defined", max_variants_count);
     }
@@ -161,6 +457,26 @@ pub(crate) fn is_fallback_attribute(attr: &Attribute) -> bool {
     is_attribute(attr, "fallback")
 }
 
+/// `#[reset = 0x1F]` of `DefaultBits`, which `bitsize_internal` removes, since the struct no longer has that derive.
+pub(crate) fn is_reset_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("reset")
+}
+
+/// `#[serde_bits(repr = "int")]` of `SerializeBits` and `DeserializeBits`, which `bitsize_internal` removes as well.
+pub(crate) fn is_serde_bits_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("serde_bits")
+}
+
+/// `#[serde(rename = "..")]` of `SerializeBits` and `DeserializeBits` on a field, which shouldn't end up on its accessors.
+pub(crate) fn is_serde_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("serde")
+}
+
+/// `#[default = 3]` of `DefaultBits` on a field, which shouldn't end up on its accessors.
+pub(crate) fn is_default_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("default")
+}
+
 /// attempts to extract the bitsize from an ident equal to `uN` or `bool`.
 /// should return `Result` instead of `Option`, if we decide to add more descriptive error handling.
 pub fn bitsize_from_type_ident(type_name: &Ident) -> Option<BitSize> {
@@ -173,14 +489,14 @@ pub fn bitsize_from_type_ident(type_name: &Ident) -> Option<BitSize> {
         // parse() will reject letters and underscores, so this should be correct.
         let bitsize = suffix.parse().ok();
 
-        // the namespace contains u2 up to u{MAX_STRUCT_BIT_SIZE}. can't make assumptions about larger values
-        bitsize.filter(|&n| n <= MAX_STRUCT_BIT_SIZE)
+        // the namespace contains u2 up to u{MAX_INT_BIT_SIZE}. can't make assumptions about larger values
+        bitsize.filter(|&n| n <= MAX_INT_BIT_SIZE)
     } else {
         None
     }
 }
 
-pub fn to_int_match_arm(enum_name: &Ident, variant_name: &Ident, arb_int: &TokenStream, variant_value: Literal) -> TokenStream {
+pub fn to_int_match_arm(enum_name: &Ident, variant_name: &Ident, arb_int: &TokenStream, variant_value: TokenStream) -> TokenStream {
     quote! { #enum_name::#variant_name => #arb_int::new(#variant_value), }
 }
 
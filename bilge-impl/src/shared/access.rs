@@ -0,0 +1,84 @@
+use proc_macro2::TokenStream;
+use proc_macro_error2::abort;
+use quote::quote;
+use syn::{Attribute, Field, Ident, Meta};
+
+/// How a field may be accessed, given by `#[access(..)]` on the field.
+///
+/// This only controls which accessors get generated, constructors still take every (non-reserved) field.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// `rw`, the default: getter and setter
+    ReadWrite,
+    /// `ro`: only a getter
+    ReadOnly,
+    /// `wo`: only a setter
+    WriteOnly,
+    /// `w1c`, write-1-to-clear: a getter and `clear_x()`, which sets all bits of the field
+    WriteOneToClear,
+    /// `rc`, read-to-clear: only a getter, since reading already clears the field in hardware
+    ReadToClear,
+}
+
+impl Access {
+    pub fn of(field: &Field) -> Access {
+        let mut access_attrs = field.attrs.iter().filter(|attr| is_access_attribute(attr));
+        let Some(attr) = access_attrs.next() else {
+            return Access::ReadWrite;
+        };
+        if let Some(duplicate) = access_attrs.next() {
+            abort!(duplicate, "only one access policy per field is allowed")
+        }
+
+        let help = "use one of `#[access(rw)]`, `#[access(ro)]`, `#[access(wo)]`, `#[access(w1c)]` or `#[access(rc)]`";
+        let Meta::List(list) = &attr.meta else {
+            abort!(attr, "access policy is missing"; help = help)
+        };
+        let policy: Ident = syn::parse2(list.tokens.clone()).unwrap_or_else(|_| abort!(list.tokens, "access policy is invalid"; help = help));
+        match policy.to_string().as_str() {
+            "rw" => Access::ReadWrite,
+            "ro" => Access::ReadOnly,
+            "wo" => Access::WriteOnly,
+            "w1c" => Access::WriteOneToClear,
+            "rc" => Access::ReadToClear,
+            _ => abort!(policy, "access policy is invalid"; help = help),
+        }
+    }
+
+    /// the matching `bilge::Access`, used by `bilge::Layout`
+    pub fn to_runtime(self) -> TokenStream {
+        match self {
+            Access::ReadWrite => quote!(::bilge::Access::ReadWrite),
+            Access::ReadOnly => quote!(::bilge::Access::ReadOnly),
+            Access::WriteOnly => quote!(::bilge::Access::WriteOnly),
+            Access::WriteOneToClear => quote!(::bilge::Access::WriteOneToClear),
+            Access::ReadToClear => quote!(::bilge::Access::ReadToClear),
+        }
+    }
+
+    /// whether this field gets a getter
+    pub fn is_readable(self) -> bool {
+        !matches!(self, Access::WriteOnly)
+    }
+
+    /// whether this field gets a setter
+    pub fn is_writable(self) -> bool {
+        matches!(self, Access::ReadWrite | Access::WriteOnly)
+    }
+}
+
+pub fn is_access_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("access")
+}
+
+/// Reads a field inside of derives like `DebugBits`, which also need write-only fields.
+///
+/// Write-only fields don't have a getter, so we read them inline.
+pub fn generate_field_read(field: &Field, name: &Ident, offset: &TokenStream, repr: super::Repr) -> TokenStream {
+    if Access::of(field).is_readable() {
+        quote!(self.#name())
+    } else {
+        let getter_value = crate::bitsize_internal::struct_gen::generate_getter_value(&field.ty, offset, false, repr);
+        quote!({ #getter_value })
+    }
+}
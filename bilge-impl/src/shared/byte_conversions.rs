@@ -0,0 +1,188 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::Generics;
+
+use super::{is_wide, BitSize};
+
+/// Generates `from_le_bytes`, `from_be_bytes`, `to_le_bytes` and `to_be_bytes`, going through the
+/// `From`/`TryFrom` impls of the derive calling this, which is why `fallible` decides the return type.
+///
+/// With `is_const`, they are const: instead of `From`, they go through the raw conversions, like `__bilge_from_bits`
+/// (see `bitsize_internal`), which generic bitfields and some tagged enums don't have, see [`super::has_const_conversions`].
+/// `TryFrom` can't be replaced like that, so `from_*_bytes` stay non-const for it.
+///
+/// Bitfields are rounded up to whole bytes, the bits above the bitsize are ignored when reading.
+/// `try_from_le_slice` and `try_from_be_slice` read slices, which need to have exactly that many bytes.
+pub(crate) fn generate_byte_conversions(name: &Ident, generics: &Generics, bitsize: BitSize, fallible: bool, is_const: bool) -> TokenStream {
+    let byte_count = (bitsize as usize).div_ceil(8);
+    // `FromBits` of generic bitfields needs its type parameters to be filled, see `from_bits`
+    let generics = if fallible {
+        generics.clone()
+    } else {
+        super::generics_with_bound(generics, quote!(::bilge::Filled))
+    };
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let bytes_ty = quote!([u8; #byte_count]);
+
+    let const_if = |is_const: bool| {
+        if is_const || cfg!(feature = "nightly") {
+            quote!(const)
+        } else {
+            quote!()
+        }
+    };
+    let (from_const, to_const) = (const_if(is_const && !fallible), const_if(is_const));
+
+    let (result_ty, convert) = match (fallible, is_const, is_wide(bitsize)) {
+        (true, ..) => (quote!(::core::result::Result<Self, ::bilge::BitsError>), quote!(Self::try_from)),
+        (false, true, true) => (quote!(Self), quote!(Self::from_raw)),
+        (false, true, false) => (quote!(Self), quote!(Self::__bilge_from_bits)),
+        (false, false, _) => (quote!(Self), quote!(Self::from)),
+    };
+    // the raw value of wide bitfields are their bytes
+    let into_wide = if is_const {
+        quote!(self.to_raw())
+    } else {
+        quote!(<#bytes_ty>::from(self))
+    };
+
+    // wide bitfields are already backed by their little-endian bytes
+    let (from_le, from_be, to_le, to_be) = if is_wide(bitsize) {
+        let reverse = quote! {
+            let mut reversed = [0u8; #byte_count];
+            let mut i = 0;
+            while i < #byte_count {
+                reversed[i] = bytes[#byte_count - 1 - i];
+                i += 1;
+            }
+        };
+        (
+            quote!(#convert(bytes)),
+            quote! {
+                #reverse
+                #convert(reversed)
+            },
+            quote!(#into_wide),
+            quote! {
+                let bytes = #into_wide;
+                #reverse
+                reversed
+            },
+        )
+    } else {
+        let from_bytes = |index: TokenStream| {
+            if is_const && !fallible {
+                let bitsize = bitsize as usize;
+                return quote! {
+                    let mut value: u128 = 0;
+                    let mut i = 0;
+                    while i < #byte_count {
+                        value |= (bytes[#index] as u128) << (i * 8);
+                        i += 1;
+                    }
+                    // ignore the bits above our bitsize
+                    let value = value & (u128::MAX >> (u128::BITS as usize - #bitsize));
+                    #convert(value)
+                };
+            }
+            quote! {
+                type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
+                type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
+
+                let mut value: BaseIntOf<Self> = 0;
+                let mut i = 0;
+                while i < #byte_count {
+                    value |= (bytes[#index] as BaseIntOf<Self>) << (i * 8);
+                    i += 1;
+                }
+                // ignore the bits above our bitsize
+                let value = value & <ArbIntOf<Self> as Bitsized>::MAX.value();
+                #convert(<ArbIntOf<Self>>::new(value))
+            }
+        };
+        let to_bytes = |index: TokenStream| {
+            let value = if is_const {
+                quote!(self.__bilge_into_bits())
+            } else {
+                quote! {{
+                    type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
+                    <ArbIntOf<Self>>::from(self).value()
+                }}
+            };
+            quote! {
+                let value = #value;
+                let mut bytes = [0u8; #byte_count];
+                let mut i = 0;
+                while i < #byte_count {
+                    bytes[#index] = (value >> (i * 8)) as u8;
+                    i += 1;
+                }
+                bytes
+            }
+        };
+        (
+            from_bytes(quote!(i)),
+            from_bytes(quote!(#byte_count - 1 - i)),
+            to_bytes(quote!(i)),
+            to_bytes(quote!(#byte_count - 1 - i)),
+        )
+    };
+
+    let from_slice = |from_bytes: TokenStream| {
+        let from_bytes = if fallible {
+            quote!(Self::#from_bytes(bytes))
+        } else {
+            quote!(Ok(Self::#from_bytes(bytes)))
+        };
+        quote! {
+            if slice.len() != #byte_count {
+                return Err(::bilge::give_me_length_error(#byte_count, slice.len()));
+            }
+            // constness: `<[u8; N]>::try_from` is not const, so we're copying with a while loop
+            let mut bytes = [0u8; #byte_count];
+            let mut i = 0;
+            while i < #byte_count {
+                bytes[i] = slice[i];
+                i += 1;
+            }
+            #from_bytes
+        }
+    };
+    let (from_le_slice, from_be_slice) = (from_slice(quote!(from_le_bytes)), from_slice(quote!(from_be_bytes)));
+
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Creates this bitfield from its little-endian bytes, ignoring any bits above its bitsize.
+            #[allow(clippy::unnecessary_cast)]
+            pub #from_const fn from_le_bytes(bytes: #bytes_ty) -> #result_ty {
+                #from_le
+            }
+
+            /// Creates this bitfield from its big-endian bytes, ignoring any bits above its bitsize.
+            #[allow(clippy::unnecessary_cast)]
+            pub #from_const fn from_be_bytes(bytes: #bytes_ty) -> #result_ty {
+                #from_be
+            }
+
+            /// Creates this bitfield from exactly as many little-endian bytes as it has, see [`Self::from_le_bytes`].
+            pub #from_const fn try_from_le_slice(slice: &[u8]) -> ::core::result::Result<Self, ::bilge::BitsError> {
+                #from_le_slice
+            }
+
+            /// Creates this bitfield from exactly as many big-endian bytes as it has, see [`Self::from_be_bytes`].
+            pub #from_const fn try_from_be_slice(slice: &[u8]) -> ::core::result::Result<Self, ::bilge::BitsError> {
+                #from_be_slice
+            }
+
+            /// Returns the little-endian bytes of this bitfield, with any bits above its bitsize being zero.
+            pub #to_const fn to_le_bytes(self) -> #bytes_ty {
+                #to_le
+            }
+
+            /// Returns the big-endian bytes of this bitfield, with any bits above its bitsize being zero.
+            pub #to_const fn to_be_bytes(self) -> #bytes_ty {
+                #to_be
+            }
+        }
+    }
+}
@@ -1,56 +1,240 @@
-use proc_macro2::Literal;
+use proc_macro2::{Ident, Literal, TokenStream};
 use proc_macro_error2::abort;
-use syn::{Expr, ExprLit, Lit, Variant};
+use quote::{format_ident, quote};
+use syn::{Expr, ExprLit, ExprUnary, Fields, Lit, UnOp, Variant};
 
-use super::{unreachable, BitSize};
+use super::{unreachable, BitSize, MAX_INT_BIT_SIZE, MAX_REPR_ENUM_BIT_SIZE};
 
-pub(crate) struct DiscriminantAssigner {
+/// The value of a variant, given by its discriminant or by counting up from the previous variant.
+enum Value {
+    /// integer literals like `= 3` or `= -2`, which we can read ourselves
+    Known(u128),
+    /// everything else, like `= BASE + 3`, which only the compiler knows, see [`generate_discriminant_consts`]
+    Const(Ident),
+}
+
+pub(crate) struct DiscriminantAssigner<'a> {
     bitsize: BitSize,
-    next_expected_assignment: u128,
+    enum_name: &'a Ident,
+    /// `None` before the first variant
+    previous: Option<Value>,
+    /// whether the previous `Value::Known` is below zero, so counting up wraps around to zero, like in rust
+    previous_negative: bool,
+    /// the last discriminant only the compiler knows, with the number of variants counted up from it since
+    const_base: Option<(Expr, i128)>,
 }
 
-impl DiscriminantAssigner {
-    pub fn new(bitsize: u8) -> DiscriminantAssigner {
+impl<'a> DiscriminantAssigner<'a> {
+    pub fn new(bitsize: BitSize, enum_name: &'a Ident) -> DiscriminantAssigner<'a> {
         DiscriminantAssigner {
             bitsize,
-            next_expected_assignment: 0,
+            enum_name,
+            previous: None,
+            previous_negative: false,
+            const_base: None,
         }
     }
 
     fn max_value(&self) -> u128 {
-        (1u128 << self.bitsize) - 1
+        // `1 << 128` would overflow
+        u128::MAX >> (MAX_INT_BIT_SIZE - self.bitsize)
     }
 
-    fn value_from_discriminant(&self, variant: &Variant) -> Option<u128> {
-        let discriminant = variant.discriminant.as_ref()?;
-        let discriminant_expr = &discriminant.1;
-        let variant_name = &variant.ident;
+    /// Reads `3` as is and `-2` as two's complement, e.g. `0b1110` in a 4-bit enum.
+    fn known_value(&self, expr: &Expr, variant: &Variant) -> Option<u128> {
+        let exceeds = || abort!(variant, "Value of variant exceeds the given number of bits");
+        match expr {
+            Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) => {
+                let value: u128 = int.base10_parse().unwrap_or_else(unreachable);
+                if value > self.max_value() {
+                    exceeds()
+                }
+                Some(value)
+            }
+            Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => {
+                let Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) = &**expr else {
+                    return None;
+                };
+                let magnitude: u128 = int.base10_parse().unwrap_or_else(unreachable);
+                if magnitude > 1 << (self.bitsize - 1) {
+                    exceeds()
+                }
+                Some(magnitude.wrapping_neg() & self.max_value())
+            }
+            _ => None,
+        }
+    }
 
-        let Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) = discriminant_expr else {
-            abort!(
-                discriminant_expr,
-                "variant `{}` is not a number", variant_name;
-                help = "only literal integers currently supported"
-            )
+    /// Returns the variant's value, and for `Value::Const`, the expression defining that const.
+    fn assign(&mut self, variant: &Variant) -> (Value, Option<TokenStream>) {
+        // a fallback with value has every value without a variant, it doesn't have its own
+        if !matches!(variant.fields, Fields::Unit) {
+            return (Value::Known(0), None);
+        }
+        let const_name = || format_ident!("__BILGE_DISCRIMINANT_{}", variant.ident);
+        let (value, definition) = match &variant.discriminant {
+            Some((_, expr)) => match self.known_value(expr, variant) {
+                Some(value) => {
+                    self.previous_negative = value != 0 && matches!(expr, Expr::Unary(ExprUnary { op: UnOp::Neg(_), .. }));
+                    (Value::Known(value), None)
+                }
+                None => {
+                    self.const_base = Some((expr.clone(), 0));
+                    (Value::Const(const_name()), Some(self.generate_const(variant)))
+                }
+            },
+            None => match &self.previous {
+                None => (Value::Known(0), None),
+                Some(Value::Known(previous)) if self.previous_negative => {
+                    // e.g. `-1` is `0b1111` in a 4-bit enum, so the next variant is `0b0000`
+                    let value = previous.wrapping_add(1) & self.max_value();
+                    self.previous_negative = value != 0;
+                    (Value::Known(value), None)
+                }
+                Some(Value::Known(previous)) => {
+                    let value = previous
+                        .checked_add(1)
+                        .filter(|value| *value <= self.max_value())
+                        .unwrap_or_else(|| abort!(variant, "Value of variant exceeds the given number of bits"));
+                    (Value::Known(value), None)
+                }
+                Some(Value::Const(_)) => {
+                    let (_, offset) = self.const_base.as_mut().unwrap_or_else(|| unreachable(()));
+                    *offset += 1;
+                    (Value::Const(const_name()), Some(self.generate_const(variant)))
+                }
+            },
         };
+        self.previous = Some(match &value {
+            Value::Known(value) => Value::Known(*value),
+            Value::Const(name) => Value::Const(name.clone()),
+        });
+        (value, definition)
+    }
 
-        let discriminant_value: u128 = int.base10_parse().unwrap_or_else(unreachable);
-        if discriminant_value > self.max_value() {
-            abort!(variant, "Value of variant exceeds the given number of bits")
+    /// syn adds a suffix when printing Rust integers. we use an unsuffixed `Literal` for better-looking codegen
+    ///
+    /// Variants with a const discriminant refer to their const instead, which works as a pattern as well.
+    pub fn assign_unsuffixed(&mut self, variant: &Variant) -> TokenStream {
+        match self.assign(variant).0 {
+            Value::Known(value) => {
+                let value = Literal::u128_unsuffixed(value);
+                quote!(#value)
+            }
+            Value::Const(name) => {
+                let enum_name = self.enum_name;
+                quote!(#enum_name::#name)
+            }
         }
+    }
 
-        Some(discriminant_value)
+    /// `= BASE + 3` or `= -OFFSET` can be of any integer type, so we go through `i128` and keep the lowest bits.
+    ///
+    /// Variants without a discriminant count up from the last one in `i128` as well, so e.g. `-1` is followed by `0`, like in rust.
+    fn generate_const(&self, variant: &Variant) -> TokenStream {
+        let (expr, offset) = self.const_base.as_ref().unwrap_or_else(|| unreachable(()));
+        let bitsize = self.bitsize;
+        let message = format!("value of variant `{}` exceeds the given number of bits", variant.ident);
+        // all `i128` values fit into 128 bits
+        let range_check = if bitsize < MAX_INT_BIT_SIZE {
+            let min = Literal::i128_unsuffixed(-(1 << (bitsize - 1)));
+            let max = Literal::i128_unsuffixed(self.max_value() as i128);
+            quote!(::core::assert!(#min <= value && value <= #max, #message);)
+        } else {
+            quote!()
+        };
+        let offset = match offset {
+            0 => quote!(),
+            offset => {
+                let offset = Literal::i128_unsuffixed(*offset);
+                quote!(+ #offset)
+            }
+        };
+        let mask = Literal::u128_unsuffixed(self.max_value());
+        quote! {
+            let value = (#expr) as i128 #offset;
+            #range_check
+            (value as u128 & #mask) as <<Self as Bitsized>::ArbitraryInt as Integer>::UnderlyingType
+        }
     }
+}
+
+/// Rust reads discriminants as `isize`, so we can only keep them if they are literals fitting into it.
+/// Otherwise, `bitsize_internal` removes them. Either way, we check the uniqueness of their bits ourselves, see [`generate_discriminant_consts`].
+pub fn keeps_discriminants<'a>(bitsize: BitSize, mut variants: impl Iterator<Item = &'a Variant>) -> bool {
+    let is_literal = |expr: &Expr| match expr {
+        Expr::Lit(ExprLit { lit: Lit::Int(_), .. }) => true,
+        Expr::Unary(ExprUnary { op: UnOp::Neg(_), expr, .. }) => matches!(&**expr, Expr::Lit(ExprLit { lit: Lit::Int(_), .. })),
+        _ => false,
+    };
+    // enums with a fallback like `Unknown(u4)` would need a `#[repr]` to keep them
+    bitsize <= MAX_REPR_ENUM_BIT_SIZE
+        && variants.all(|variant| matches!(variant.fields, Fields::Unit) && variant.discriminant.as_ref().is_none_or(|(_, expr)| is_literal(expr)))
+}
 
-    fn assign(&mut self, variant: &Variant) -> u128 {
-        let value = self.value_from_discriminant(variant).unwrap_or(self.next_expected_assignment);
-        self.next_expected_assignment = value + 1;
-        value
+/// Discriminants like `= BASE + 3` are evaluated by the compiler, into a hidden associated const of the enum:
+/// ```ignore
+/// impl Opcode {
+///     const __BILGE_DISCRIMINANT_Load: u8 = { /* BASE + 3, checked to fit into the bitsize */ };
+/// }
+/// ```
+/// The derives match on these instead of on literals, see [`DiscriminantAssigner::assign_unsuffixed`].
+pub(crate) fn generate_discriminant_consts<'a>(bitsize: BitSize, enum_name: &Ident, variants: impl Iterator<Item = &'a Variant>) -> TokenStream {
+    let variants: Vec<_> = variants.collect();
+    let mut assigner = DiscriminantAssigner::new(bitsize, enum_name);
+    let mut consts = vec![];
+    let mut values = vec![];
+    for variant in &variants {
+        let (value, definition) = assigner.assign(variant);
+        // see `DiscriminantAssigner::assign`
+        if !matches!(variant.fields, Fields::Unit) {
+            continue;
+        }
+        match value {
+            Value::Known(value) => {
+                let value = Literal::u128_unsuffixed(value);
+                values.push(quote!(#value));
+            }
+            Value::Const(name) => {
+                values.push(quote!(#enum_name::#name as u128));
+                consts.push(quote! {
+                    #[doc(hidden)]
+                    #[allow(non_upper_case_globals)]
+                    const #name: <<Self as Bitsized>::ArbitraryInt as Integer>::UnderlyingType = { #definition };
+                });
+            }
+        }
     }
 
-    /// syn adds a suffix when printing Rust integers. we use an unsuffixed `Literal` for better-looking codegen
-    pub fn assign_unsuffixed(&mut self, variant: &Variant) -> Literal {
-        let next = self.assign(variant);
-        Literal::u128_unsuffixed(next)
+    let consts = if consts.is_empty() {
+        quote!()
+    } else {
+        quote! {
+            impl #enum_name {
+                #( #consts )*
+            }
+        }
+    };
+
+    // rust checks the discriminants it keeps, but not their lowest bits, so e.g. `-1` and `15` in 4 bits get past it
+    let count = values.len();
+    let uniqueness_check = quote! {
+        const _: () = {
+            let values: [u128; #count] = [#( #values ),*];
+            let mut i = 0;
+            while i < #count {
+                let mut j = i + 1;
+                while j < #count {
+                    ::core::assert!(values[i] != values[j], "enum variants need to have unique values");
+                    j += 1;
+                }
+                i += 1;
+            }
+        };
+    };
+
+    quote! {
+        #consts
+        #uniqueness_check
     }
 }
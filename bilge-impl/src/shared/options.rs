@@ -0,0 +1,98 @@
+use proc_macro2::{Literal, TokenStream, TokenTree};
+use proc_macro_error2::abort;
+use quote::{quote, ToTokens};
+use syn::{punctuated::Punctuated, Expr, ExprLit, Lit, Meta, Token};
+
+use super::BitSize;
+
+/// Everything after the size in `#[bitsize(8, option, ..)]`.
+///
+/// `bitsize` passes these on to `bitsize_internal`, so derives can read them as well.
+#[derive(Clone, Copy, Default)]
+pub struct BitsizeOptions {
+    /// `sealed`: `value` is only reachable through `to_raw()` and `unsafe from_raw_unchecked()`
+    pub sealed: bool,
+    /// `msb_first`: the first field starts at the most significant bit, see [`super::fields_in_layout_order`]
+    pub msb_first: bool,
+    /// `tag_bits = 4`: the size of the tag selecting the variant of a tagged enum, see [`super::tagged`]
+    pub tag_bits: Option<BitSize>,
+    /// `const_fields`: every field type is an integer, a `bool` or a `#[bitsize]` item, see [`super::Repr::is_const`]
+    pub const_fields: bool,
+}
+
+impl BitsizeOptions {
+    pub fn from_args(args: TokenStream) -> BitsizeOptions {
+        let (_, options) = split_bitsize_args(args);
+        let help =
+            "currently, `sealed`, `msb_first`, `const_fields` and `tag_bits = N` are supported, like this: `#[bitsize(32, sealed, msb_first)]`";
+        let options = syn::parse::Parser::parse2(Punctuated::<Meta, Token![,]>::parse_terminated, options.clone())
+            .unwrap_or_else(|_| abort!(options, "bitsize options are invalid"; help = help));
+
+        let mut parsed = BitsizeOptions::default();
+        for option in options {
+            let Some(name) = option.path().get_ident() else {
+                abort!(option, "bitsize options are invalid"; help = help)
+            };
+            match (name.to_string().as_str(), &option) {
+                ("sealed" | "msb_first" | "const_fields", Meta::Path(_)) => {
+                    let flag = match name.to_string().as_str() {
+                        "sealed" => &mut parsed.sealed,
+                        "msb_first" => &mut parsed.msb_first,
+                        _ => &mut parsed.const_fields,
+                    };
+                    if *flag {
+                        abort!(option, "bitsize option `{}` is given twice", name)
+                    }
+                    *flag = true;
+                }
+                ("tag_bits", Meta::NameValue(name_value)) => {
+                    let Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) = &name_value.value else {
+                        abort!(name_value.value, "`tag_bits` is not a number"; help = "define the size of the tag like this: `tag_bits = 4`")
+                    };
+                    if parsed.tag_bits.is_some() {
+                        abort!(option, "bitsize option `{}` is given twice", name)
+                    }
+                    let tag_bits = int.base10_parse().unwrap_or_else(|_| abort!(int, "`tag_bits` is not a valid number"));
+                    parsed.tag_bits = Some(tag_bits);
+                }
+                ("tag_bits", _) => abort!(option, "`tag_bits` is missing its size"; help = "define the size of the tag like this: `tag_bits = 4`"),
+                _ => abort!(option, "unknown bitsize option `{}`", option.to_token_stream(); help = help),
+            }
+        }
+        parsed
+    }
+}
+
+impl ToTokens for BitsizeOptions {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let BitsizeOptions {
+            sealed,
+            msb_first,
+            tag_bits,
+            const_fields,
+        } = self;
+        if *sealed {
+            tokens.extend(quote!(, sealed));
+        }
+        if *msb_first {
+            tokens.extend(quote!(, msb_first));
+        }
+        if *const_fields {
+            tokens.extend(quote!(, const_fields));
+        }
+        if let Some(tag_bits) = tag_bits {
+            let tag_bits = Literal::u16_unsuffixed(*tag_bits);
+            tokens.extend(quote!(, tag_bits = #tag_bits));
+        }
+    }
+}
+
+/// Splits `8, sealed` into the size `8` and the options `sealed`.
+pub fn split_bitsize_args(args: TokenStream) -> (TokenStream, TokenStream) {
+    let mut tokens = args.into_iter();
+    let bitsize = tokens
+        .by_ref()
+        .take_while(|token| !matches!(token, TokenTree::Punct(punct) if punct.as_char() == ','))
+        .collect();
+    (bitsize, tokens.collect())
+}
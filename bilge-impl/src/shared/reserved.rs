@@ -0,0 +1,89 @@
+use proc_macro2::TokenStream;
+use proc_macro_error2::abort;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Attribute, Expr, Field, Meta};
+
+use super::{bitsize_from_type_ident, last_ident_of_path, unreachable};
+
+/// The value a `reserved` or `padding` field needs to have, given by `#[reserved(..)]` on the field.
+///
+/// `TryFrom` rejects any other value, while `new`, the builder and `Default` write this one.
+pub enum ReservedValue {
+    /// `zero`: all bits cleared, which is what `new` writes anyway
+    Zero,
+    /// `ones`: all bits set
+    Ones,
+    /// `value = 0b10`: any constant fitting into the field
+    Value(Expr),
+}
+
+impl ReservedValue {
+    /// `None` for fields without `#[reserved(..)]`.
+    ///
+    /// `bitsize` already checked the attribute with [`ReservedValue::check`], so this doesn't report anything twice.
+    pub fn of(field: &Field) -> Option<ReservedValue> {
+        let mut reserved_attrs = field.attrs.iter().filter(|attr| is_reserved_attribute(attr));
+        let attr = reserved_attrs.next()?;
+        if let Some(duplicate) = reserved_attrs.next() {
+            abort!(duplicate, "only one required value per field is allowed")
+        }
+
+        let help = "use one of `#[reserved(zero)]`, `#[reserved(ones)]` or `#[reserved(value = 0b10)]`";
+        let Meta::List(list) = &attr.meta else {
+            abort!(attr, "required value is missing"; help = help)
+        };
+        let rule: Meta = syn::parse2(list.tokens.clone()).unwrap_or_else(|_| abort!(list.tokens, "required value is invalid"; help = help));
+        match &rule {
+            Meta::Path(path) if path.is_ident("zero") => Some(ReservedValue::Zero),
+            Meta::Path(path) if path.is_ident("ones") => Some(ReservedValue::Ones),
+            Meta::NameValue(name_value) if name_value.path.is_ident("value") => Some(ReservedValue::Value(name_value.value.clone())),
+            _ => abort!(rule, "required value is invalid"; help = help),
+        }
+    }
+
+    /// Validates `#[reserved(..)]` on a field, which `bitsize` already renamed to `reserved_i` and so on.
+    ///
+    /// Returns a compile-time check that the value fits into the field.
+    pub fn check(field: &Field) -> TokenStream {
+        let Some(reserved_value) = ReservedValue::of(field) else {
+            return quote!();
+        };
+        let name = field.ident.as_ref().map(|ident| ident.to_string()).unwrap_or_default();
+        if !(name.starts_with("reserved_") || name.starts_with("padding_")) {
+            let attr = field
+                .attrs
+                .iter()
+                .find(|attr| is_reserved_attribute(attr))
+                .unwrap_or_else(|| unreachable(()));
+            abort!(attr, "only `reserved` and `padding` fields can have a required value"; help = "rename this field to `reserved`")
+        }
+        if last_ident_of_path(&field.ty).and_then(bitsize_from_type_ident).is_none() {
+            abort!(field.ty, "required values need an integer field"; help = "use a type like `u4` or `bool`")
+        }
+
+        let ReservedValue::Value(value) = reserved_value else {
+            return quote!();
+        };
+        let ty = &field.ty;
+        quote_spanned! {value.span()=>
+            const _: () = assert!(
+                { let value = #value; value as u128 } <= u128::MAX >> (128 - <#ty as ::bilge::Bitsized>::BITS),
+                "required value doesn't fit into its field"
+            );
+        }
+    }
+
+    /// The raw value of a field of type `ty` as `u128`.
+    pub fn to_u128(&self, ty: &syn::Type) -> TokenStream {
+        match self {
+            ReservedValue::Zero => quote!(0u128),
+            ReservedValue::Ones => quote!((u128::MAX >> (128 - <#ty as Bitsized>::BITS))),
+            // bound first, as casting a literal like `0b10` directly would be linted
+            ReservedValue::Value(value) => quote!({ let value = #value; value as u128 }),
+        }
+    }
+}
+
+pub fn is_reserved_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("reserved")
+}
@@ -0,0 +1,399 @@
+use proc_macro2::{Ident, Literal, TokenStream};
+use proc_macro_error2::{abort, abort_call_site};
+use quote::{quote, ToTokens};
+use syn::{Attribute, Expr, ExprLit, Fields, Lit, Meta, Type, Variant};
+
+use super::{fallback::Fallback, is_fallback_attribute, options::BitsizeOptions, unreachable, BitSize};
+
+/// A variant of a tagged enum, like `#[tag = 0b0011] Load(LoadFields)`.
+pub struct TaggedVariant<'a> {
+    ident: &'a Ident,
+    tag: u128,
+    /// `None` for unit variants
+    payload: Option<&'a Type>,
+}
+
+/// An enum like
+/// ```ignore
+/// #[bitsize(32, tag_bits = 4)]
+/// #[derive(TryFromBits)]
+/// enum Instruction {
+///     #[tag = 0b0000]
+///     Nop,
+///     #[tag = 0b0011]
+///     Load(LoadFields),
+/// }
+/// ```
+/// where the tag selects the variant and the remaining bits hold its payload.
+/// The tag takes the lowest bits, or the highest bits with `msb_first`.
+///
+/// Payloads need to fill all bits next to the tag, see [`Tagged::generate_payload_size_checks`].
+/// Unit variants set these bits to zero when converting back to an integer. `TryFromBits` rejects any other value
+/// in them, while `FromBits` ignores them.
+pub struct Tagged<'a> {
+    tag_bits: BitSize,
+    payload_bits: BitSize,
+    msb_first: bool,
+    /// see [`BitsizeOptions::const_fields`]
+    const_fields: bool,
+    variants: Vec<TaggedVariant<'a>>,
+}
+
+impl<'a> Tagged<'a> {
+    /// Returns `None` for enums without `tag_bits`, which are handled like any other enum.
+    ///
+    /// The fallback is validated by the derives, see `fallback::fallback_variant`.
+    pub fn analyze(variants: impl Iterator<Item = &'a Variant>, bitsize: BitSize, options: BitsizeOptions) -> Option<Tagged<'a>> {
+        let variants: Vec<_> = variants.collect();
+        let Some(tag_bits) = options.tag_bits else {
+            if let Some(attr) = variants.iter().flat_map(|variant| &variant.attrs).find(|attr| is_tag_attribute(attr)) {
+                abort!(attr, "`#[tag]` is missing the size of the tag"; help = "add it to the bitsize like this: `#[bitsize(32, tag_bits = 4)]`")
+            }
+            return None;
+        };
+        if tag_bits == 0 || tag_bits >= bitsize {
+            abort_call_site!("`tag_bits` needs to be between 1 and {}", bitsize - 1; help = "the payload needs at least one bit next to the tag")
+        }
+
+        let max_tag = u128::MAX >> (u128::BITS as BitSize - tag_bits);
+        let mut tagged_variants: Vec<TaggedVariant> = vec![];
+        for variant in variants {
+            let tag_attr = variant.attrs.iter().find(|attr| is_tag_attribute(attr));
+            if variant.attrs.iter().any(is_fallback_attribute) {
+                if let Some(attr) = tag_attr {
+                    abort!(attr, "a fallback can't have a tag"; help = "it is used for all tags without a variant")
+                }
+                if matches!(variant.fields, Fields::Unit) {
+                    abort!(variant, "the fallback of a tagged enum needs to keep its value"; help = "use a fallback with value, like `#[fallback] Unknown(uN)`")
+                }
+                continue;
+            }
+            if let Some((eq, _)) = &variant.discriminant {
+                abort!(eq, "variants of tagged enums can't have discriminants"; help = "use `#[tag = ..]` instead")
+            }
+            let Some(attr) = tag_attr else {
+                abort!(variant, "variant has no tag"; help = "add one like this: `#[tag = 0b0011]`")
+            };
+            let tag = tag_value(attr);
+            if tag > max_tag {
+                abort!(attr, "tag exceeds the given `tag_bits`"; help = "tags need to fit into {} bits", tag_bits)
+            }
+            if let Some(other) = tagged_variants.iter().find(|other| other.tag == tag) {
+                abort!(attr, "tag is already used by `{}`", other.ident)
+            }
+            let payload = match &variant.fields {
+                Fields::Unit => None,
+                Fields::Unnamed(fields) if fields.unnamed.len() == 1 => Some(&fields.unnamed[0].ty),
+                _ => {
+                    abort!(variant.fields, "tagged variants can only hold a single payload"; help = "use a bitfield struct as payload, like `Load(LoadFields)`")
+                }
+            };
+            tagged_variants.push(TaggedVariant {
+                ident: &variant.ident,
+                tag,
+                payload,
+            });
+        }
+
+        Some(Tagged {
+            tag_bits,
+            payload_bits: bitsize - tag_bits,
+            msb_first: options.msb_first,
+            const_fields: options.const_fields,
+            variants: tagged_variants,
+        })
+    }
+
+    /// Whether the payloads can be converted in const fns, which [`Tagged::generate_bits_conversions`] needs.
+    pub fn has_const_payloads(&self) -> bool {
+        let payloads = self.variants.iter().filter_map(|variant| variant.payload);
+        super::has_const_types(payloads, self.const_fields)
+    }
+
+    /// Whether every tag has a variant, which `FromBits` needs unless there is a fallback.
+    pub fn covers_all_tags(&self) -> bool {
+        self.variants.len() as u128 == 1 << self.tag_bits
+    }
+
+    /// Payloads are stored next to the tag, so they need to be exactly that big.
+    pub fn generate_payload_size_checks(&self, enum_name: &Ident) -> TokenStream {
+        let payload_bits = self.payload_bits as usize;
+        let checks = self.variants.iter().filter_map(|variant| {
+            let ty = variant.payload?;
+            let message = format!(
+                "payload of `{}::{}` needs to have {} bits, to fill the bits next to the tag",
+                enum_name, variant.ident, payload_bits
+            );
+            Some(quote! {
+                const _: () = ::core::assert!(<#ty as Bitsized>::BITS == #payload_bits, #message);
+            })
+        });
+        quote!(#( #checks )*)
+    }
+
+    /// `Validity::Tagged`, accepting the tags of all variants with a valid payload, and any other tag with a fallback.
+    ///
+    /// Like `TryFromBits`, unit variants only accept zero next to their tag.
+    pub fn generate_validity(&self, has_fallback: bool) -> TokenStream {
+        let (tag_offset, payload_offset) = if self.msb_first {
+            (self.payload_bits as usize, 0)
+        } else {
+            (0, self.tag_bits as usize)
+        };
+        let (tag_bits, payload_bits) = (self.tag_bits as usize, self.payload_bits as usize);
+        let variants = self.variants.iter().map(|TaggedVariant { tag, payload, .. }| {
+            let tag = Literal::u128_unsuffixed(*tag);
+            match payload {
+                Some(ty) => quote!((#tag, &<#ty as Bitsized>::VALIDITY)),
+                None => quote!((#tag, &::bilge::Validity::OneOf(&[0]))),
+            }
+        });
+        quote! {
+            ::bilge::Validity::Tagged {
+                tag_offset: #tag_offset,
+                tag_bits: #tag_bits,
+                payload_offset: #payload_offset,
+                payload_bits: #payload_bits,
+                variants: &[#( #variants ),*],
+                has_fallback: #has_fallback,
+            }
+        }
+    }
+
+    /// `__bilge_from_bits` and `__bilge_into_bits`, the const conversions every `#[bitsize]` item has, see `bitsize_internal`.
+    ///
+    /// `fallback` is the fallback variant with the type of its value, since `bitsize_internal` has no `Fallback`.
+    /// Without [`Tagged::has_const_payloads`], there are none and the derives go through `From` instead.
+    pub fn generate_bits_conversions(&self, fallback: Option<(&Ident, &Type)>) -> TokenStream {
+        if !self.has_const_payloads() {
+            return quote!();
+        }
+        let (split, tag_shift, payload_shift) = self.split_and_shifts();
+        let shifted = |value: TokenStream, shift: &Option<Literal>| match shift {
+            Some(shift) => quote!((#value << #shift)),
+            None => value,
+        };
+
+        let from_bits_arms = self.variants.iter().map(|TaggedVariant { ident, tag, payload }| {
+            let tag = Literal::u128_unsuffixed(*tag);
+            match payload {
+                Some(ty) => {
+                    let payload = super::generate_const_from_bits(ty, quote!(payload));
+                    quote!(#tag => Self::#ident(#payload),)
+                }
+                None => quote!(#tag => Self::#ident,),
+            }
+        });
+        let into_bits_arms = self.variants.iter().map(|TaggedVariant { ident, tag, payload }| {
+            let tag = shifted(Literal::u128_unsuffixed(*tag).into_token_stream(), &tag_shift);
+            match payload {
+                Some(ty) => {
+                    let payload = shifted(super::generate_const_into_bits(ty, quote!((*payload))), &payload_shift);
+                    quote!(Self::#ident(payload) => #tag | #payload,)
+                }
+                None => quote!(Self::#ident => #tag,),
+            }
+        });
+        let (catch_all_arm, fallback_into_bits_arm) = match fallback {
+            Some((fallback_ident, ty)) => {
+                let from_bits = super::generate_const_from_bits(ty, quote!(value));
+                let into_bits = super::generate_const_into_bits(ty, quote!((*number)));
+                (
+                    quote!(_ => Self::#fallback_ident(#from_bits),),
+                    quote!(Self::#fallback_ident(number) => #into_bits,),
+                )
+            }
+            None => (quote!(_ => ::core::panic!("unreachable: every tag has a variant"),), quote!()),
+        };
+
+        quote! {
+            #[doc(hidden)]
+            #[allow(clippy::unnecessary_cast)]
+            pub const fn __bilge_from_bits(bits: u128) -> Self {
+                let value = bits;
+                #split
+                match tag {
+                    #( #from_bits_arms )*
+                    #catch_all_arm
+                }
+            }
+
+            #[doc(hidden)]
+            #[allow(clippy::unnecessary_cast)]
+            pub const fn __bilge_into_bits(&self) -> u128 {
+                match self {
+                    #( #into_bits_arms )*
+                    #fallback_into_bits_arm
+                }
+            }
+        }
+    }
+
+    /// Splits `value` into `let tag` and `let payload`, and returns how far each is shifted into the value,
+    /// `None` for the one starting at bit 0.
+    fn split_and_shifts(&self) -> (TokenStream, Option<Literal>, Option<Literal>) {
+        let payload_bits = Literal::u16_unsuffixed(self.payload_bits);
+        let tag_bits = Literal::u16_unsuffixed(self.tag_bits);
+        if self.msb_first {
+            let payload_mask = Literal::u128_unsuffixed(u128::MAX >> (u128::BITS as BitSize - self.payload_bits));
+            let split = quote! {
+                let tag = value >> #payload_bits;
+                let payload = value & #payload_mask;
+            };
+            (split, Some(payload_bits), None)
+        } else {
+            let tag_mask = Literal::u128_unsuffixed(u128::MAX >> (u128::BITS as BitSize - self.tag_bits));
+            let split = quote! {
+                let tag = value & #tag_mask;
+                let payload = value >> #tag_bits;
+            };
+            (split, None, Some(tag_bits))
+        }
+    }
+
+    /// Generates `From<uN>` or `TryFrom<uN>` for the enum, and `From<Enum>` for `uN`.
+    ///
+    /// `FromBits` needs every payload to be `From<uN>`, `TryFromBits` uses their `TryFrom<uN>`
+    /// and additionally rejects tags without a variant.
+    pub fn generate_conversions(&self, enum_name: &Ident, arb_int: &TokenStream, fallback: Option<&Fallback>, fallible: bool) -> TokenStream {
+        let const_ = if cfg!(feature = "nightly") { quote!(const) } else { quote!() };
+
+        let (split, tag_shift, payload_shift) = self.split_and_shifts();
+        let shifted = |value: TokenStream, shift: &Option<Literal>| match shift {
+            Some(shift) => quote!((#value << #shift)),
+            None => value,
+        };
+
+        let payload_offset = payload_shift.clone().unwrap_or_else(|| Literal::u16_unsuffixed(0));
+        let from_int_arms = self.variants.iter().map(|TaggedVariant { ident, tag, payload }| {
+            let tag = Literal::u128_unsuffixed(*tag);
+            let variant_name = ident.to_string();
+            let Some(ty) = payload else {
+                if !fallible {
+                    return quote!(#tag => Self::#ident,);
+                }
+                return quote! {
+                    #tag => match payload {
+                        0 => Ok(Self::#ident),
+                        _ => {
+                            let path = [::bilge::PathSegment::Variant(#variant_name)];
+                            Err(::bilge::give_me_field_error(&path, #payload_offset, payload as u128))
+                        }
+                    },
+                };
+            };
+            let payload = quote!(<ArbIntOf<#ty>>::new(payload as BaseIntOf<#ty>));
+            if fallible {
+                quote! {
+                    #tag => match <#ty as ::core::convert::TryFrom<ArbIntOf<#ty>>>::try_from(#payload) {
+                        Ok(payload) => Ok(Self::#ident(payload)),
+                        Err(error) => {
+                            use ::bilge::NestBitsError as _;
+                            let path = [::bilge::PathSegment::Variant(#variant_name)];
+                            Err((&::bilge::FieldError(error)).nest(&path, #payload_offset, payload as u128))
+                        }
+                    },
+                }
+            } else {
+                quote!(#tag => Self::#ident(<#ty as ::core::convert::From<ArbIntOf<#ty>>>::from(#payload)),)
+            }
+        });
+
+        let catch_all_arm = match fallback {
+            Some(Fallback::WithValue(fallback_ident)) => quote!(_ => Self::#fallback_ident(number),),
+            Some(Fallback::Unit(_)) => unreachable(()),
+            None if fallible => quote!(_ => Err(::bilge::give_me_value_error(value as u128)),),
+            None => quote! {
+                // constness: unreachable!() is not const yet
+                _ => ::core::panic!("unreachable: every tag has a variant"),
+            },
+        };
+
+        let to_int_arms = self.variants.iter().map(|TaggedVariant { ident, tag, payload }| {
+            let tag = shifted(Literal::u128_unsuffixed(*tag).into_token_stream(), &tag_shift);
+            match payload {
+                Some(ty) => {
+                    let payload = shifted(quote!(payload), &payload_shift);
+                    quote! {
+                        #enum_name::#ident(payload) => {
+                            let payload = <ArbIntOf<#ty>>::from(payload).value() as BaseIntOf<#enum_name>;
+                            #arb_int::new(#tag | #payload)
+                        }
+                    }
+                }
+                None => quote!(#enum_name::#ident => #arb_int::new(#tag),),
+            }
+        });
+        let fallback_to_int_arm = match fallback {
+            Some(Fallback::WithValue(fallback_ident)) => quote!(#enum_name::#fallback_ident(number) => number,),
+            _ => quote!(),
+        };
+
+        let aliases = quote! {
+            type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
+            type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
+        };
+        let from_int_impl = if fallible {
+            quote! {
+                impl #const_ ::core::convert::TryFrom<#arb_int> for #enum_name {
+                    type Error = ::bilge::BitsError;
+
+                    fn try_from(number: #arb_int) -> ::core::result::Result<Self, Self::Error> {
+                        #aliases
+                        let value = number.value();
+                        #split
+                        match tag {
+                            #( #from_int_arms )*
+                            #catch_all_arm
+                        }
+                    }
+                }
+            }
+        } else {
+            quote! {
+                impl #const_ ::core::convert::From<#arb_int> for #enum_name {
+                    fn from(number: #arb_int) -> Self {
+                        #aliases
+                        let value = number.value();
+                        #split
+                        match tag {
+                            #( #from_int_arms )*
+                            #catch_all_arm
+                        }
+                    }
+                }
+            }
+        };
+
+        quote! {
+            #from_int_impl
+
+            impl #const_ ::core::convert::From<#enum_name> for #arb_int {
+                fn from(enum_value: #enum_name) -> Self {
+                    #aliases
+                    match enum_value {
+                        #( #to_int_arms )*
+                        #fallback_to_int_arm
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn is_tag_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("tag")
+}
+
+/// Reads `#[tag = 0b0011]`.
+fn tag_value(attr: &Attribute) -> u128 {
+    let help = "tags need to be integer literals, like `#[tag = 0b0011]`";
+    let Meta::NameValue(name_value) = &attr.meta else {
+        abort!(attr, "tag is missing its value"; help = help)
+    };
+    let Expr::Lit(ExprLit { lit: Lit::Int(int), .. }) = &name_value.value else {
+        abort!(name_value.value, "tag is not a number"; help = help)
+    };
+    int.base10_parse()
+        .unwrap_or_else(|_| abort!(int, "tag is not a valid number"; help = help))
+}
@@ -1,4 +1,7 @@
-use syn::Path;
+use quote::ToTokens;
+#[cfg(test)]
+use syn::parse_quote;
+use syn::{Path, Type};
 #[cfg(test)]
 use syn_path::path;
 
@@ -44,6 +47,37 @@ impl PathExt for Path {
     }
 }
 
+/// `[u2; 4]` instead of the `[u2 ; 4]` which `ToTokens` would give us, for readable type names.
+pub fn type_name(ty: &Type) -> String {
+    let name = ty.to_token_stream().to_string();
+    [
+        (" ;", ";"),
+        (" ,", ","),
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" < ", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        ("& ", "&"),
+    ]
+    .iter()
+    .fold(name, |name, (from, to)| name.replace(from, to))
+}
+
+#[test]
+fn type_names() {
+    let types: [(Type, &str); 4] = [
+        (parse_quote!(u4), "u4"),
+        (parse_quote!([u2; 4]), "[u2; 4]"),
+        (parse_quote!((bool, [Mode; 2])), "(bool, [Mode; 2])"),
+        (parse_quote!(registers::Entry<T, 4>), "registers::Entry<T, 4>"),
+    ];
+
+    for (ty, name) in types {
+        assert_eq!(type_name(&ty), name);
+    }
+}
+
 #[test]
 fn path_matching() {
     let paths = [
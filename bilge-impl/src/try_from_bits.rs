@@ -1,23 +1,49 @@
 use proc_macro2::{Ident, TokenStream};
 use proc_macro_error2::{abort, emit_call_site_warning};
-use quote::quote;
-use syn::{punctuated::Iter, Data, DeriveInput, Fields, Type, Variant};
+use quote::{format_ident, quote};
+use syn::{punctuated::Iter, Data, DeriveInput, Field, Fields, Generics, Type, Variant};
 
-use crate::shared::{self, discriminant_assigner::DiscriminantAssigner, enum_fills_bitsize, fallback::Fallback, unreachable, BitSize};
-use crate::shared::{bitsize_from_type_ident, last_ident_of_path};
+use crate::bitsize_internal::{field_name, struct_gen};
+use crate::shared::{
+    self, byte_conversions, discriminant_assigner::DiscriminantAssigner, enum_fills_bitsize, fallback::Fallback, reserved::ReservedValue,
+    tagged::Tagged, unreachable, BitSize, Repr,
+};
 
 pub(super) fn try_from_bits(item: TokenStream) -> TokenStream {
     let derive_input = parse(item);
     let (derive_data, arb_int, name, internal_bitsize, ..) = analyze(&derive_input);
-    match derive_data {
-        Data::Struct(ref data) => codegen_struct(arb_int, name, &data.fields),
+    let options = shared::options_from_internal_attr(&derive_input.attrs);
+    let expanded = match derive_data {
+        Data::Struct(ref data) => {
+            let offsets = shared::generate_field_offsets(&data.fields, options.msb_first);
+            let mut fields: Vec<_> = data
+                .fields
+                .iter()
+                .zip(offsets)
+                .enumerate()
+                .map(|(i, (field, offset))| (field, field_name(field, i), offset))
+                .collect();
+            // in layout order, like `shared::fields_in_layout_order`
+            if options.msb_first {
+                fields.reverse();
+            }
+            let repr = Repr::new(internal_bitsize, &derive_input.generics, &data.fields, options);
+            codegen_struct(arb_int, name, &derive_input.generics, &fields, repr)
+        }
         Data::Enum(ref enum_data) => {
-            let variants = enum_data.variants.iter();
-            let match_arms = analyze_enum(variants, name, internal_bitsize, &arb_int);
-            codegen_enum(arb_int, name, match_arms)
+            if let Some(tagged) = Tagged::analyze(enum_data.variants.iter(), internal_bitsize, options) {
+                tagged.generate_conversions(name, &arb_int, None, true)
+            } else {
+                let variants = enum_data.variants.iter();
+                let match_arms = analyze_enum(variants, name, internal_bitsize, &arb_int);
+                codegen_enum(arb_int, name, match_arms)
+            }
         }
         _ => unreachable(()),
-    }
+    };
+    let is_const = shared::has_const_conversions(&derive_input, internal_bitsize);
+    let byte_conversions = byte_conversions::generate_byte_conversions(name, &derive_input.generics, internal_bitsize, true, is_const);
+    quote!(#expanded #byte_conversions)
 }
 
 fn parse(item: TokenStream) -> DeriveInput {
@@ -35,7 +61,7 @@ fn analyze_enum(variants: Iter<Variant>, name: &Ident, internal_bitsize: BitSize
         emit_call_site_warning!("enum fills its bitsize"; help = "you can use `#[derive(FromBits)]` instead, rust will provide `TryFrom` for you (so you don't necessarily have to update call-sites)");
     }
 
-    let mut assigner = DiscriminantAssigner::new(internal_bitsize);
+    let mut assigner = DiscriminantAssigner::new(internal_bitsize, name);
 
     variants
         .map(|variant| {
@@ -66,7 +92,7 @@ fn codegen_enum(arb_int: TokenStream, enum_type: &Ident, match_arms: (Vec<TokenS
             fn try_from(number: #arb_int) -> ::core::result::Result<Self, Self::Error> {
                 match number.value() {
                     #( #from_int_match_arms )*
-                    i => Err(::bilge::give_me_error()),
+                    i => Err(::bilge::give_me_value_error(i as u128)),
                 }
             }
         }
@@ -76,58 +102,135 @@ fn codegen_enum(arb_int: TokenStream, enum_type: &Ident, match_arms: (Vec<TokenS
     }
 }
 
-fn generate_field_check(ty: &Type) -> TokenStream {
-    // Yes, this is hacky module management.
-    crate::bitsize_internal::struct_gen::generate_getter_inner(ty, false)
+/// Checks the field or element of type `ty` at the cursor, returning a `BitsError` with its `path` and bit `offset` if it's invalid.
+///
+/// `depth` counts the arrays around this element, so nested arrays get their own index variable.
+fn generate_field_check(ty: &Type, path: &[TokenStream], offset: &TokenStream, depth: usize, repr: Repr) -> TokenStream {
+    use Type::*;
+    if !needs_check(ty) {
+        // skip the obviously filled values
+        let size = shared::generate_type_bitsize(ty);
+        return struct_gen::skip_cursor(&size, repr.wide);
+    }
+    match ty {
+        Tuple(tuple) => {
+            let mut elem_offset = offset.clone();
+            let checks = tuple.elems.iter().enumerate().map(|(i, elem)| {
+                let segment = i.to_string();
+                let path = [path, &[quote!(::bilge::PathSegment::Field(#segment))]].concat();
+                let check = generate_field_check(elem, &path, &elem_offset, depth, repr);
+                let size = shared::generate_type_bitsize(elem);
+                elem_offset = quote!((#elem_offset + #size));
+                check
+            });
+            quote!( #( #checks )* )
+        }
+        Array(array) => {
+            let index = format_ident!("index_{}", depth);
+            let (len, elem_ty) = (&array.len, &*array.elem);
+            let elem_size = shared::generate_type_bitsize(elem_ty);
+            let path = [path, &[quote!(::bilge::PathSegment::Index(#index))]].concat();
+            let elem_offset = quote!((#offset + #index * #elem_size));
+            let check = generate_field_check(elem_ty, &path, &elem_offset, depth + 1, repr);
+            quote! { {
+                // constness: iter, for-loop, range are not const, so we're using while loops
+                let mut #index = 0;
+                while #index < #len {
+                    #check
+                    #index += 1;
+                }
+            } }
+        }
+        Path(_) => {
+            let elem_value = struct_gen::generate_elem_value(ty, repr);
+            quote! { {
+                #elem_value
+                // note this is available even if the type is `From`
+                if let Err(error) = <#ty>::try_from(elem_value) {
+                    let path = [#( #path ),*];
+                    let value = <ArbIntOf<#ty> as Integer>::as_u128(elem_value);
+                    return Err((&::bilge::FieldError(error)).nest(&path, #offset, value));
+                }
+            } }
+        }
+        _ => unreachable(()),
+    }
 }
 
-fn codegen_struct(arb_int: TokenStream, struct_type: &Ident, fields: &Fields) -> TokenStream {
-    let is_ok: TokenStream = fields
-        .iter()
-        .map(|field| {
-            let ty = &field.ty;
-            let size_from_type = last_ident_of_path(ty).and_then(bitsize_from_type_ident);
-            if let Some(size) = size_from_type {
-                quote! { {
-                    // we still need to shift by the element's size
-                    let size = #size;
-                    cursor = cursor.wrapping_shr(size as u32);
-                    true
-                } }
-            } else {
-                generate_field_check(ty)
-            }
-        })
-        .reduce(|acc, next| quote!((#acc && #next)))
-        // `Struct {}` would be handled like this:
-        .unwrap_or_else(|| quote!(true));
+/// Checks that a `reserved` field holds its `#[reserved(..)]` value.
+fn generate_reserved_check(ty: &Type, reserved_value: &ReservedValue, path: &[TokenStream], offset: &TokenStream, repr: Repr) -> TokenStream {
+    let elem_value = struct_gen::generate_elem_value(ty, repr);
+    let required = reserved_value.to_u128(ty);
+    quote! { {
+        #elem_value
+        let value = <ArbIntOf<#ty> as Integer>::as_u128(elem_value);
+        if value != #required {
+            return Err(::bilge::give_me_field_error(&[#( #path ),*], #offset, value));
+        }
+    } }
+}
+
+/// Whether `ty` contains anything which can be unfilled, like enums or structs.
+fn needs_check(ty: &Type) -> bool {
+    match ty {
+        Type::Tuple(tuple) => tuple.elems.iter().any(needs_check),
+        Type::Array(array) => needs_check(&array.elem),
+        _ => !shared::is_always_filled(ty),
+    }
+}
+
+/// `fields` need to be in layout order, since the cursor moves from bit 0 upwards.
+fn codegen_struct(
+    arb_int: TokenStream, struct_type: &Ident, generics: &Generics, fields: &[(&Field, Ident, TokenStream)], repr: Repr,
+) -> TokenStream {
+    let checks = fields.iter().map(|(field, name, offset)| {
+        let name = name.to_string();
+        let path = [quote!(::bilge::PathSegment::Field(#name))];
+        match ReservedValue::of(field) {
+            Some(reserved_value) => generate_reserved_check(&field.ty, &reserved_value, &path, offset, repr),
+            None => generate_field_check(&field.ty, &path, offset, 0, repr),
+        }
+    });
 
     let const_ = if cfg!(feature = "nightly") { quote!(const) } else { quote!() };
 
+    let cursor_init = if repr.wide {
+        quote! {
+            // cursor is the bit position in value's bytes, starting at the first field
+            let bytes = &value;
+            let mut cursor = 0;
+        }
+    } else {
+        quote! {
+            // cursor starts at value's first field
+            let mut cursor = value.value();
+        }
+    };
+
+    let init = shared::generate_struct_init(generics);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
     quote! {
-        impl #const_ ::core::convert::TryFrom<#arb_int> for #struct_type {
+        impl #impl_generics #const_ ::core::convert::TryFrom<#arb_int> for #struct_type #ty_generics #where_clause {
             type Error = ::bilge::BitsError;
 
-            // validates all values, which means enums, even in inner structs (TODO: and reserved fields?)
+            // validates all values, which means enums, even in inner structs, and `#[reserved(..)]` fields,
+            // and returns the first invalid one
             fn try_from(value: #arb_int) -> ::core::result::Result<Self, Self::Error> {
+                use ::bilge::NestBitsError as _;
                 type ArbIntOf<T> = <T as Bitsized>::ArbitraryInt;
                 type BaseIntOf<T> = <ArbIntOf<T> as Integer>::UnderlyingType;
 
-                // cursor starts at value's first field
-                let mut cursor = value.value();
+                #cursor_init
 
-                let is_ok: bool = {#is_ok};
+                #( #checks )*
 
-                if is_ok {
-                    Ok(Self { value })
-                } else {
-                    Err(::bilge::give_me_error())
-                }
+                Ok(#init)
             }
         }
 
-        impl #const_ ::core::convert::From<#struct_type> for #arb_int {
-            fn from(struct_value: #struct_type) -> Self {
+        impl #impl_generics #const_ ::core::convert::From<#struct_type #ty_generics> for #arb_int #where_clause {
+            fn from(struct_value: #struct_type #ty_generics) -> Self {
                 struct_value.value
             }
         }
@@ -0,0 +1,234 @@
+//! Writes the parsed device as bilge definitions, one module per peripheral:
+//! ```ignore
+//! pub mod uart0 {
+//!     pub const BASE_ADDRESS: usize = 0x4000_1000;
+//!
+//!     #[bilge::register_block]
+//!     pub struct RegisterBlock {
+//!         #[offset(0x0)]
+//!         pub ctrl: Reg<Ctrl>,
+//!     }
+//!
+//!     #[bitsize(32)]
+//!     #[reset = 0x0000_0300]
+//!     pub struct Ctrl {
+//!         pub en: bool,
+//!         pub mode: CtrlMode,
+//!         reserved: u29,
+//!     }
+//!     // ..
+//! }
+//! ```
+
+use crate::names::{pascal_case, snake_case};
+use crate::svd::{Access, Device, EnumeratedValues, Field, Peripheral, Register};
+
+/// Collects the generated lines, indenting them by the current nesting.
+struct Writer {
+    out: String,
+    indent: usize,
+}
+
+impl Writer {
+    fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+        if !line.is_empty() {
+            self.out.extend(core::iter::repeat_n("    ", self.indent));
+        }
+        self.out.push_str(line);
+        self.out.push('\n');
+    }
+
+    fn doc(&mut self, description: &Option<String>) {
+        if let Some(description) = description {
+            self.line(format!("/// {description}"));
+        }
+    }
+
+    fn open(&mut self, line: impl AsRef<str>) {
+        self.line(line);
+        self.indent += 1;
+    }
+
+    fn close(&mut self, line: &str) {
+        self.indent -= 1;
+        self.line(line);
+    }
+}
+
+pub(crate) fn generate(device: &Device) -> String {
+    let mut writer = Writer {
+        out: String::new(),
+        indent: 0,
+    };
+    writer.line(format!(
+        "// Generated by bilge-svd from the SVD of `{}`, don't edit this by hand.",
+        device.name
+    ));
+    if let Some(description) = &device.description {
+        writer.line(format!("// {description}"));
+    }
+    for peripheral in &device.peripherals {
+        writer.line("");
+        match &peripheral.derived_from {
+            Some(base) => generate_derived_peripheral(&mut writer, peripheral, base),
+            None => generate_peripheral(&mut writer, peripheral),
+        }
+    }
+    writer.out
+}
+
+fn generate_peripheral(writer: &mut Writer, peripheral: &Peripheral) {
+    writer.doc(&peripheral.description);
+    writer.open(format!("pub mod {} {{", snake_case(&peripheral.name)));
+    writer.line("#[allow(unused_imports)]");
+    writer.line("use bilge::mmio::{Reg, RoReg, WoReg};");
+    writer.line("use bilge::prelude::*;");
+    writer.line("");
+    generate_base_address(writer, peripheral);
+
+    if !peripheral.registers.is_empty() {
+        writer.line("");
+        writer.line("#[bilge::register_block]");
+        writer.open("pub struct RegisterBlock {");
+        for register in &peripheral.registers {
+            let wrapper = match register.access {
+                Access::ReadOnly => "RoReg",
+                Access::WriteOnly => "WoReg",
+                _ => "Reg",
+            };
+            writer.doc(&register.description);
+            writer.line(format!("#[offset({:#x})]", register.offset));
+            writer.line(format!("pub {}: {wrapper}<{}>,", snake_case(&register.name), pascal_case(&register.name)));
+        }
+        writer.close("}");
+    }
+
+    for register in &peripheral.registers {
+        writer.line("");
+        generate_register(writer, register);
+    }
+    writer.close("}");
+}
+
+/// Reuses everything of `base`, besides the address.
+fn generate_derived_peripheral(writer: &mut Writer, peripheral: &Peripheral, base: &str) {
+    writer.doc(&peripheral.description);
+    writer.open(format!("pub mod {} {{", snake_case(&peripheral.name)));
+    writer.line(format!("pub use super::{}::*;", snake_case(base)));
+    writer.line("");
+    generate_base_address(writer, peripheral);
+    writer.close("}");
+}
+
+fn generate_base_address(writer: &mut Writer, peripheral: &Peripheral) {
+    writer.line("/// The address of this peripheral's `RegisterBlock`.");
+    writer.line(format!("pub const BASE_ADDRESS: usize = {};", hex(peripheral.base_address, 32)));
+}
+
+fn generate_register(writer: &mut Writer, register: &Register) {
+    let name = pascal_case(&register.name);
+    // registers without fields are a single value
+    let whole = Field {
+        name: "value".to_owned(),
+        description: None,
+        offset: 0,
+        width: register.size,
+        access: register.access,
+        values: None,
+    };
+    let fields = if register.fields.is_empty() {
+        core::slice::from_ref(&whole)
+    } else {
+        &register.fields[..]
+    };
+
+    writer.doc(&register.description);
+    writer.line(format!("#[bitsize({})]", register.size));
+    writer.line("#[derive(FromBits, DebugBits, DefaultBits, Clone, Copy, PartialEq)]");
+    // `Default` and `DEFAULT` are the value after reset, which gets checked at compile time
+    writer.line(format!("#[reset = {}]", hex(register.reset_value, register.size)));
+    writer.open(format!("pub struct {name} {{"));
+    let mut end = 0;
+    for field in fields {
+        // gaps between fields are reserved
+        if field.offset > end {
+            writer.line(format!("reserved: u{},", field.offset - end));
+        }
+        end = field.offset + field.width;
+
+        writer.doc(&field.description);
+        match field.access {
+            Access::ReadWrite => {}
+            Access::ReadOnly => writer.line("#[access(ro)]"),
+            Access::WriteOnly => writer.line("#[access(wo)]"),
+            Access::WriteOneToClear => writer.line("#[access(w1c)]"),
+            Access::ReadToClear => writer.line("#[access(rc)]"),
+        }
+        let ty = match &field.values {
+            Some(_) => enum_name(register, field),
+            None if field.width == 1 => "bool".to_owned(),
+            None => format!("u{}", field.width),
+        };
+        writer.line(format!("pub {}: {ty},", snake_case(&field.name)));
+    }
+    if register.size > end {
+        writer.line(format!("reserved: u{},", register.size - end));
+    }
+    writer.close("}");
+
+    for field in fields {
+        if let Some(values) = &field.values {
+            writer.line("");
+            generate_enum(writer, register, field, values);
+        }
+    }
+}
+
+/// Enums which don't list every value get a `#[fallback]` keeping the value,
+/// named after the SVD's `isDefault` value if there is one.
+fn generate_enum(writer: &mut Writer, register: &Register, field: &Field, values: &EnumeratedValues) {
+    writer.doc(&field.description);
+    writer.line(format!("#[bitsize({})]", field.width));
+    writer.line("#[derive(FromBits, Debug, Clone, Copy, PartialEq)]");
+    writer.open(format!("pub enum {} {{", enum_name(register, field)));
+    for value in &values.values {
+        writer.doc(&value.description);
+        let discriminant = if value.value < 10 {
+            value.value.to_string()
+        } else {
+            format!("{:#x}", value.value)
+        };
+        writer.line(format!("{} = {discriminant},", pascal_case(&value.name)));
+    }
+
+    let is_complete = field.width < u64::BITS && values.values.len() as u64 == 1 << field.width;
+    if !is_complete {
+        let (name, description) = match &values.default {
+            Some(default) => (pascal_case(&default.name), default.description.clone()),
+            None => ("Reserved".to_owned(), Some("Any value not listed above".to_owned())),
+        };
+        writer.doc(&description);
+        writer.line("#[fallback]");
+        writer.line(format!("{name}(u{}),", field.width));
+    }
+    writer.close("}");
+}
+
+/// `CTRL.MODE` -> `CtrlMode`
+fn enum_name(register: &Register, field: &Field) -> String {
+    format!("{}{}", pascal_case(&register.name), pascal_case(&field.name))
+}
+
+/// `0x4000_1000`, with all digits a value of `bits` can have
+fn hex(value: u64, bits: u32) -> String {
+    let digits = format!("{:0width$x}", value, width = bits.div_ceil(4) as usize);
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 4 == 0 {
+            grouped.push('_');
+        }
+        grouped.push(digit);
+    }
+    format!("0x{grouped}")
+}
@@ -0,0 +1,77 @@
+//! Generates bilge register definitions from CMSIS-SVD files.
+//!
+//! Every peripheral becomes a module with a `#[register_block]`, its `BASE_ADDRESS` and a `#[bitsize]` struct per register,
+//! whose `Default` is the register's reset value.
+//! Gaps between fields become `reserved` fields, the SVD's access modes become `#[access(..)]`,
+//! and enumerated values become `#[bitsize]` enums, with a `#[fallback]` if they don't list every value.
+//!
+//! From a `build.rs`, only reading local files:
+//! ```no_run
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("device.rs");
+//! std::fs::write(out, bilge_svd::generate_file("device.svd").unwrap()).unwrap();
+//! ```
+//! which can then be used with `include!(concat!(env!("OUT_DIR"), "/device.rs"));`.
+//!
+//! The `bilge-svd` binary does the same: `bilge-svd device.svd device.rs`.
+//!
+//! Register arrays (`dim`), clusters, alternate registers and derived registers or fields aren't supported yet.
+
+use std::{fmt, fs, io, path::Path};
+
+mod codegen;
+mod names;
+mod svd;
+
+/// Generates the Rust code for the SVD file's contents.
+pub fn generate(svd: &str) -> Result<String, Error> {
+    let device = svd::parse(svd)?;
+    Ok(codegen::generate(&device))
+}
+
+/// Generates the Rust code for the SVD file at `path`.
+pub fn generate_file(path: impl AsRef<Path>) -> Result<String, Error> {
+    let svd = fs::read_to_string(path)?;
+    generate(&svd)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The SVD file couldn't be read.
+    Io(io::Error),
+    /// The SVD file isn't valid XML.
+    Xml(roxmltree::Error),
+    /// The SVD file is missing something, or uses something which isn't supported yet.
+    Svd(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "couldn't read the SVD file: {error}"),
+            Error::Xml(error) => write!(f, "invalid XML: {error}"),
+            Error::Svd(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            Error::Xml(error) => Some(error),
+            Error::Svd(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<roxmltree::Error> for Error {
+    fn from(error: roxmltree::Error) -> Self {
+        Error::Xml(error)
+    }
+}
@@ -0,0 +1,33 @@
+//! `bilge-svd <input.svd> [output.rs]`, writing to stdout without an output file.
+
+use std::{env, fs, process::ExitCode};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (input, output) = match &args[..] {
+        [input] => (input, None),
+        [input, output] => (input, Some(output)),
+        _ => {
+            eprintln!("usage: bilge-svd <input.svd> [output.rs]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let code = match bilge_svd::generate_file(input) {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {input}: {error}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match output {
+        Some(output) => {
+            if let Err(error) = fs::write(output, code) {
+                eprintln!("error: couldn't write {output}: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
+        None => print!("{code}"),
+    }
+    ExitCode::SUCCESS
+}
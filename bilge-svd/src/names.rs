@@ -0,0 +1,63 @@
+//! Turns SVD names like `RX_ONLY` into Rust identifiers.
+
+/// `RX_ONLY` -> `RxOnly`, for types and variants
+pub(crate) fn pascal_case(name: &str) -> String {
+    let name: String = words(name)
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|first| first.to_ascii_uppercase());
+            first.into_iter().chain(chars.map(|char| char.to_ascii_lowercase())).collect::<String>()
+        })
+        .collect();
+    if name.starts_with(|char: char| char.is_ascii_digit()) {
+        format!("V{name}")
+    } else if name == "Self" {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+/// `RX_ONLY` -> `rx_only`, for modules and fields
+pub(crate) fn snake_case(name: &str) -> String {
+    let name = words(name).map(str::to_ascii_lowercase).collect::<Vec<_>>().join("_");
+    if name.starts_with(|char: char| char.is_ascii_digit()) {
+        format!("_{name}")
+    } else if is_keyword(&name) {
+        // `type_`, like other generators do, since raw identifiers don't work in accessor names like `set_type`
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+/// Splits at anything besides ASCII letters and digits.
+fn words(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|char: char| !char.is_ascii_alphanumeric()).filter(|word| !word.is_empty())
+}
+
+fn is_keyword(name: &str) -> bool {
+    #[rustfmt::skip]
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+        "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+        "static", "struct", "super", "trait", "true", "try", "type", "unsafe", "use", "where", "while", "yield",
+    ];
+    KEYWORDS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cases() {
+        assert_eq!(pascal_case("RX_ONLY"), "RxOnly");
+        assert_eq!(pascal_case("UART0"), "Uart0");
+        assert_eq!(pascal_case("8BIT"), "V8bit");
+        assert_eq!(snake_case("RX_ONLY"), "rx_only");
+        assert_eq!(snake_case("Tx-Empty"), "tx_empty");
+        assert_eq!(snake_case("TYPE"), "type_");
+        assert_eq!(snake_case("2ND"), "_2nd");
+    }
+}
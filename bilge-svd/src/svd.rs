@@ -0,0 +1,392 @@
+//! The parts of a CMSIS-SVD file we generate code for.
+//!
+//! Register properties like `size`, `access` and `resetValue` are inherited from the device and the peripheral,
+//! so every [`Register`] and [`Field`] already has them resolved.
+
+use roxmltree::Node;
+
+use crate::Error;
+
+pub(crate) struct Device {
+    pub name: String,
+    pub description: Option<String>,
+    pub peripherals: Vec<Peripheral>,
+}
+
+pub(crate) struct Peripheral {
+    pub name: String,
+    pub description: Option<String>,
+    pub base_address: u64,
+    /// `derivedFrom`: this peripheral has the same registers as the named one
+    pub derived_from: Option<String>,
+    pub registers: Vec<Register>,
+}
+
+pub(crate) struct Register {
+    pub name: String,
+    pub description: Option<String>,
+    /// in bytes, from the peripheral's base address
+    pub offset: u64,
+    /// in bits
+    pub size: u32,
+    pub access: Access,
+    pub reset_value: u64,
+    /// sorted by offset, without any overlaps
+    pub fields: Vec<Field>,
+}
+
+pub(crate) struct Field {
+    pub name: String,
+    pub description: Option<String>,
+    /// in bits, from the register's least significant bit
+    pub offset: u32,
+    pub width: u32,
+    pub access: Access,
+    pub values: Option<EnumeratedValues>,
+}
+
+pub(crate) struct EnumeratedValues {
+    pub values: Vec<EnumeratedValue>,
+    /// the `isDefault` value, used for every value which isn't listed
+    pub default: Option<EnumeratedValue>,
+}
+
+pub(crate) struct EnumeratedValue {
+    pub name: String,
+    pub description: Option<String>,
+    /// always `0` for the default
+    pub value: u64,
+}
+
+/// Combines `access`, `modifiedWriteValues` and `readAction` into the policies `#[access(..)]` knows.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    ReadWrite,
+    ReadOnly,
+    WriteOnly,
+    WriteOneToClear,
+    ReadToClear,
+}
+
+/// The register properties a device, peripheral or register passes on to everything it contains.
+#[derive(Clone, Copy)]
+struct Properties {
+    size: Option<u32>,
+    access: Option<Access>,
+    reset_value: Option<u64>,
+}
+
+impl Properties {
+    fn inherit(self, node: Node, context: &str) -> Result<Properties, Error> {
+        Ok(Properties {
+            size: optional_number(node, "size", context)?.map(|size| size as u32).or(self.size),
+            access: access_of(node, context)?.or(self.access),
+            reset_value: optional_number(node, "resetValue", context)?.or(self.reset_value),
+        })
+    }
+}
+
+pub(crate) fn parse(svd: &str) -> Result<Device, Error> {
+    let document = roxmltree::Document::parse(svd)?;
+    let device = document.root_element();
+    if device.tag_name().name() != "device" {
+        return Err(Error::Svd(format!("expected a `device`, found `{}`", device.tag_name().name())));
+    }
+    let name = required_text(device, "name", "device")?;
+    let properties = Properties {
+        size: None,
+        access: None,
+        reset_value: None,
+    }
+    .inherit(device, &name)?;
+
+    let peripherals = match child(device, "peripherals") {
+        Some(peripherals) => elements(peripherals, "peripheral")
+            .map(|peripheral| parse_peripheral(peripheral, properties))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![],
+    };
+    for peripheral in &peripherals {
+        let Some(base) = &peripheral.derived_from else { continue };
+        match peripherals.iter().find(|other| &other.name == base) {
+            Some(other) if other.derived_from.is_none() => {}
+            Some(_) => return Err(unsupported(&peripheral.name, "peripherals derived from derived peripherals")),
+            None => {
+                return Err(Error::Svd(format!(
+                    "`{}` is derived from `{}`, which doesn't exist",
+                    peripheral.name, base
+                )))
+            }
+        }
+    }
+
+    Ok(Device {
+        description: text(device, "description"),
+        name,
+        peripherals,
+    })
+}
+
+fn parse_peripheral(node: Node, properties: Properties) -> Result<Peripheral, Error> {
+    let name = required_text(node, "name", "peripheral")?;
+    reject_arrays(node, &name)?;
+    let properties = properties.inherit(node, &name)?;
+    let base_address = required_number(node, "baseAddress", &name)?;
+    let derived_from = node.attribute("derivedFrom").map(str::to_owned);
+
+    let mut registers = vec![];
+    if let Some(node) = child(node, "registers") {
+        if let Some(cluster) = elements(node, "cluster").next() {
+            let cluster = text(cluster, "name").unwrap_or_default();
+            return Err(unsupported(&format!("{name}.{cluster}"), "clusters"));
+        }
+        for register in elements(node, "register") {
+            registers.push(parse_register(register, properties, &name)?);
+        }
+    }
+    if derived_from.is_some() && !registers.is_empty() {
+        return Err(unsupported(&name, "derived peripherals with registers of their own"));
+    }
+    registers.sort_by_key(|register| register.offset);
+    // `alternateRegister`s share their address, but a `RegisterBlock` can only have one field per address
+    for pair in registers.windows(2) {
+        if pair[0].offset + u64::from(pair[0].size / 8) > pair[1].offset {
+            return Err(Error::Svd(format!(
+                "`{name}`: `{}` overlaps `{}`, alternate registers are not supported yet",
+                pair[0].name, pair[1].name
+            )));
+        }
+    }
+
+    Ok(Peripheral {
+        description: text(node, "description"),
+        name,
+        base_address,
+        derived_from,
+        registers,
+    })
+}
+
+fn parse_register(node: Node, properties: Properties, peripheral: &str) -> Result<Register, Error> {
+    let name = required_text(node, "name", peripheral)?;
+    let context = &format!("{peripheral}.{name}");
+    reject_arrays(node, context)?;
+    if node.has_attribute("derivedFrom") {
+        return Err(unsupported(context, "derived registers"));
+    }
+    let properties = properties.inherit(node, context)?;
+    let size = properties.size.ok_or_else(|| Error::Svd(format!("`{context}` has no size")))?;
+    if ![8, 16, 32, 64].contains(&size) {
+        return Err(Error::Svd(format!(
+            "`{context}` has a size of {size} bits, but only 8, 16, 32 and 64 are supported"
+        )));
+    }
+    let access = properties.access.unwrap_or(Access::ReadWrite);
+    let reset_value = properties.reset_value.unwrap_or(0);
+    if size < u64::BITS && reset_value >> size != 0 {
+        return Err(Error::Svd(format!(
+            "`{context}` has a reset value which doesn't fit into its {size} bits"
+        )));
+    }
+
+    let mut fields = match child(node, "fields") {
+        Some(fields) => elements(fields, "field")
+            .map(|field| parse_field(field, access, context))
+            .collect::<Result<Vec<_>, _>>()?,
+        None => vec![],
+    };
+    fields.sort_by_key(|field| field.offset);
+    for pair in fields.windows(2) {
+        if pair[0].offset + pair[0].width > pair[1].offset {
+            return Err(Error::Svd(format!("`{context}`: `{}` overlaps `{}`", pair[0].name, pair[1].name)));
+        }
+    }
+    if let Some(last) = fields.last() {
+        if last.offset + last.width > size {
+            return Err(Error::Svd(format!("`{context}`: `{}` exceeds the register's {size} bits", last.name)));
+        }
+    }
+
+    Ok(Register {
+        description: text(node, "description"),
+        offset: required_number(node, "addressOffset", context)?,
+        name,
+        size,
+        access,
+        reset_value,
+        fields,
+    })
+}
+
+fn parse_field(node: Node, register_access: Access, register: &str) -> Result<Field, Error> {
+    let name = required_text(node, "name", register)?;
+    let context = &format!("{register}.{name}");
+    reject_arrays(node, context)?;
+    if node.has_attribute("derivedFrom") {
+        return Err(unsupported(context, "derived fields"));
+    }
+    let (offset, width) = bit_range(node, context)?;
+    if width == 0 {
+        return Err(Error::Svd(format!("`{context}` has no bits")));
+    }
+    let values = match child(node, "enumeratedValues") {
+        Some(values) => Some(parse_enumerated_values(values, width, context)?),
+        None => None,
+    };
+
+    Ok(Field {
+        name,
+        description: text(node, "description"),
+        offset,
+        width,
+        access: access_of(node, context)?.unwrap_or(register_access),
+        values,
+    })
+}
+
+fn parse_enumerated_values(node: Node, width: u32, field: &str) -> Result<EnumeratedValues, Error> {
+    if node.has_attribute("derivedFrom") {
+        return Err(unsupported(field, "derived enumerated values"));
+    }
+    let max = u64::MAX >> (u64::BITS - width.min(u64::BITS));
+    let mut values: Vec<EnumeratedValue> = vec![];
+    let mut default = None;
+    for node in elements(node, "enumeratedValue") {
+        let name = required_text(node, "name", field)?;
+        let description = text(node, "description");
+        if text(node, "isDefault").is_some_and(|is_default| is_default == "true" || is_default == "1") {
+            default = Some(EnumeratedValue { name, description, value: 0 });
+            continue;
+        }
+        let value = required_number(node, "value", &format!("{field}.{name}"))?;
+        if value > max {
+            return Err(Error::Svd(format!("`{field}.{name}` doesn't fit into the field's {width} bits")));
+        }
+        if let Some(other) = values.iter().find(|other| other.value == value) {
+            return Err(Error::Svd(format!("`{field}.{name}` has the same value as `{}`", other.name)));
+        }
+        values.push(EnumeratedValue { name, description, value });
+    }
+    Ok(EnumeratedValues { values, default })
+}
+
+/// Reads `bitOffset` and `bitWidth`, `lsb` and `msb`, or `bitRange` like `[7:4]`, as offset and width.
+fn bit_range(node: Node, field: &str) -> Result<(u32, u32), Error> {
+    let (lsb, msb) = if let Some(offset) = optional_number(node, "bitOffset", field)? {
+        let width = optional_number(node, "bitWidth", field)?.unwrap_or(1);
+        return Ok((offset as u32, width as u32));
+    } else if let Some(lsb) = optional_number(node, "lsb", field)? {
+        (lsb, required_number(node, "msb", field)?)
+    } else if let Some(range) = text(node, "bitRange") {
+        let invalid = || Error::Svd(format!("`{field}` has an invalid bit range `{range}`"));
+        let (msb, lsb) = range
+            .strip_prefix('[')
+            .and_then(|range| range.strip_suffix(']'))
+            .and_then(|range| range.split_once(':'))
+            .ok_or_else(invalid)?;
+        (parse_number(lsb).ok_or_else(invalid)?, parse_number(msb).ok_or_else(invalid)?)
+    } else {
+        return Err(Error::Svd(format!("`{field}` has no bit range")));
+    };
+    if msb < lsb {
+        return Err(Error::Svd(format!(
+            "`{field}` has its most significant bit below its least significant one"
+        )));
+    }
+    Ok((lsb as u32, (msb - lsb + 1) as u32))
+}
+
+fn access_of(node: Node, context: &str) -> Result<Option<Access>, Error> {
+    let access = match text(node, "access").as_deref() {
+        None => None,
+        Some("read-write" | "read-writeOnce") => Some(Access::ReadWrite),
+        Some("read-only") => Some(Access::ReadOnly),
+        Some("write-only" | "writeOnce") => Some(Access::WriteOnly),
+        Some(access) => return Err(Error::Svd(format!("`{context}` has an unknown access `{access}`"))),
+    };
+    // these refine `access`, so the register's access still applies to the field
+    if text(node, "modifiedWriteValues").as_deref() == Some("oneToClear") {
+        return Ok(Some(Access::WriteOneToClear));
+    }
+    if text(node, "readAction").as_deref() == Some("clear") {
+        return Ok(Some(Access::ReadToClear));
+    }
+    Ok(access)
+}
+
+/// `dim` turns registers and fields into arrays, which need their names expanded.
+fn reject_arrays(node: Node, context: &str) -> Result<(), Error> {
+    if child(node, "dim").is_some() || context.contains("%s") {
+        return Err(unsupported(context, "arrays (`dim`)"));
+    }
+    Ok(())
+}
+
+fn unsupported(context: &str, what: &str) -> Error {
+    Error::Svd(format!("`{context}`: {what} are not supported yet"))
+}
+
+fn elements<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children().filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &'static str) -> Option<Node<'a, 'input>> {
+    elements(node, name).next()
+}
+
+/// The trimmed text of the child element `name`, with all whitespace collapsed into single spaces.
+fn text(node: Node, name: &'static str) -> Option<String> {
+    let text = child(node, name)?.text()?;
+    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn required_text(node: Node, name: &'static str, context: &str) -> Result<String, Error> {
+    text(node, name).ok_or_else(|| Error::Svd(format!("`{context}` is missing `{name}`")))
+}
+
+fn optional_number(node: Node, name: &'static str, context: &str) -> Result<Option<u64>, Error> {
+    let Some(text) = text(node, name) else { return Ok(None) };
+    parse_number(&text)
+        .map(Some)
+        .ok_or_else(|| Error::Svd(format!("`{context}` has an invalid `{name}`: `{text}`")))
+}
+
+fn required_number(node: Node, name: &'static str, context: &str) -> Result<u64, Error> {
+    optional_number(node, name, context)?.ok_or_else(|| Error::Svd(format!("`{context}` is missing `{name}`")))
+}
+
+/// Parses decimal, `0x` hexadecimal and `0b` or `#` binary numbers.
+///
+/// Binary numbers with don't-care bits like `#1x0` aren't supported.
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (digits, radix) = if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(binary) = text
+        .strip_prefix("0b")
+        .or_else(|| text.strip_prefix("0B"))
+        .or_else(|| text.strip_prefix('#'))
+    {
+        (binary, 2)
+    } else {
+        (text, 10)
+    };
+    u64::from_str_radix(digits, radix).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("42"), Some(42));
+        assert_eq!(parse_number("0x40001000"), Some(0x4000_1000));
+        assert_eq!(parse_number("0XfF"), Some(0xff));
+        assert_eq!(parse_number("0b101"), Some(0b101));
+        assert_eq!(parse_number("#0110"), Some(0b0110));
+        assert_eq!(parse_number(" 7 "), Some(7));
+        assert_eq!(parse_number("#1x0"), None);
+        assert_eq!(parse_number("0x"), None);
+    }
+}
@@ -0,0 +1,168 @@
+// Generated by bilge-svd from the SVD of `SAMPLE`, don't edit this by hand.
+// A small device used to test the SVD importer
+
+/// Universal asynchronous receiver and transmitter
+pub mod uart0 {
+    #[allow(unused_imports)]
+    use bilge::mmio::{Reg, RoReg, WoReg};
+    use bilge::prelude::*;
+
+    /// The address of this peripheral's `RegisterBlock`.
+    pub const BASE_ADDRESS: usize = 0x4000_1000;
+
+    #[bilge::register_block]
+    pub struct RegisterBlock {
+        /// Control register
+        #[offset(0x0)]
+        pub ctrl: Reg<Ctrl>,
+        /// Status register
+        #[offset(0x4)]
+        pub status: Reg<Status>,
+        /// Transmit data
+        #[offset(0x8)]
+        pub data: WoReg<Data>,
+    }
+
+    /// Control register
+    #[bitsize(32)]
+    #[derive(FromBits, DebugBits, DefaultBits, Clone, Copy, PartialEq)]
+    #[reset = 0x0000_0300]
+    pub struct Ctrl {
+        /// Enables the UART
+        pub en: bool,
+        /// Transfer mode
+        pub mode: CtrlMode,
+        reserved: u2,
+        pub parity: CtrlParity,
+        reserved: u2,
+        /// Baud rate divider
+        pub div: u8,
+        /// FIFO trigger level
+        pub level: CtrlLevel,
+        reserved: u13,
+    }
+
+    /// Transfer mode
+    #[bitsize(2)]
+    #[derive(FromBits, Debug, Clone, Copy, PartialEq)]
+    pub enum CtrlMode {
+        /// Neither receiving nor transmitting
+        Off = 0,
+        RxOnly = 1,
+        Duplex = 3,
+        /// Any value not listed above
+        #[fallback]
+        Reserved(u2),
+    }
+
+    #[bitsize(1)]
+    #[derive(FromBits, Debug, Clone, Copy, PartialEq)]
+    pub enum CtrlParity {
+        Even = 0,
+        Odd = 1,
+    }
+
+    /// FIFO trigger level
+    #[bitsize(3)]
+    #[derive(FromBits, Debug, Clone, Copy, PartialEq)]
+    pub enum CtrlLevel {
+        Low = 0,
+        High = 7,
+        /// Any other level
+        #[fallback]
+        Custom(u3),
+    }
+
+    /// Status register
+    #[bitsize(32)]
+    #[derive(FromBits, DebugBits, DefaultBits, Clone, Copy, PartialEq)]
+    #[reset = 0x0000_0000]
+    pub struct Status {
+        /// Transmit buffer empty
+        #[access(ro)]
+        pub txe: bool,
+        /// Receive buffer not empty
+        #[access(ro)]
+        pub rxne: bool,
+        reserved: u2,
+        /// Framing error, cleared by writing 1
+        #[access(w1c)]
+        pub err: bool,
+        /// Overrun, cleared by reading
+        #[access(rc)]
+        pub ovr: bool,
+        reserved: u26,
+    }
+
+    /// Transmit data
+    #[bitsize(8)]
+    #[derive(FromBits, DebugBits, DefaultBits, Clone, Copy, PartialEq)]
+    #[reset = 0x00]
+    pub struct Data {
+        #[access(wo)]
+        pub data: u8,
+    }
+}
+
+pub mod uart1 {
+    pub use super::uart0::*;
+
+    /// The address of this peripheral's `RegisterBlock`.
+    pub const BASE_ADDRESS: usize = 0x4000_2000;
+}
+
+/// General purpose timer
+pub mod timer {
+    #[allow(unused_imports)]
+    use bilge::mmio::{Reg, RoReg, WoReg};
+    use bilge::prelude::*;
+
+    /// The address of this peripheral's `RegisterBlock`.
+    pub const BASE_ADDRESS: usize = 0x4000_3000;
+
+    #[bilge::register_block]
+    pub struct RegisterBlock {
+        /// Current count
+        #[offset(0x0)]
+        pub count: RoReg<Count>,
+        /// Compare value
+        #[offset(0x4)]
+        pub compare: Reg<Compare>,
+        /// Commands
+        #[offset(0x8)]
+        pub cmd: WoReg<Cmd>,
+    }
+
+    /// Current count
+    #[bitsize(16)]
+    #[derive(FromBits, DebugBits, DefaultBits, Clone, Copy, PartialEq)]
+    #[reset = 0xffff]
+    pub struct Count {
+        #[access(ro)]
+        pub value: u16,
+    }
+
+    /// Compare value
+    #[bitsize(32)]
+    #[derive(FromBits, DebugBits, DefaultBits, Clone, Copy, PartialEq)]
+    #[reset = 0x0000_0000]
+    pub struct Compare {
+        pub value: u32,
+    }
+
+    /// Commands
+    #[bitsize(32)]
+    #[derive(FromBits, DebugBits, DefaultBits, Clone, Copy, PartialEq)]
+    #[reset = 0x0000_0000]
+    pub struct Cmd {
+        #[access(wo)]
+        pub start: bool,
+        #[access(wo)]
+        pub stop: bool,
+        reserved: u6,
+        /// A keyword as name
+        #[access(wo)]
+        pub type_: u2,
+        reserved: u22,
+    }
+}
@@ -0,0 +1,94 @@
+#![allow(clippy::unusual_byte_groupings)]
+//! Checks the code generated from `sample.svd`, which is also compiled here.
+
+mod sample {
+    include!("generated/sample.rs");
+}
+
+use bilge::prelude::*;
+use sample::{timer, uart0, uart1};
+
+#[test]
+fn generated_code_is_up_to_date() {
+    let generated = bilge_svd::generate_file("tests/sample.svd").unwrap();
+    assert!(
+        generated == include_str!("generated/sample.rs"),
+        "regenerate it with `cargo run -p bilge-svd -- tests/sample.svd tests/generated/sample.rs`"
+    );
+}
+
+#[test]
+fn fields_and_reserved_gaps() {
+    let ctrl = uart0::Ctrl::from(0b101_00001111_00_1_00_11_1);
+    assert!(ctrl.en());
+    assert_eq!(ctrl.mode(), uart0::CtrlMode::Duplex);
+    assert_eq!(ctrl.parity(), uart0::CtrlParity::Odd);
+    assert_eq!(ctrl.div(), 0b00001111);
+    assert_eq!(ctrl.level(), uart0::CtrlLevel::Custom(u3::new(0b101)));
+    assert_eq!(uart0::Ctrl::DIV_OFFSET, 8);
+}
+
+#[test]
+fn incomplete_enums_fall_back() {
+    assert_eq!(uart0::CtrlMode::from(u2::new(0b10)), uart0::CtrlMode::Reserved(u2::new(0b10)));
+    assert_eq!(uart0::CtrlLevel::from(u3::new(7)), uart0::CtrlLevel::High);
+}
+
+#[test]
+fn register_blocks() {
+    assert_eq!(uart0::BASE_ADDRESS, 0x4000_1000);
+    assert_eq!(uart1::BASE_ADDRESS, 0x4000_2000);
+    assert_eq!(timer::BASE_ADDRESS, 0x4000_3000);
+
+    let mut memory = [0u32; 3];
+    let uart: &uart1::RegisterBlock = unsafe { uart1::RegisterBlock::from_ptr(memory.as_mut_ptr().cast()) };
+    uart.ctrl().write(uart0::Ctrl::DEFAULT);
+    uart.ctrl().modify(|ctrl| ctrl.set_en(true));
+    uart.status().modify(|status| status.clear_err());
+    assert_eq!(memory[..2], [0x0000_0301, 0b1_0000]);
+
+    let mut memory = [u16::from(timer::Count::DEFAULT) as u32, 0, 0];
+    let timer = unsafe { timer::RegisterBlock::from_ptr(memory.as_mut_ptr().cast()) };
+    assert_eq!(timer.count().read().value(), 0xffff);
+    let mut cmd = timer::Cmd::from(0);
+    cmd.set_start(true);
+    cmd.set_type_(u2::new(2));
+    timer.cmd().write(cmd);
+    assert_eq!(memory[2], 0b10_000000_0_1);
+}
+
+#[test]
+fn unsupported_svds_are_rejected() {
+    let svd = |register: &str| {
+        format!(
+            "<device><name>D</name><size>32</size><peripherals><peripheral><name>P</name><baseAddress>0</baseAddress>\
+             <registers>{register}</registers></peripheral></peripherals></device>"
+        )
+    };
+    let error = |register: &str| bilge_svd::generate(&svd(register)).unwrap_err().to_string();
+
+    assert_eq!(
+        error("<register><dim>4</dim><name>R%s</name><addressOffset>0</addressOffset></register>"),
+        "`P.R%s`: arrays (`dim`) are not supported yet"
+    );
+    assert_eq!(
+        error(
+            "<register><name>R</name><addressOffset>0</addressOffset><fields>\
+             <field><name>A</name><bitRange>[3:0]</bitRange></field><field><name>B</name><bitOffset>2</bitOffset></field>\
+             </fields></register>"
+        ),
+        "`P.R`: `A` overlaps `B`"
+    );
+    assert_eq!(
+        error(
+            "<register><name>RX</name><addressOffset>0x4</addressOffset></register>\
+             <register><name>TX</name><addressOffset>0x4</addressOffset><alternateRegister>RX</alternateRegister></register>"
+        ),
+        "`P`: `RX` overlaps `TX`, alternate registers are not supported yet"
+    );
+    assert_eq!(
+        error("<register><name>R</name><addressOffset>0</addressOffset><size>8</size><resetValue>0x100</resetValue></register>"),
+        "`P.R` has a reset value which doesn't fit into its 8 bits"
+    );
+    assert!(bilge_svd::generate("<device>").unwrap_err().to_string().starts_with("invalid XML"));
+}
@@ -2,7 +2,7 @@
 #![allow(clippy::unusual_byte_groupings)]
 use bilge::prelude::*;
 
-#[bitsize(14)]
+#[bitsize(14, const_fields)]
 #[derive(FromBits)]
 struct Register {
     header: u4,
@@ -10,7 +10,7 @@ struct Register {
     footer: Footer,
 }
 
-#[bitsize(3)]
+#[bitsize(3, const_fields)]
 #[derive(FromBits)]
 struct Footer {
     is_last: bool,
@@ -66,6 +66,11 @@ enum Subclass2 {
     Reserved(u32),
 }
 
+const RESET: Register = Register::new(u4::new(0), u7::new(0), Footer::new(false, Code::Success)).with_header(u4::new(0b1010));
+const HEADER: u4 = RESET.header();
+const RAW: u14 = RESET.to_raw();
+const PARSED: Footer = Footer::from_raw(u3::new(0b1_11));
+
 fn main() {
     let reg1 = Register::new(u4::new(0b1010), u7::new(0b010_1010), Footer::new(true, Code::GoodExample));
     let mut reg2 = Register::from(u14::new(0b11_1_0101010_1010));
@@ -78,6 +83,10 @@ fn main() {
     ise.set_val_0_at(2, ise5);
     assert_eq!(0b0000_0000_0000_0000_0000_0000_0001_0100, ise.value);
 
+    assert_eq!(HEADER, u4::new(0b1010));
+    assert_eq!(RAW, u14::new(0b1010));
+    assert!(PARSED.is_last());
+
     assert_eq!(Subclass::Speakers, Subclass::from(2));
     assert_eq!(Subclass::Reserved, Subclass::from(3));
     assert_eq!(Subclass::Reserved, Subclass::from(42));
@@ -6,6 +6,10 @@ use bilge::prelude::*;
 #[derive(TryFromBits)]
 struct CanBeChanged(Unfilled);
 
+#[bitsize(4, sealed)]
+#[derive(TryFromBits)]
+struct CantBeChanged(Unfilled);
+
 #[bitsize(4)]
 #[derive(TryFromBits)]
 enum Unfilled {
@@ -15,14 +19,22 @@ enum Unfilled {
 }
 
 fn main() {
-    // This file mostly shows one flaw to still be solved or at least to be made configurable:
-    // The inner value of a bitfield, which holds invariants, can currently still be changed.
+    // This file mostly shows one flaw, which is only solved for sealed bitfields:
+    // The inner value of a bitfield, which holds invariants, can by default still be changed.
     let mut a = CanBeChanged::new(Unfilled::A);
     // There is no enum value for `3` or `0b11`, but we can set it anyways:
     a.value = u4::new(3);
+    let _ = a;
     // This panics internally:
     // a.val_0();
 
+    // `#[bitsize(4, sealed)]` puts the generated struct inside its own module, so this doesn't compile:
+    // b.value = u4::new(3);
+    // Raw access needs to go through these instead:
+    let b = CantBeChanged::new(Unfilled::B);
+    let raw = b.to_raw();
+    let _b = unsafe { CantBeChanged::from_raw_unchecked(raw) };
+
     // Here we try to use a custom impl and also put the generated code inside a module,
     // thereby making `.value` inaccessible.
     // Let's say we want bit 3, 4 and 31, so 0, 1, 1:
@@ -43,18 +55,14 @@ mod somebits {
     }
 
     // should not really be allowed, though might be useful for optimization
-    // we could provide a feature toggle, or just an attribute param to control this
-    //
-    // basically, putting the generated struct ThingsIWant into a module and exposing
-    // it with a use statement again would be less "leaky", but this would also
-    // mean all the private items would not be accessible..
-    //
-    // If we find any other way to make `value` inaccessible, update this.
+    // `#[bitsize(3, sealed)]` prevents this, by putting the generated struct into
+    // its own module and exposing it with a use statement again
     #[allow(dead_code)]
     fn modify_inner() {
         let a = 0b10101010;
         let mut b = SomeBits::new(true, true, true);
         b.value = u3::new(a);
+        let _ = b;
     }
 }
 use somebits::*;
@@ -0,0 +1,337 @@
+//! Exports bitfield layouts for other languages, built on [`Layout`].
+//!
+//! [`c_header`] writes a C header for a single bitfield, [`CHeader`] one for several bitfields and enums:
+//! ```
+//! # use bilge::prelude::*;
+//! use bilge::export::{CHeader, CItem};
+//!
+//! #[bitsize(2)]
+//! #[derive(FromBits)]
+//! enum Mode {
+//!     Off,
+//!     Slow,
+//!     Fast,
+//!     Turbo,
+//! }
+//!
+//! #[bitsize(8)]
+//! #[derive(FromBits)]
+//! struct Control {
+//!     enable: bool,
+//!     mode: Mode,
+//!     divider: u5,
+//! }
+//!
+//! // e.g. written to a file from a `build.rs`
+//! let header = CHeader::new("CONTROL_H", &[CItem::of::<Mode>(), CItem::of::<Control>()]).to_string();
+//! assert!(header.contains("#define MODE_FAST 0x02u"));
+//! assert!(header.contains("#define CONTROL_DIVIDER_MASK 0xF8u"));
+//! assert!(header.contains("static inline uint8_t control_get_divider(uint8_t value)"));
+//! ```
+//! Structs get `#define`s for the `OFFSET`, `BITS` and `MASK` of every field and `static inline` functions
+//! mirroring their accessors, which take and return the raw value: `control_get_mode(value)`, `control_set_mode(value, field)`
+//! and, for `#[access(w1c)]`, `control_clear_mode(value)`. Enums get a `#define` per variant.
+//! Fields which are enums, structs or arrays are read and written as raw integers.
+//!
+//! C has no integers above 64 bits, so bigger bitfields become an `#error`, as do items with two `#define`s of the
+//! same name, like an enum with a variant `Bits` next to its `MODE_BITS`.
+
+use core::fmt::{self, Display, Formatter, Write};
+
+use crate::{Access, FieldInfo, Layout, VariantInfo};
+
+/// Returns a C header for `T` alone, guarded by `T_H`, e.g. `CONTROL_H`.
+pub fn c_header<T: Layout>() -> CHeader<'static> {
+    CHeader {
+        guard: None,
+        items: Items::One(CItem::of::<T>()),
+    }
+}
+
+/// A C header for several bitfields and enums, written through its [`Display`] implementation.
+#[derive(Debug, Clone, Copy)]
+pub struct CHeader<'a> {
+    /// `None` for [`c_header`], which names the guard after its item
+    guard: Option<&'a str>,
+    items: Items<'a>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Items<'a> {
+    One(CItem),
+    Many(&'a [CItem]),
+}
+
+impl<'a> CHeader<'a> {
+    /// Exports `items` in the given order, guarded by `#ifndef guard`.
+    pub const fn new(guard: &'a str, items: &'a [CItem]) -> CHeader<'a> {
+        CHeader {
+            guard: Some(guard),
+            items: Items::Many(items),
+        }
+    }
+}
+
+/// A bitfield struct or enum to export, see [`CHeader`].
+#[derive(Debug, Clone, Copy)]
+pub struct CItem {
+    name: &'static str,
+    bits: usize,
+    fields: &'static [FieldInfo],
+    variants: &'static [VariantInfo],
+}
+
+impl CItem {
+    /// Exports `T` under the name of its type, e.g. `control_get_mode` and `CONTROL_MODE_MASK` for `Control`.
+    pub fn of<T: Layout>() -> CItem {
+        CItem {
+            name: short_type_name(core::any::type_name::<T>()),
+            bits: T::BITS,
+            fields: T::FIELDS,
+            variants: T::VARIANTS,
+        }
+    }
+
+    /// Exports this item under `name` instead, like `Uart0Control`, e.g. to tell apart instances of a generic bitfield.
+    pub const fn named(self, name: &'static str) -> CItem {
+        CItem { name, ..self }
+    }
+}
+
+impl Display for CHeader<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let items = match &self.items {
+            Items::One(item) => core::slice::from_ref(item),
+            Items::Many(items) => items,
+        };
+        let guard = Guard(self.guard, items.first().map_or("", |item| item.name));
+
+        writeln!(f, "/* Generated by bilge, don't edit this by hand. */")?;
+        writeln!(f, "#ifndef {guard}")?;
+        writeln!(f, "#define {guard}")?;
+        writeln!(f)?;
+        writeln!(f, "#include <stdbool.h>")?;
+        writeln!(f, "#include <stdint.h>")?;
+        for item in items {
+            writeln!(f)?;
+            item.fmt(f)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "#endif /* {guard} */")
+    }
+}
+
+impl Display for CItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let CItem { name, bits, .. } = *self;
+        let upper = Case::Upper(name);
+        writeln!(f, "/* {name}: {bits} bits */")?;
+        let Some(int) = CInt::fitting(bits) else {
+            return writeln!(f, "#error \"{name} has {bits} bits, which don't fit into a C integer\"");
+        };
+        if let Some((a, b)) = self.collision() {
+            return writeln!(f, "#error \"{name}: {a} and {b} have the same name in C\"");
+        }
+        writeln!(f, "#define {upper}_BITS {bits}u")?;
+
+        for variant in self.variants {
+            writeln!(f, "#define {upper}_{} {}", Case::Upper(variant.name), int.literal(variant.value))?;
+        }
+
+        for field in self.exported_fields() {
+            let field_name = Case::Upper(field.name);
+            writeln!(f, "#define {upper}_{field_name}_OFFSET {}u", field.offset)?;
+            writeln!(f, "#define {upper}_{field_name}_BITS {}u", field.width)?;
+            let mask = (u128::MAX >> (128 - field.width)) << field.offset;
+            writeln!(f, "#define {upper}_{field_name}_MASK {}", int.literal(mask))?;
+        }
+
+        let lower = Case::Lower(name);
+        for field in self.exported_fields() {
+            let (field_upper, field_lower) = (Case::Upper(field.name), Case::Lower(field.name));
+            let is_bool = field.type_name == "bool";
+            let field_type = if is_bool {
+                "bool"
+            } else {
+                CInt::fitting(field.width).unwrap_or(int).name()
+            };
+            let int = int.name();
+            let (mask, offset) = (Prefixed(upper, field_upper, "MASK"), Prefixed(upper, field_upper, "OFFSET"));
+
+            if !matches!(field.access, Access::WriteOnly) {
+                writeln!(f)?;
+                writeln!(f, "static inline {field_type} {lower}_get_{field_lower}({int} value) {{")?;
+                if is_bool {
+                    writeln!(f, "    return (value & {mask}) != 0u;")?;
+                } else {
+                    writeln!(f, "    return ({field_type})((value & {mask}) >> {offset});")?;
+                }
+                writeln!(f, "}}")?;
+            }
+            match field.access {
+                Access::ReadWrite | Access::WriteOnly => {
+                    writeln!(f)?;
+                    writeln!(f, "static inline {int} {lower}_set_{field_lower}({int} value, {field_type} field) {{")?;
+                    writeln!(f, "    return ({int})((value & ~{mask}) | ((({int})field << {offset}) & {mask}));")?;
+                    writeln!(f, "}}")?;
+                }
+                Access::WriteOneToClear => {
+                    writeln!(f)?;
+                    writeln!(f, "static inline {int} {lower}_clear_{field_lower}({int} value) {{")?;
+                    writeln!(f, "    return ({int})(value | {mask});")?;
+                    writeln!(f, "}}")?;
+                }
+                Access::ReadOnly | Access::ReadToClear => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl CItem {
+    /// The fields with accessors, which leaves out reserved ones.
+    fn exported_fields(&self) -> impl Iterator<Item = &'static FieldInfo> + Clone {
+        self.fields.iter().filter(|field| !field.reserved && field.width > 0)
+    }
+
+    /// Two names which end up as the same `#define`, like the size of `Mode` and its variant `Bits`, both `MODE_BITS`.
+    fn collision(&self) -> Option<(Name, Name)> {
+        let same = |a: &str, b: &str| Case::Upper(a).chars().eq(Case::Upper(b).chars());
+        if let Some(variant) = self.variants.iter().find(|variant| same(variant.name, "BITS")) {
+            return Some((Name::Size, Name::Item(variant.name)));
+        }
+        // `RxOnly` and `RX_ONLY` are both `RX_ONLY`
+        let variants = self.variants.iter().map(|variant| variant.name);
+        let fields = self.exported_fields().map(|field| field.name);
+        first_duplicate(variants, same).or_else(|| first_duplicate(fields, same))
+    }
+}
+
+/// A name which becomes part of a `#define`, see [`CItem::collision`].
+enum Name {
+    /// the size, as in `MODE_BITS`
+    Size,
+    /// a variant or field
+    Item(&'static str),
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Name::Size => f.write_str("its size"),
+            Name::Item(name) => write!(f, "`{name}`"),
+        }
+    }
+}
+
+fn first_duplicate(mut names: impl Iterator<Item = &'static str> + Clone, same: impl Fn(&str, &str) -> bool) -> Option<(Name, Name)> {
+    while let Some(name) = names.next() {
+        if let Some(other) = names.clone().find(|other| same(name, other)) {
+            return Some((Name::Item(name), Name::Item(other)));
+        }
+    }
+    None
+}
+
+/// The smallest C integer holding a bitfield.
+#[derive(Clone, Copy)]
+enum CInt {
+    U8,
+    U16,
+    U32,
+    U64,
+}
+
+impl CInt {
+    fn fitting(bits: usize) -> Option<CInt> {
+        match bits {
+            0..=8 => Some(CInt::U8),
+            9..=16 => Some(CInt::U16),
+            17..=32 => Some(CInt::U32),
+            33..=64 => Some(CInt::U64),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CInt::U8 => "uint8_t",
+            CInt::U16 => "uint16_t",
+            CInt::U32 => "uint32_t",
+            CInt::U64 => "uint64_t",
+        }
+    }
+
+    /// A hexadecimal literal with all digits of this integer, like `0x0Fu`.
+    fn literal(self, value: u128) -> impl Display {
+        struct Literal(CInt, u128);
+        impl Display for Literal {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                let Literal(int, value) = *self;
+                match int {
+                    CInt::U8 => write!(f, "0x{value:02X}u"),
+                    CInt::U16 => write!(f, "0x{value:04X}u"),
+                    CInt::U32 => write!(f, "0x{value:08X}u"),
+                    CInt::U64 => write!(f, "0x{value:016X}ull"),
+                }
+            }
+        }
+        Literal(self, value)
+    }
+}
+
+/// `Control` or `divider` as `CONTROL` and `DIVIDER` for macros, `RxOnly` as `rx_only` for functions.
+#[derive(Clone, Copy)]
+enum Case<'a> {
+    Upper(&'a str),
+    Lower(&'a str),
+}
+
+impl<'a> Case<'a> {
+    /// The converted name, one character at a time.
+    fn chars(self) -> impl Iterator<Item = char> + Clone + 'a {
+        let (Case::Upper(name) | Case::Lower(name)) = self;
+        let upper = matches!(self, Case::Upper(_));
+        let mut previous: Option<char> = None;
+        name.chars().flat_map(move |char| {
+            // `RxOnly` -> `Rx_Only`, but `val_0` and `UART` stay as they are
+            let separator = char.is_ascii_uppercase() && previous.is_some_and(|previous| previous.is_ascii_lowercase() || previous.is_ascii_digit());
+            previous = Some(char);
+            let char = if upper { char.to_ascii_uppercase() } else { char.to_ascii_lowercase() };
+            separator.then_some('_').into_iter().chain(Some(char))
+        })
+    }
+}
+
+impl Display for Case<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.chars().try_for_each(|char| f.write_char(char))
+    }
+}
+
+/// `CONTROL_MODE_MASK`
+struct Prefixed<'a>(Case<'a>, Case<'a>, &'static str);
+
+impl Display for Prefixed<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}_{}", self.0, self.1, self.2)
+    }
+}
+
+/// The given guard, or `CONTROL_H` for an item named `Control`.
+struct Guard<'a>(Option<&'a str>, &'a str);
+
+impl Display for Guard<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(guard) => f.write_str(guard),
+            None => write!(f, "{}_H", Case::Upper(self.1)),
+        }
+    }
+}
+
+/// `crate::registers::Entry<u4>` -> `Entry`
+fn short_type_name(type_name: &'static str) -> &'static str {
+    let without_generics = type_name.split('<').next().unwrap_or(type_name);
+    without_generics.rsplit("::").next().unwrap_or(without_generics)
+}
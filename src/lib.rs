@@ -3,9 +3,12 @@
 
 use core::fmt;
 
+pub mod export;
+pub mod mmio;
+
 #[doc(no_inline)]
 pub use arbitrary_int;
-pub use bilge_impl::{bitsize, bitsize_internal, BinaryBits, DebugBits, DefaultBits, FromBits, TryFromBits};
+pub use bilge_impl::{bitsize, bitsize_internal, register_block, BinaryBits, DebugBits, DefaultBits, FlagsBits, FromBits, TryFromBits};
 #[cfg(feature = "serde")]
 pub use bilge_impl::{DeserializeBits, SerializeBits};
 
@@ -15,7 +18,7 @@ pub mod prelude {
     #[doc(no_inline)]
     pub use super::{
         bitsize, Bitsized,
-        FromBits, TryFromBits, DebugBits, BinaryBits, DefaultBits,
+        FromBits, TryFromBits, DebugBits, BinaryBits, DefaultBits, FlagsBits,
         // we control the version, so this should not be a problem
         arbitrary_int::prelude::*,
     };
@@ -24,6 +27,9 @@ pub mod prelude {
 }
 
 /// This is internally used, but might be useful. No guarantees are given (for now).
+///
+/// Types implementing this by hand are converted through `From` and `TryFrom` when used as fields, so they can't be
+/// fields of `#[bitsize(N, const_fields)]` bitfields, whose accessors use hidden const fns only `#[bitsize]` generates.
 pub trait Bitsized {
     /// The arbitrary_int type, used internally to 'generically' access its methods.
     type ArbitraryInt;
@@ -31,6 +37,12 @@ pub trait Bitsized {
     const BITS: usize;
     /// The maximum value this type can hold.
     const MAX: Self::ArbitraryInt;
+    /// Which values `TryFrom` accepts, as far as `#[bitsize]` knows. [`Validity::Unknown`] for types implementing this by hand.
+    #[doc(hidden)]
+    const VALIDITY: Validity = Validity::Unknown;
+    /// The bits of `#[access(w1c)]` fields, which [`mmio::Reg::modify`] doesn't write back.
+    #[doc(hidden)]
+    const __BILGE_W1C_MASK: u128 = 0;
 }
 
 /// Internally used marker trait.
@@ -40,26 +52,495 @@ pub trait Bitsized {
 pub unsafe trait Filled: Bitsized {}
 unsafe impl<T> Filled for T where T: Bitsized + From<<T as Bitsized>::ArbitraryInt> {}
 
+/// Internally used as the bound of generic fields, like `T` in `struct Entry<T> { flag: bool, payload: T }`.
+///
+/// Every type usable as a bitfield field implements this, so there is no need to implement it yourself.
+pub trait BitsizedField: Bitsized<ArbitraryInt: arbitrary_int::traits::Integer + From<Self>> + TryFrom<<Self as Bitsized>::ArbitraryInt> {}
+impl<T> BitsizedField for T
+where
+    T: Bitsized + TryFrom<<T as Bitsized>::ArbitraryInt>,
+    <T as Bitsized>::ArbitraryInt: arbitrary_int::traits::Integer + From<T>,
+{
+}
+
+/// Describes every field of a bitfield struct or every variant of a bitfield enum,
+/// for generic tooling like register viewers, dumps, documentation or [`export`].
+///
+/// `#[bitsize]` implements this for every struct, and for every enum besides tagged ones.
+/// ```
+/// # use bilge::prelude::*;
+/// #[bitsize(8)]
+/// #[derive(FromBits)]
+/// struct Status {
+///     ready: bool,
+///     reserved: u3,
+///     #[access(ro)]
+///     count: u4,
+/// }
+///
+/// for field in <Status as bilge::Layout>::FIELDS {
+///     if !field.reserved {
+///         // "ready: bool, bits 0..1" and "count: u4, bits 4..8"
+///         let (name, type_name, start, end) = (field.name, field.type_name, field.offset, field.offset + field.width);
+///     }
+/// }
+/// ```
+pub trait Layout: Bitsized {
+    /// All fields in declaration order, including reserved ones. Empty for enums.
+    const FIELDS: &'static [FieldInfo];
+    /// All variants in declaration order, besides a `#[fallback]` with value. Empty for structs.
+    const VARIANTS: &'static [VariantInfo] = &[];
+}
+
+/// A field of a bitfield struct, see [`Layout`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+    /// The name of the field's getter, which is `val_0` and so on for tuple structs.
+    pub name: &'static str,
+    /// The position of the field's least significant bit, counting from the least significant bit of the bitfield.
+    pub offset: usize,
+    /// The size of the field in bits.
+    pub width: usize,
+    /// The field's type, as written in the struct.
+    pub type_name: &'static str,
+    /// The field's access policy, given by `#[access(..)]`.
+    pub access: Access,
+    /// Whether this is a `reserved` or `padding` field.
+    pub reserved: bool,
+}
+
+impl FieldInfo {
+    /// Internally used by `#[bitsize]`, since `FieldInfo` can't be created outside of `bilge`.
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, offset: usize, width: usize, type_name: &'static str, access: Access, reserved: bool) -> FieldInfo {
+        FieldInfo {
+            name,
+            offset,
+            width,
+            type_name,
+            access,
+            reserved,
+        }
+    }
+}
+
+/// A variant of a bitfield enum, see [`Layout`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariantInfo {
+    /// The variant's name.
+    pub name: &'static str,
+    /// The variant's value, like `u8::from(Enum::Variant)`. Negative discriminants are stored as two's complement.
+    pub value: u128,
+}
+
+impl VariantInfo {
+    /// Internally used by `#[bitsize]`, since `VariantInfo` can't be created outside of `bilge`.
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, value: u128) -> VariantInfo {
+        VariantInfo { name, value }
+    }
+}
+
+/// How a field may be accessed, which decides the accessors it gets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// `#[access(rw)]`, the default: a getter and a setter.
+    ReadWrite,
+    /// `#[access(ro)]`: only a getter.
+    ReadOnly,
+    /// `#[access(wo)]`: only a setter.
+    WriteOnly,
+    /// `#[access(w1c)]`: a getter and `clear_field()`, which writes ones.
+    WriteOneToClear,
+    /// `#[access(rc)]`: only a getter, reading clears the field in hardware.
+    ReadToClear,
+}
+
 /// This is generated to statically validate that a type implements `FromBits`.
 pub const fn assume_filled<T: Filled>() {}
 
-/// The error type used for `TryFromBits`.
+/// The error type used for `TryFromBits`, telling which field held an invalid value.
+///
+/// ```
+/// # use bilge::prelude::*;
+/// # use bilge::PathSegment;
+/// #[bitsize(2)]
+/// #[derive(TryFromBits, Debug, Clone, Copy)]
+/// enum Mode { Off, Slow, Fast }
+///
+/// #[bitsize(8)]
+/// #[derive(TryFromBits, DebugBits)]
+/// struct Status {
+///     ready: bool,
+///     modes: [Mode; 2],
+///     reserved: u3,
+/// }
+///
+/// let error = Status::try_from(0b000_11_00_1).unwrap_err();
+/// assert_eq!(error.path(), &[PathSegment::Field("modes"), PathSegment::Index(1)]);
+/// assert_eq!((error.offset(), error.value()), (3, 0b11));
+/// assert_eq!(error.to_string(), "invalid value 0x3 in `modes[1]` at bit 3");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct BitsError {
+    /// the outermost segments of the path, if it's longer than `MAX_DEPTH`
+    path: [PathSegment; BitsError::MAX_DEPTH],
+    depth: usize,
+    offset: usize,
+    value: u128,
+    kind: BitsErrorKind,
+}
+
+/// What went wrong, see [`BitsError::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[non_exhaustive]
-#[derive(Debug, PartialEq)]
-pub struct BitsError;
+pub enum BitsErrorKind {
+    /// A field, or the whole value, held a value without a matching enum variant or the wrong required value.
+    InvalidValue,
+    /// `try_from_le_slice` or `try_from_be_slice` was given `found` bytes instead of `expected`.
+    InvalidLength { expected: usize, found: usize },
+}
+
+/// A step in the [path](BitsError::path) to an invalid field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment {
+    /// A struct field by its getter name, or a tuple element like `"0"`.
+    Field(&'static str),
+    /// An array element.
+    Index(usize),
+    /// The payload of a tagged enum variant, by its name.
+    Variant(&'static str),
+}
+
+impl BitsError {
+    /// The number of path segments kept, deeper ones are cut off.
+    pub const MAX_DEPTH: usize = 4;
+
+    /// Whether a value or the number of bytes was invalid.
+    pub const fn kind(&self) -> BitsErrorKind {
+        self.kind
+    }
+
+    /// The field holding the invalid value, starting at the outermost struct, like `status.modes[1]`.
+    ///
+    /// Empty if the value itself is invalid, e.g. for an enum.
+    ///
+    /// The path stops at a generic field, even if its type is a bitfield with a path of its own,
+    /// since `TryFromBits` can't tell its error apart from any other there.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.path[..self.depth.min(Self::MAX_DEPTH)]
+    }
+
+    /// Whether [`path`](Self::path) misses segments beyond [`MAX_DEPTH`](Self::MAX_DEPTH).
+    pub const fn is_path_truncated(&self) -> bool {
+        self.depth > Self::MAX_DEPTH
+    }
+
+    /// The position of the invalid field's least significant bit, counting from the least significant bit of the whole value.
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The raw value of the invalid field, or the number of bytes given to `try_from_le_slice` or `try_from_be_slice`.
+    pub const fn value(&self) -> u128 {
+        self.value
+    }
+
+    /// Puts this error into a field at `path` and `offset`, like an enum into its struct.
+    const fn nested(mut self, path: &[PathSegment], offset: usize) -> BitsError {
+        let mut nested = [PathSegment::Index(0); Self::MAX_DEPTH];
+        // constness: iter, for-loop, range are not const, so we're using while loops
+        let mut i = 0;
+        while i < Self::MAX_DEPTH {
+            if i < path.len() {
+                nested[i] = path[i];
+            } else if i - path.len() < self.depth {
+                nested[i] = self.path[i - path.len()];
+            }
+            i += 1;
+        }
+        self.path = nested;
+        self.depth += path.len();
+        self.offset += offset;
+        self
+    }
+}
 
 impl fmt::Display for BitsError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "unable to parse bit pattern")
+        if let BitsErrorKind::InvalidLength { expected, found } = self.kind {
+            return write!(f, "expected {expected} bytes, found {found}");
+        }
+        write!(f, "invalid value {:#x}", self.value)?;
+        if self.depth > 0 {
+            write!(f, " in `{}` at bit {}", DisplayPath(self), self.offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BitsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let BitsErrorKind::InvalidLength { expected, found } = self.kind {
+            return f.debug_struct("BitsError").field("expected", &expected).field("found", &found).finish();
+        }
+        f.debug_struct("BitsError")
+            .field("path", &self.path())
+            .field("offset", &self.offset)
+            .field("value", &self.value)
+            .finish()
     }
 }
 
+/// `modes[1].level`
+struct DisplayPath<'a>(&'a BitsError);
+
+impl fmt::Display for DisplayPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, segment) in self.0.path().iter().enumerate() {
+            match segment {
+                PathSegment::Field(name) | PathSegment::Variant(name) if i == 0 => write!(f, "{name}")?,
+                PathSegment::Field(name) | PathSegment::Variant(name) => write!(f, ".{name}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        if self.0.is_path_truncated() {
+            write!(f, "..")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for BitsError {}
+
 /// Internally used for generating the `Result::Err` type in `TryFrom`.
 ///
 /// This is needed since we don't want users to be able to create `BitsError` right now.
-/// We'll be able to turn `BitsError` into an enum later, or anything else really.
+#[deprecated(note = "use `give_me_value_error`, which keeps the invalid value")]
 pub const fn give_me_error() -> BitsError {
-    BitsError
+    give_me_value_error(0)
+}
+
+/// Internally used for generating the `Result::Err` type in `TryFrom`, for an invalid `value`.
+pub const fn give_me_value_error(value: u128) -> BitsError {
+    BitsError {
+        path: [PathSegment::Index(0); BitsError::MAX_DEPTH],
+        depth: 0,
+        offset: 0,
+        value,
+        kind: BitsErrorKind::InvalidValue,
+    }
+}
+
+/// Internally used for `try_from_*_slice`, when given `len` instead of `expected_len` bytes.
+pub const fn give_me_length_error(expected_len: usize, len: usize) -> BitsError {
+    BitsError {
+        kind: BitsErrorKind::InvalidLength {
+            expected: expected_len,
+            found: len,
+        },
+        ..give_me_value_error(len as u128)
+    }
+}
+
+/// Internally used by `TryFromBits` for a field at `path` and `offset` holding the invalid `value`, like a `reserved` field.
+pub const fn give_me_field_error(path: &[PathSegment], offset: usize, value: u128) -> BitsError {
+    give_me_value_error(value).nested(path, offset)
+}
+
+/// Internally used by `TryFromBits` to put the error of an invalid field into a [`BitsError`], see [`NestBitsError`].
+#[doc(hidden)]
+pub struct FieldError<E>(pub E);
+
+/// Internally used by `TryFromBits`, which calls `(&FieldError(error)).nest(..)`.
+///
+/// Errors of nested bitfields keep their path below this field, while any other error starts at this field.
+/// Method resolution picks the first impl for `BitsError`, without specialization.
+/// It happens before a generic field's type is known, so those always get the second impl.
+#[doc(hidden)]
+pub trait NestBitsError {
+    fn nest(&self, path: &[PathSegment], offset: usize, value: u128) -> BitsError;
+}
+
+impl NestBitsError for FieldError<BitsError> {
+    fn nest(&self, path: &[PathSegment], offset: usize, _value: u128) -> BitsError {
+        self.0.nested(path, offset)
+    }
+}
+
+impl<E> NestBitsError for &FieldError<E> {
+    fn nest(&self, path: &[PathSegment], offset: usize, value: u128) -> BitsError {
+        give_me_field_error(path, offset, value)
+    }
+}
+
+/// Internally used for reading a field out of a wide bitfield (one above 128 bits).
+///
+/// Returns `width` bits of `bytes`, starting at bit `offset`, where byte `0` holds bits `0..8`.
+/// `width` can be at most 128, since every field type is at most an `u128`.
+pub const fn read_bits<const N: usize>(bytes: &[u8; N], offset: usize, width: usize) -> u128 {
+    let mut value = 0;
+    let mut done = 0;
+    // constness: iter, for-loop, range are not const, so we're using while loops
+    while done < width {
+        let bit = offset + done;
+        let shift = bit % 8;
+        // read up to the end of this byte or of this field, whichever comes first
+        let take = if 8 - shift < width - done { 8 - shift } else { width - done };
+        let chunk = (bytes[bit / 8] >> shift) as u128 & ((1 << take) - 1);
+        value |= chunk << done;
+        done += take;
+    }
+    value
+}
+
+/// Internally used for writing a field into a wide bitfield (one above 128 bits).
+///
+/// Overwrites `width` bits of `bytes`, starting at bit `offset`, with the lowest `width` bits of `value`.
+pub const fn write_bits<const N: usize>(bytes: &mut [u8; N], offset: usize, width: usize, value: u128) {
+    let mut done = 0;
+    while done < width {
+        let bit = offset + done;
+        let shift = bit % 8;
+        let take = if 8 - shift < width - done { 8 - shift } else { width - done };
+        let mask = (((1u16 << take) - 1) << shift) as u8;
+        let chunk = ((value >> done) as u8) << shift;
+        bytes[bit / 8] = (bytes[bit / 8] & !mask) | (chunk & mask);
+        done += take;
+    }
+}
+
+/// Internally used to check `#[reset = ..]` of `DefaultBits` against `TryFromBits` at compile time.
+///
+/// `TryFrom` can't be called in const contexts, so `#[bitsize]` describes the values it accepts with this instead.
+#[doc(hidden)]
+#[derive(Debug)]
+pub enum Validity {
+    /// Every value, like for `uN` or enums with a fallback.
+    All,
+    /// Not known, since `Bitsized` and `TryFrom` are implemented by hand. Accepts every value, see [`Validity::is_known`].
+    Unknown,
+    /// Only these values, like the discriminants of an enum.
+    OneOf(&'static [u128]),
+    /// A valid value in every field of a struct.
+    Fields(&'static [FieldValidity]),
+    /// A tag with a variant, holding a valid payload.
+    Tagged {
+        tag_offset: usize,
+        tag_bits: usize,
+        payload_offset: usize,
+        payload_bits: usize,
+        /// every tag with the validity of its payload, which only accepts zero for unit variants
+        variants: &'static [(u128, &'static Validity)],
+        /// whether a fallback takes all other tags
+        has_fallback: bool,
+    },
+}
+
+/// A field of a struct, or all elements of an array field, see [`Validity::Fields`].
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct FieldValidity {
+    pub offset: usize,
+    /// the size of one element
+    pub bits: usize,
+    /// the number of elements, which is `1` for anything but arrays
+    pub len: usize,
+    pub validity: &'static Validity,
+}
+
+impl Validity {
+    /// Whether `TryFrom` accepts `value`, of a type up to 128 bits.
+    pub const fn accepts(&self, value: u128) -> bool {
+        match self {
+            Validity::All | Validity::Unknown => true,
+            Validity::OneOf(values) => {
+                // constness: iter, for-loop, range are not const, so we're using while loops
+                let mut i = 0;
+                while i < values.len() {
+                    if values[i] == value {
+                        return true;
+                    }
+                    i += 1;
+                }
+                false
+            }
+            Validity::Fields(fields) => {
+                let mut i = 0;
+                while i < fields.len() {
+                    let FieldValidity { offset, bits, len, validity } = fields[i];
+                    let mut element = 0;
+                    while element < len {
+                        if !validity.accepts(extract(value, offset + element * bits, bits)) {
+                            return false;
+                        }
+                        element += 1;
+                    }
+                    i += 1;
+                }
+                true
+            }
+            Validity::Tagged {
+                tag_offset,
+                tag_bits,
+                payload_offset,
+                payload_bits,
+                variants,
+                has_fallback,
+            } => {
+                let tag = extract(value, *tag_offset, *tag_bits);
+                let mut i = 0;
+                while i < variants.len() {
+                    let (variant_tag, payload) = variants[i];
+                    if variant_tag == tag {
+                        return payload.accepts(extract(value, *payload_offset, *payload_bits));
+                    }
+                    i += 1;
+                }
+                *has_fallback
+            }
+        }
+    }
+
+    /// Whether [`accepts`](Self::accepts) matches `TryFrom`, which it doesn't if any field is [`Validity::Unknown`].
+    pub const fn is_known(&self) -> bool {
+        match self {
+            Validity::All | Validity::OneOf(_) => true,
+            Validity::Unknown => false,
+            Validity::Fields(fields) => {
+                // constness: iter, for-loop, range are not const, so we're using while loops
+                let mut i = 0;
+                while i < fields.len() {
+                    if !fields[i].validity.is_known() {
+                        return false;
+                    }
+                    i += 1;
+                }
+                true
+            }
+            Validity::Tagged { variants, .. } => {
+                let mut i = 0;
+                while i < variants.len() {
+                    if !variants[i].1.is_known() {
+                        return false;
+                    }
+                    i += 1;
+                }
+                true
+            }
+        }
+    }
+}
+
+/// `bits` bits of `value`, starting at bit `offset`.
+const fn extract(value: u128, offset: usize, bits: usize) -> u128 {
+    if bits == 0 {
+        0
+    } else {
+        (value >> offset) & (u128::MAX >> (128 - bits))
+    }
 }
 
 /// Only basing this on Integer did not work, as bool and others are not Integer.
@@ -74,6 +555,7 @@ where
     type ArbitraryInt = Self;
     const BITS: usize = BITS;
     const MAX: Self::ArbitraryInt = <Self as arbitrary_int::traits::Integer>::MAX;
+    const VALIDITY: Validity = Validity::All;
 }
 
 impl<BaseType, const BITS: usize> Bitsized for arbitrary_int::Int<BaseType, BITS>
@@ -84,6 +566,7 @@ where
     type ArbitraryInt = Self;
     const BITS: usize = BITS;
     const MAX: Self::ArbitraryInt = <Self as arbitrary_int::traits::Integer>::MAX;
+    const VALIDITY: Validity = Validity::All;
 }
 
 macro_rules! bitsized_impl {
@@ -93,6 +576,7 @@ macro_rules! bitsized_impl {
                 type ArbitraryInt = Self;
                 const BITS: usize = $bits;
                 const MAX: Self::ArbitraryInt = <Self as arbitrary_int::traits::Integer>::MAX;
+                const VALIDITY: Validity = Validity::All;
             }
         )+
     };
@@ -105,4 +589,5 @@ impl Bitsized for bool {
     type ArbitraryInt = arbitrary_int::u1;
     const BITS: usize = 1;
     const MAX: Self::ArbitraryInt = <arbitrary_int::u1 as arbitrary_int::traits::Integer>::MAX;
+    const VALIDITY: Validity = Validity::All;
 }
@@ -0,0 +1,231 @@
+//! Memory-mapped registers holding bitfields.
+//!
+//! Every register wraps a pointer to the bitfield's underlying integer, like a `*mut u32` for a `#[bitsize(32)]` struct,
+//! and accesses it with volatile reads and writes:
+//! ```
+//! # use bilge::prelude::*;
+//! use bilge::mmio::Reg;
+//!
+//! #[bitsize(32)]
+//! #[derive(FromBits)]
+//! struct Control {
+//!     enable: bool,
+//!     divider: u4,
+//!     reserved: u27,
+//! }
+//!
+//! // this would be the register's address, like `0x4000_1000 as *mut u32`
+//! let mut memory = 0u32;
+//! let control = unsafe { Reg::<Control>::new(&mut memory) };
+//!
+//! control.write(Control::new(true, u4::new(3)));
+//! // one volatile read, one volatile write
+//! control.modify(|control| control.set_divider(u4::new(9)));
+//! assert_eq!(control.read().divider(), u4::new(9));
+//! ```
+//! Bits above the bitfield's size are ignored when reading and written as zero.
+
+use core::marker::PhantomData;
+use core::ptr;
+
+use arbitrary_int::traits::Integer;
+
+use crate::Bitsized;
+
+/// The integer a register of `T` points to, e.g. `u8` for a `#[bitsize(5)]` bitfield.
+pub type RawOf<T> = <<T as Bitsized>::ArbitraryInt as Integer>::UnderlyingType;
+
+/// Implemented by [`Reg`], [`RoReg`] and [`WoReg`], so [`register_block`](crate::register_block) can lay them out.
+pub trait Register {
+    /// The bitfield held by this register.
+    type Bitfield: Bitsized<ArbitraryInt: Integer>;
+}
+
+impl<T: Bitsized<ArbitraryInt: Integer>> Register for Reg<T> {
+    type Bitfield = T;
+}
+
+impl<T: Bitsized<ArbitraryInt: Integer>> Register for RoReg<T> {
+    type Bitfield = T;
+}
+
+impl<T: Bitsized<ArbitraryInt: Integer>> Register for WoReg<T> {
+    type Bitfield = T;
+}
+
+/// Reads the raw value, ignoring any bits above `T::BITS`.
+///
+/// # Safety
+///
+/// See [`Reg::new`].
+unsafe fn read<T>(ptr: *mut RawOf<T>) -> T::ArbitraryInt
+where
+    T: Bitsized<ArbitraryInt: Integer>,
+{
+    <T::ArbitraryInt as Integer>::masked_new(ptr::read_volatile(ptr))
+}
+
+/// # Safety
+///
+/// See [`Reg::new`].
+unsafe fn write<T>(ptr: *mut RawOf<T>, value: T)
+where
+    T: Bitsized<ArbitraryInt: Integer + From<T>>,
+{
+    ptr::write_volatile(ptr, T::ArbitraryInt::from(value).value())
+}
+
+/// Zeroes the bits of `#[access(w1c)]` fields, since writing back a pending 1 would clear it.
+fn without_w1c<T>(value: T::ArbitraryInt) -> T::ArbitraryInt
+where
+    T: Bitsized<ArbitraryInt: Integer>,
+{
+    <T::ArbitraryInt as Integer>::masked_new(value.as_u128() & !T::__BILGE_W1C_MASK)
+}
+
+/// A register which can be read and written.
+pub struct Reg<T: Bitsized<ArbitraryInt: Integer>> {
+    ptr: *mut RawOf<T>,
+    _bitfield: PhantomData<T>,
+}
+
+impl<T: Bitsized<ArbitraryInt: Integer>> Reg<T> {
+    /// Creates a register at `ptr`, which can also point to plain memory.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` needs to be valid for volatile reads and writes, and aligned, for as long as this register is used.
+    pub const unsafe fn new(ptr: *mut RawOf<T>) -> Self {
+        Reg { ptr, _bitfield: PhantomData }
+    }
+
+    /// Returns the register's address.
+    pub const fn as_ptr(&self) -> *mut RawOf<T> {
+        self.ptr
+    }
+
+    /// Reads the register once.
+    pub fn read(&self) -> T
+    where
+        T: From<T::ArbitraryInt>,
+    {
+        // SAFETY: guaranteed by `new`
+        T::from(unsafe { read::<T>(self.ptr) })
+    }
+
+    /// Reads the register once, validating its value.
+    pub fn try_read(&self) -> Result<T, T::Error>
+    where
+        T: TryFrom<T::ArbitraryInt>,
+    {
+        // SAFETY: guaranteed by `new`
+        T::try_from(unsafe { read::<T>(self.ptr) })
+    }
+
+    /// Writes the register once.
+    pub fn write(&self, value: T)
+    where
+        T::ArbitraryInt: From<T>,
+    {
+        // SAFETY: guaranteed by `new`
+        unsafe { write(self.ptr, value) }
+    }
+
+    /// Reads the register once, lets `f` change the value and writes it back once.
+    ///
+    /// `#[access(w1c)]` fields are read as zero, so only the ones cleared by `f` get acknowledged.
+    pub fn modify(&self, f: impl FnOnce(&mut T))
+    where
+        T: From<T::ArbitraryInt>,
+        T::ArbitraryInt: From<T>,
+    {
+        // SAFETY: guaranteed by `new`
+        let mut value = T::from(without_w1c::<T>(unsafe { read::<T>(self.ptr) }));
+        f(&mut value);
+        self.write(value);
+    }
+
+    /// Like [`Reg::modify`], but validates the value first. The register is only written if it is valid.
+    pub fn try_modify(&self, f: impl FnOnce(&mut T)) -> Result<(), T::Error>
+    where
+        T: TryFrom<T::ArbitraryInt>,
+        T::ArbitraryInt: From<T>,
+    {
+        // SAFETY: guaranteed by `new`
+        let mut value = T::try_from(without_w1c::<T>(unsafe { read::<T>(self.ptr) }))?;
+        f(&mut value);
+        self.write(value);
+        Ok(())
+    }
+}
+
+/// A register which can only be read, like a status register.
+pub struct RoReg<T: Bitsized<ArbitraryInt: Integer>> {
+    ptr: *mut RawOf<T>,
+    _bitfield: PhantomData<T>,
+}
+
+impl<T: Bitsized<ArbitraryInt: Integer>> RoReg<T> {
+    /// Creates a read-only register at `ptr`, which can also point to plain memory.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` needs to be valid for volatile reads, and aligned, for as long as this register is used.
+    pub const unsafe fn new(ptr: *mut RawOf<T>) -> Self {
+        RoReg { ptr, _bitfield: PhantomData }
+    }
+
+    /// Returns the register's address.
+    pub const fn as_ptr(&self) -> *mut RawOf<T> {
+        self.ptr
+    }
+
+    /// Reads the register once.
+    pub fn read(&self) -> T
+    where
+        T: From<T::ArbitraryInt>,
+    {
+        // SAFETY: guaranteed by `new`
+        T::from(unsafe { read::<T>(self.ptr) })
+    }
+
+    /// Reads the register once, validating its value.
+    pub fn try_read(&self) -> Result<T, T::Error>
+    where
+        T: TryFrom<T::ArbitraryInt>,
+    {
+        // SAFETY: guaranteed by `new`
+        T::try_from(unsafe { read::<T>(self.ptr) })
+    }
+}
+
+/// A register which can only be written, like a command register.
+pub struct WoReg<T: Bitsized<ArbitraryInt: Integer>> {
+    ptr: *mut RawOf<T>,
+    _bitfield: PhantomData<T>,
+}
+
+impl<T: Bitsized<ArbitraryInt: Integer>> WoReg<T> {
+    /// Creates a write-only register at `ptr`, which can also point to plain memory.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` needs to be valid for volatile writes, and aligned, for as long as this register is used.
+    pub const unsafe fn new(ptr: *mut RawOf<T>) -> Self {
+        WoReg { ptr, _bitfield: PhantomData }
+    }
+
+    /// Returns the register's address.
+    pub const fn as_ptr(&self) -> *mut RawOf<T> {
+        self.ptr
+    }
+
+    /// Writes the register once.
+    pub fn write(&self, value: T)
+    where
+        T::ArbitraryInt: From<T>,
+    {
+        // SAFETY: guaranteed by `new`
+        unsafe { write(self.ptr, value) }
+    }
+}
@@ -0,0 +1,64 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Status {
+    #[access(ro)]
+    ready: bool,
+    #[access(wo)]
+    command: u3,
+    #[access(w1c)]
+    errors: [bool; 4],
+    #[access(rc)]
+    count: u4,
+    #[access(rw)]
+    mode: u4,
+}
+
+#[test]
+fn access_policies() {
+    let mut status = Status::new(true, u3::new(0b101), [false, true, false, false], u4::new(9), u4::new(3));
+    assert!(status.ready());
+    assert_eq!(status.errors(), [false, true, false, false]);
+    assert_eq!(status.count(), u4::new(9));
+
+    // write-only fields can still be written and are shown by `DebugBits`
+    status.set_command(u3::new(0b011));
+    assert_eq!(
+        format!("{status:?}"),
+        "Status { ready: true, command: 3, errors: [false, true, false, false], count: 9, mode: 3 }"
+    );
+
+    // clearing writes ones, the hardware then clears them
+    status.clear_errors_at(0);
+    assert_eq!(status.errors(), [true, true, false, false]);
+    status.clear_errors();
+    assert_eq!(status, Status::from(0b0011_1001_1111_011_1));
+
+    status.set_mode(u4::new(0));
+    assert_eq!(status.mode(), u4::new(0));
+}
+
+#[bitsize(256)]
+#[derive(FromBits, DebugBits)]
+struct WideStatus {
+    #[access(w1c)]
+    pending: [u64; 3],
+    #[access(wo)]
+    doorbell: u64,
+}
+
+#[test]
+fn wide_access_policies() {
+    let mut status = WideStatus::from([0u8; 32]);
+    status.clear_pending_at(1);
+    assert_eq!(status.pending(), [0, u64::MAX, 0]);
+    status.clear_pending();
+    assert_eq!(status.pending(), [u64::MAX; 3]);
+
+    status.set_doorbell(42);
+    let bytes: [u8; 32] = status.into();
+    assert_eq!(bytes[24], 42);
+}
@@ -0,0 +1,51 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(24)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Control {
+    raw: u16,
+    #[alias(of = "raw")]
+    split: (u8, u8),
+    mode: Mode,
+    payload: u6,
+    // spans `mode` and `payload`, depending on the mode
+    #[alias(of = "mode")]
+    fast: FastPayload,
+}
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Mode {
+    Off,
+    Slow,
+    Fast,
+    Turbo,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct FastPayload {
+    mode: Mode,
+    divider: u4,
+    burst: u2,
+}
+
+#[test]
+fn aliases() {
+    let mut control = Control::new(0x1234, Mode::Fast, u6::new(0b10_0101));
+    assert_eq!(control.split(), (0x34, 0x12));
+    assert_eq!(control.fast(), FastPayload::new(Mode::Fast, u4::new(0b0101), u2::new(0b10)));
+
+    // writing a view writes the shared bits
+    control.set_split((0xcd, 0xab));
+    assert_eq!(control.raw(), 0xabcd);
+    control.set_fast(FastPayload::new(Mode::Fast, u4::new(0xf), u2::new(0)));
+    assert_eq!(control.payload(), u6::new(0b00_1111));
+    assert_eq!(control.mode(), Mode::Fast);
+
+    // views are not part of the struct itself
+    assert_eq!(control, Control::from(u24::new(0b001111_10_1010101111001101)));
+    assert_eq!(format!("{control:?}"), "Control { raw: 43981, mode: Fast, payload: 15 }");
+}
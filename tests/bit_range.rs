@@ -0,0 +1,62 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Register {
+    #[bits(0..=3)]
+    header: u4,
+    #[bit(7)]
+    enabled: bool,
+    #[bits(8..14)]
+    body: u6,
+}
+
+#[test]
+fn bit_ranges() {
+    let reg = Register::from(u16::new(0b00_101010_1_000_1100));
+    assert_eq!(reg.header(), u4::new(0b1100));
+    assert!(reg.enabled());
+    assert_eq!(reg.body(), u6::new(0b101010));
+
+    // gaps are filled with reserved fields, which are skipped in constructors
+    assert_eq!(reg, Register::new(u4::new(0b1100), true, u6::new(0b101010)));
+    assert_eq!(
+        format!("{reg:?}"),
+        "Register { header: 12, reserved_i: 0, enabled: true, body: 42, reserved_ii: 0 }"
+    );
+}
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, PartialEq, Clone, Copy)]
+enum Mode {
+    Off,
+    Slow,
+    Fast,
+    Turbo,
+}
+
+#[bitsize(256)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct WideRegister {
+    #[bits(0..=1)]
+    mode: Mode,
+    #[bits(200..=215)]
+    lanes: [u4; 4],
+}
+
+#[test]
+fn wide_bit_ranges() {
+    let mut reg = WideRegister::new(Mode::Fast, [u4::new(1), u4::new(2), u4::new(3), u4::new(4)]);
+    assert_eq!(reg.mode(), Mode::Fast);
+    assert_eq!(reg.lanes_at(3), u4::new(4));
+    reg.set_mode(Mode::Turbo);
+    assert_eq!(reg.mode(), Mode::Turbo);
+
+    // the 198 bit gap needs two reserved fields
+    assert_eq!(reg.reserved_i(), u128::new(0));
+    assert_eq!(reg.reserved_ii(), u70::new(0));
+    let bytes: [u8; 32] = reg.into();
+    assert_eq!(bytes[25], 0x21);
+}
@@ -0,0 +1,179 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::{prelude::*, BitsError, BitsErrorKind, PathSegment::*};
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Off,
+    Slow,
+    Fast,
+}
+
+#[bitsize(6)]
+#[derive(TryFromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Channel {
+    enabled: bool,
+    modes: [Mode; 2],
+    reserved: u1,
+}
+
+#[bitsize(32)]
+#[derive(TryFromBits, DebugBits, PartialEq)]
+struct Status {
+    ready: bool,
+    mode: Mode,
+    channels: [Channel; 3],
+    pair: (u2, Mode),
+    padding: u7,
+}
+
+#[test]
+fn enum_value() {
+    let error = Mode::try_from(u2::new(3)).unwrap_err();
+    assert_eq!((error.path(), error.offset(), error.value()), (&[][..], 0, 3));
+    assert_eq!(error.to_string(), "invalid value 0x3");
+}
+
+#[test]
+fn field_path_offset_and_value() {
+    let error = Status::try_from(0b0000000_00_00_000000_000000_000000_11_0).unwrap_err();
+    assert_eq!((error.path(), error.offset(), error.value()), (&[Field("mode")][..], 1, 3));
+
+    // the second mode of the third channel
+    let error = Status::try_from(0b0000000_00_00_0_11_00_0_000000_000000_00_0).unwrap_err();
+    assert_eq!(error.path(), &[Field("channels"), Index(2), Field("modes"), Index(1)]);
+    assert_eq!((error.offset(), error.value()), (3 + 2 * 6 + 3, 3));
+    assert_eq!(error.to_string(), "invalid value 0x3 in `channels[2].modes[1]` at bit 18");
+
+    let error = Status::try_from(0b0000000_11_00_000000_000000_000000_00_0).unwrap_err();
+    assert_eq!(error.path(), &[Field("pair"), Field("1")]);
+    assert_eq!(error.offset(), 23);
+
+    // the first invalid field is reported
+    let error = Status::try_from(0b0000000_11_00_000000_000000_000000_11_0).unwrap_err();
+    assert_eq!(error.path(), &[Field("mode")]);
+
+    assert!(Status::try_from(0b1111111_10_11_010101_000000_101000_10_1).is_ok());
+}
+
+#[bitsize(8, msb_first)]
+#[derive(TryFromBits, DebugBits)]
+struct Header(Mode, u6);
+
+#[bitsize(4)]
+#[derive(TryFromBits, DebugBits)]
+struct Deep {
+    inner: Inner,
+}
+
+#[bitsize(4)]
+#[derive(TryFromBits, DebugBits)]
+struct Inner {
+    lanes: [[Mode; 1]; 2],
+}
+
+#[bitsize(8)]
+#[derive(TryFromBits, DebugBits)]
+struct Deeper {
+    reserved: u4,
+    deep: Deep,
+}
+
+#[test]
+fn layouts() {
+    // msb_first puts the first field at the top
+    let error = Header::try_from(0b11_000000).unwrap_err();
+    assert_eq!((error.path(), error.offset()), (&[Field("val_0")][..], 6));
+
+    let error = Deeper::try_from(0b11_00_0000).unwrap_err();
+    assert_eq!(error.path(), &[Field("deep"), Field("inner"), Field("lanes"), Index(1)]);
+    assert!(error.is_path_truncated());
+    assert_eq!((error.offset(), error.value()), (6, 3));
+    assert_eq!(error.to_string(), "invalid value 0x3 in `deep.inner.lanes[1]..` at bit 6");
+}
+
+/// A field type with its own error, which isn't a `BitsError`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Even(u8);
+
+impl Bitsized for Even {
+    type ArbitraryInt = u4;
+    const BITS: usize = 4;
+    const MAX: u4 = u4::new(15);
+}
+
+impl TryFrom<u4> for Even {
+    type Error = &'static str;
+
+    fn try_from(value: u4) -> Result<Self, Self::Error> {
+        match value.value() {
+            value if value % 2 == 0 => Ok(Even(value)),
+            _ => Err("odd"),
+        }
+    }
+}
+
+impl From<Even> for u4 {
+    fn from(even: Even) -> u4 {
+        u4::new(even.0)
+    }
+}
+
+#[bitsize(8)]
+#[derive(TryFromBits, DebugBits)]
+struct Custom {
+    low: u4,
+    even: Even,
+}
+
+#[test]
+fn other_errors() {
+    let error = Custom::try_from(0b0111_0000).unwrap_err();
+    assert_eq!(error.kind(), BitsErrorKind::InvalidValue);
+    assert_eq!((error.path(), error.offset(), error.value()), (&[Field("even")][..], 4, 7));
+    // `#[reset]` can't be checked against a `TryFrom` implemented by hand
+    assert!(!<Custom as Bitsized>::VALIDITY.is_known());
+
+    let error = Status::try_from_le_slice(&[0u8; 3]).unwrap_err();
+    assert_eq!(error.kind(), BitsErrorKind::InvalidLength { expected: 4, found: 3 });
+    assert_eq!(error.to_string(), "expected 4 bytes, found 3");
+    assert_eq!(format!("{error:?}"), "BitsError { expected: 4, found: 3 }");
+}
+
+#[bitsize(28)]
+#[derive(TryFromBits, DebugBits)]
+struct Config {
+    speed: u6,
+    mode: Mode,
+    divider: u20,
+}
+
+#[bitsize(32, tag_bits = 4)]
+#[derive(TryFromBits, Debug)]
+enum Command {
+    #[tag = 0]
+    Stop,
+    #[tag = 1]
+    Configure(Config),
+}
+
+#[test]
+fn tagged_payloads() {
+    let error = Command::try_from(0b00000000000000000000_11_000000_0001).unwrap_err();
+    assert_eq!(error.path(), &[Variant("Configure"), Field("mode")]);
+    assert_eq!(error.to_string(), "invalid value 0x3 in `Configure.mode` at bit 10");
+    assert_eq!((error.offset(), error.value()), (4 + 6, 3));
+
+    let error = Command::try_from(0b0010).unwrap_err();
+    assert_eq!((error.path(), error.value()), (&[][..], 0b0010));
+}
+
+#[test]
+fn is_an_error() {
+    fn source(error: &dyn core::error::Error) -> String {
+        error.to_string()
+    }
+    let error: BitsError = Mode::try_from(u2::new(3)).unwrap_err();
+    assert_eq!(source(&error), "invalid value 0x3");
+}
@@ -0,0 +1,92 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, PartialEq, Default)]
+enum Mode {
+    #[default]
+    Off,
+    Slow,
+    Fast,
+    Turbo,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, DefaultBits, PartialEq, Clone, Copy)]
+struct Ctrl {
+    enable: bool,
+    mode: Mode,
+    reserved: u1,
+    div: u4,
+    #[access(ro)]
+    status: u8,
+}
+
+#[test]
+fn with() {
+    let ctrl = Ctrl::default().with_enable(true).with_div(u4::new(3)).with_mode(Mode::Fast);
+    assert_eq!(ctrl, Ctrl::new(true, Mode::Fast, u4::new(3), 0));
+    assert_eq!(u16::from(ctrl), 0b00000000_0011_0_10_1);
+
+    // the original is left untouched
+    let original = Ctrl::from(0xff00);
+    let changed = original.with_enable(true);
+    assert_eq!(u16::from(original), 0xff00);
+    assert_eq!(u16::from(changed), 0xff01);
+}
+
+#[test]
+fn builder() {
+    let ctrl = Ctrl::builder().div(u4::new(9)).mode(Mode::Turbo).status(0xab).enable(true).build();
+    assert_eq!(ctrl, Ctrl::new(true, Mode::Turbo, u4::new(9), 0xab));
+
+    // setting a field twice keeps the last value
+    let ctrl = Ctrl::builder()
+        .enable(true)
+        .enable(false)
+        .mode(Mode::Off)
+        .div(u4::new(15))
+        .status(1)
+        .build();
+    assert_eq!(u16::from(ctrl), 0b00000001_1111_0_00_0);
+}
+
+#[bitsize(12)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Lanes(u4, [u2; 4]);
+
+#[bitsize(5)]
+#[derive(TryFromBits, DebugBits, PartialEq)]
+struct Entry<T> {
+    flag: bool,
+    payload: T,
+}
+
+#[test]
+fn tuple_and_generic_builders() {
+    let lanes = Lanes::builder()
+        .val_1([u2::new(1), u2::new(2), u2::new(3), u2::new(0)])
+        .val_0(u4::new(7))
+        .build();
+    assert_eq!(lanes, Lanes::from(u12::new(0b00_11_10_01_0111)));
+    assert_eq!(lanes.with_val_0(u4::new(1)).val_0(), u4::new(1));
+
+    let entry = Entry::builder().payload(u4::new(5)).flag(true).build();
+    assert_eq!(entry, Entry::new(true, u4::new(5)));
+    assert_eq!(entry.with_payload(u4::new(2)).payload(), u4::new(2));
+}
+
+#[bitsize(136)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Wide {
+    head: u8,
+    body: u128,
+}
+
+#[test]
+fn wide_builder() {
+    let wide = Wide::builder().body(u128::MAX).head(3).build();
+    assert_eq!(wide, Wide::new(3, u128::MAX));
+    assert_eq!(wide.with_head(4).head(), 4);
+}
@@ -0,0 +1,81 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+use bilge::prelude::*;
+
+#[bitsize(12)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Small {
+    low: u4,
+    high: u8,
+}
+
+#[bitsize(16, msb_first)]
+#[derive(TryFromBits, DebugBits, PartialEq)]
+struct Header {
+    kind: Kind,
+    length: u14,
+}
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, PartialEq)]
+enum Kind {
+    Data,
+    Ack,
+    Nack,
+}
+
+#[bitsize(256)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Wide {
+    first: u64,
+    rest: [u64; 3],
+}
+
+#[test]
+fn bytes() {
+    // bitfields get rounded up to whole bytes, the bits above are ignored
+    let small = Small::from_le_bytes([0x21, 0xf3]);
+    assert_eq!(small, Small::new(u4::new(1), 0x32));
+    assert_eq!(Small::from_be_bytes([0xf3, 0x21]), small);
+    assert_eq!(small.to_le_bytes(), [0x21, 0x03]);
+    assert_eq!(Small::new(u4::new(1), 0x32).to_be_bytes(), [0x03, 0x21]);
+
+    // the endianness of the bytes is independent of the field order
+    let header = Header::from_be_bytes([0b01_000000, 0x20]).unwrap();
+    assert_eq!(header, Header::new(Kind::Ack, u14::new(0x20)));
+    assert_eq!(Header::from_le_bytes([0x20, 0b01_000000]), Ok(Header::new(Kind::Ack, u14::new(0x20))));
+    assert!(Header::from_be_bytes([0b11_000000, 0]).is_err());
+    assert_eq!(Header::new(Kind::Nack, u14::new(1)).to_be_bytes(), [0b10_000000, 1]);
+
+    // slices need to have exactly as many bytes, in the order the caller chooses
+    assert_eq!(
+        Header::try_from_le_slice(&[0x20, 0b01_000000]),
+        Ok(Header::new(Kind::Ack, u14::new(0x20)))
+    );
+    assert_eq!(
+        Header::try_from_be_slice(&[0b01_000000, 0x20]),
+        Ok(Header::new(Kind::Ack, u14::new(0x20)))
+    );
+    assert!(Header::try_from_le_slice(&[0x20]).is_err());
+    assert_eq!(Small::try_from_be_slice(&[0xf3, 0x21]), Ok(Small::new(u4::new(1), 0x32)));
+    assert!(Small::try_from_le_slice(&[0x21, 0xf3, 0]).is_err());
+    assert_eq!(Kind::from_le_bytes([0b1111_1110]), Ok(Kind::Nack));
+}
+
+#[test]
+fn wide_bytes() {
+    let wide = Wide::new(1, [2, 3, 4]);
+    let le = Wide::new(1, [2, 3, 4]).to_le_bytes();
+    assert_eq!(le[0], 1);
+    assert_eq!(le[24], 4);
+    let be = Wide::new(1, [2, 3, 4]).to_be_bytes();
+    assert_eq!(be[31], 1);
+    assert_eq!(be[7], 4);
+    assert_eq!(Wide::from_le_bytes(le), wide);
+    assert_eq!(Wide::from_be_bytes(be), wide);
+    assert_eq!(Wide::try_from_le_slice(&le), Ok(Wide::new(1, [2, 3, 4])));
+    assert_eq!(Wide::try_from_be_slice(&be), Ok(Wide::new(1, [2, 3, 4])));
+    assert!(Wide::try_from_le_slice(&le[1..]).is_err());
+
+    const SLICE: Result<Small, bilge::BitsError> = Small::try_from_le_slice(&[0x21, 0x03]);
+    assert_eq!(SLICE, Ok(Small::new(u4::new(1), 0x32)));
+}
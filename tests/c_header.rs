@@ -0,0 +1,193 @@
+#![allow(clippy::unusual_byte_groupings)]
+//! Checks the exported C headers against the golden files in `c_header/` and the Rust accessors.
+
+use bilge::export::{c_header, CHeader, CItem};
+use bilge::prelude::*;
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Mode {
+    Off,
+    Slow,
+    Fast,
+    RxOnly,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits)]
+struct UartControl {
+    enable: bool,
+    mode: Mode,
+    reserved: u1,
+    divider: u5,
+    #[access(w1c)]
+    overrun: bool,
+    #[access(ro)]
+    lanes: [u2; 2],
+    #[access(wo)]
+    flush: bool,
+    padding: u1,
+}
+
+#[bitsize(40)]
+#[derive(FromBits)]
+struct Timestamp {
+    ticks: u39,
+    valid: bool,
+}
+
+#[bitsize(96)]
+#[derive(FromBits)]
+struct Huge(u96);
+
+#[bitsize(2)]
+#[derive(FromBits, Debug)]
+enum Width {
+    Narrow,
+    Wide,
+    Bits,
+    Other,
+}
+
+/// Compares `header` with `c_header/{name}`, or overwrites it if `BILGE_BLESS` is set.
+fn check_golden(name: &str, header: &str) {
+    let path = format!("{}/tests/c_header/{name}", env!("CARGO_MANIFEST_DIR"));
+    if std::env::var_os("BILGE_BLESS").is_some() {
+        std::fs::write(&path, header).unwrap();
+    }
+    let golden = std::fs::read_to_string(&path).unwrap();
+    assert!(
+        golden == header,
+        "{name} is outdated, regenerate it with `BILGE_BLESS=1 cargo test --test c_header`:\n{header}"
+    );
+}
+
+/// Reads `#define NAME 0x12u` from `header`.
+fn define(header: &str, name: &str) -> u64 {
+    let prefix = format!("#define {name} ");
+    let value = header
+        .lines()
+        .find_map(|line| line.strip_prefix(&prefix))
+        .unwrap_or_else(|| panic!("{name} isn't defined"));
+    let value = value.trim_end_matches(['u', 'l']);
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).unwrap(),
+        None => value.parse().unwrap(),
+    }
+}
+
+#[test]
+fn golden_files() {
+    let items = [CItem::of::<Mode>(), CItem::of::<UartControl>(), CItem::of::<Timestamp>()];
+    check_golden("uart.h", &CHeader::new("UART_H", &items).to_string());
+    check_golden("huge.h", &c_header::<Huge>().to_string());
+}
+
+/// Compiles the golden `uart.h` with the C compiler in `CC`, or `cc`, skipping this if there is none.
+#[test]
+fn golden_header_compiles() {
+    let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let path = format!("{}/tests/c_header/uart.h", env!("CARGO_MANIFEST_DIR"));
+    let output = std::process::Command::new(&compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Wpedantic", "-Werror", "-fsyntax-only", "-x", "c", &path])
+        .output();
+    match output {
+        Ok(output) => assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => eprintln!("skipped, `{compiler}` isn't installed"),
+        Err(error) => panic!("`{compiler}` failed to run: {error}"),
+    }
+}
+
+#[test]
+fn colliding_names_are_an_error() {
+    let header = c_header::<Width>().to_string();
+    assert!(header.contains("#error \"Width: its size and `Bits` have the same name in C\""));
+    assert!(!header.contains("#define WIDTH_BITS"));
+}
+
+#[test]
+fn defines_match_the_field_constants() {
+    let header = c_header::<UartControl>().to_string();
+    assert!(header.starts_with("/* Generated by bilge, don't edit this by hand. */\n#ifndef UART_CONTROL_H\n"));
+
+    assert_eq!(define(&header, "UART_CONTROL_BITS"), 16);
+    let fields = [
+        ("ENABLE", UartControl::ENABLE_OFFSET, UartControl::ENABLE_BITS, UartControl::ENABLE_MASK),
+        ("MODE", UartControl::MODE_OFFSET, UartControl::MODE_BITS, UartControl::MODE_MASK),
+        (
+            "DIVIDER",
+            UartControl::DIVIDER_OFFSET,
+            UartControl::DIVIDER_BITS,
+            UartControl::DIVIDER_MASK,
+        ),
+        (
+            "OVERRUN",
+            UartControl::OVERRUN_OFFSET,
+            UartControl::OVERRUN_BITS,
+            UartControl::OVERRUN_MASK,
+        ),
+        ("LANES", UartControl::LANES_OFFSET, UartControl::LANES_BITS, UartControl::LANES_MASK),
+        ("FLUSH", UartControl::FLUSH_OFFSET, UartControl::FLUSH_BITS, UartControl::FLUSH_MASK),
+    ];
+    for (name, offset, bits, mask) in fields {
+        assert_eq!(define(&header, &format!("UART_CONTROL_{name}_OFFSET")), offset as u64, "{name}");
+        assert_eq!(define(&header, &format!("UART_CONTROL_{name}_BITS")), bits as u64, "{name}");
+        assert_eq!(define(&header, &format!("UART_CONTROL_{name}_MASK")), mask as u64, "{name}");
+    }
+    // reserved fields have no accessors in Rust either
+    assert!(!header.contains("RESERVED") && !header.contains("PADDING"));
+}
+
+#[test]
+fn enum_values_match_the_variants() {
+    let header = c_header::<Mode>().to_string();
+    for mode in [Mode::Off, Mode::Slow, Mode::Fast, Mode::RxOnly] {
+        let name = match mode {
+            Mode::Off => "MODE_OFF",
+            Mode::Slow => "MODE_SLOW",
+            Mode::Fast => "MODE_FAST",
+            Mode::RxOnly => "MODE_RX_ONLY",
+        };
+        assert_eq!(define(&header, name), u2::from(mode).value() as u64);
+    }
+}
+
+/// Runs the C getters and setters by hand, using the defines they are made of.
+#[test]
+fn accessors_agree_with_rust() {
+    let header = c_header::<UartControl>().to_string();
+    let get = |value: u16, name: &str| {
+        let (mask, offset) = (
+            define(&header, &format!("UART_CONTROL_{name}_MASK")),
+            define(&header, &format!("UART_CONTROL_{name}_OFFSET")),
+        );
+        (value as u64 & mask) >> offset
+    };
+    let set = |value: u16, name: &str, field: u64| {
+        let (mask, offset) = (
+            define(&header, &format!("UART_CONTROL_{name}_MASK")),
+            define(&header, &format!("UART_CONTROL_{name}_OFFSET")),
+        );
+        ((value as u64 & !mask) | ((field << offset) & mask)) as u16
+    };
+    assert!(header.contains("static inline bool uart_control_get_enable(uint16_t value) {\n    return (value & UART_CONTROL_ENABLE_MASK) != 0u;\n}"));
+    assert!(header.contains("static inline uint16_t uart_control_clear_overrun(uint16_t value) {"));
+    assert!(!header.contains("uart_control_set_overrun") && !header.contains("uart_control_set_lanes"));
+    assert!(!header.contains("uart_control_get_flush"));
+
+    for value in [0x0000, 0xffff, 0b1_1_10_01_1_10110_1_10_1, 0b0_0_01_10_0_01001_0_01_0] {
+        let control = UartControl::from(value);
+        assert_eq!(get(value, "ENABLE") != 0, control.enable());
+        assert_eq!(get(value, "MODE"), u2::from(control.mode()).value() as u64);
+        assert_eq!(get(value, "DIVIDER"), control.divider().value() as u64);
+        assert_eq!(get(value, "OVERRUN") != 0, control.overrun());
+        let lanes = control.lanes();
+        assert_eq!(get(value, "LANES"), (lanes[0].value() | lanes[1].value() << 2) as u64);
+
+        let mut control = UartControl::from(value);
+        control.set_mode(Mode::Fast);
+        control.set_divider(u5::new(0b10101));
+        let value = set(set(value, "MODE", 2), "DIVIDER", 0b10101);
+        assert_eq!(value, u16::from(control));
+    }
+}
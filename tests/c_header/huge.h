@@ -0,0 +1,11 @@
+/* Generated by bilge, don't edit this by hand. */
+#ifndef HUGE_H
+#define HUGE_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Huge: 96 bits */
+#error "Huge has 96 bits, which don't fit into a C integer"
+
+#endif /* HUGE_H */
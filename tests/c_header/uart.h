@@ -0,0 +1,101 @@
+/* Generated by bilge, don't edit this by hand. */
+#ifndef UART_H
+#define UART_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Mode: 2 bits */
+#define MODE_BITS 2u
+#define MODE_OFF 0x00u
+#define MODE_SLOW 0x01u
+#define MODE_FAST 0x02u
+#define MODE_RX_ONLY 0x03u
+
+/* UartControl: 16 bits */
+#define UART_CONTROL_BITS 16u
+#define UART_CONTROL_ENABLE_OFFSET 0u
+#define UART_CONTROL_ENABLE_BITS 1u
+#define UART_CONTROL_ENABLE_MASK 0x0001u
+#define UART_CONTROL_MODE_OFFSET 1u
+#define UART_CONTROL_MODE_BITS 2u
+#define UART_CONTROL_MODE_MASK 0x0006u
+#define UART_CONTROL_DIVIDER_OFFSET 4u
+#define UART_CONTROL_DIVIDER_BITS 5u
+#define UART_CONTROL_DIVIDER_MASK 0x01F0u
+#define UART_CONTROL_OVERRUN_OFFSET 9u
+#define UART_CONTROL_OVERRUN_BITS 1u
+#define UART_CONTROL_OVERRUN_MASK 0x0200u
+#define UART_CONTROL_LANES_OFFSET 10u
+#define UART_CONTROL_LANES_BITS 4u
+#define UART_CONTROL_LANES_MASK 0x3C00u
+#define UART_CONTROL_FLUSH_OFFSET 14u
+#define UART_CONTROL_FLUSH_BITS 1u
+#define UART_CONTROL_FLUSH_MASK 0x4000u
+
+static inline bool uart_control_get_enable(uint16_t value) {
+    return (value & UART_CONTROL_ENABLE_MASK) != 0u;
+}
+
+static inline uint16_t uart_control_set_enable(uint16_t value, bool field) {
+    return (uint16_t)((value & ~UART_CONTROL_ENABLE_MASK) | (((uint16_t)field << UART_CONTROL_ENABLE_OFFSET) & UART_CONTROL_ENABLE_MASK));
+}
+
+static inline uint8_t uart_control_get_mode(uint16_t value) {
+    return (uint8_t)((value & UART_CONTROL_MODE_MASK) >> UART_CONTROL_MODE_OFFSET);
+}
+
+static inline uint16_t uart_control_set_mode(uint16_t value, uint8_t field) {
+    return (uint16_t)((value & ~UART_CONTROL_MODE_MASK) | (((uint16_t)field << UART_CONTROL_MODE_OFFSET) & UART_CONTROL_MODE_MASK));
+}
+
+static inline uint8_t uart_control_get_divider(uint16_t value) {
+    return (uint8_t)((value & UART_CONTROL_DIVIDER_MASK) >> UART_CONTROL_DIVIDER_OFFSET);
+}
+
+static inline uint16_t uart_control_set_divider(uint16_t value, uint8_t field) {
+    return (uint16_t)((value & ~UART_CONTROL_DIVIDER_MASK) | (((uint16_t)field << UART_CONTROL_DIVIDER_OFFSET) & UART_CONTROL_DIVIDER_MASK));
+}
+
+static inline bool uart_control_get_overrun(uint16_t value) {
+    return (value & UART_CONTROL_OVERRUN_MASK) != 0u;
+}
+
+static inline uint16_t uart_control_clear_overrun(uint16_t value) {
+    return (uint16_t)(value | UART_CONTROL_OVERRUN_MASK);
+}
+
+static inline uint8_t uart_control_get_lanes(uint16_t value) {
+    return (uint8_t)((value & UART_CONTROL_LANES_MASK) >> UART_CONTROL_LANES_OFFSET);
+}
+
+static inline uint16_t uart_control_set_flush(uint16_t value, bool field) {
+    return (uint16_t)((value & ~UART_CONTROL_FLUSH_MASK) | (((uint16_t)field << UART_CONTROL_FLUSH_OFFSET) & UART_CONTROL_FLUSH_MASK));
+}
+
+/* Timestamp: 40 bits */
+#define TIMESTAMP_BITS 40u
+#define TIMESTAMP_TICKS_OFFSET 0u
+#define TIMESTAMP_TICKS_BITS 39u
+#define TIMESTAMP_TICKS_MASK 0x0000007FFFFFFFFFull
+#define TIMESTAMP_VALID_OFFSET 39u
+#define TIMESTAMP_VALID_BITS 1u
+#define TIMESTAMP_VALID_MASK 0x0000008000000000ull
+
+static inline uint64_t timestamp_get_ticks(uint64_t value) {
+    return (uint64_t)((value & TIMESTAMP_TICKS_MASK) >> TIMESTAMP_TICKS_OFFSET);
+}
+
+static inline uint64_t timestamp_set_ticks(uint64_t value, uint64_t field) {
+    return (uint64_t)((value & ~TIMESTAMP_TICKS_MASK) | (((uint64_t)field << TIMESTAMP_TICKS_OFFSET) & TIMESTAMP_TICKS_MASK));
+}
+
+static inline bool timestamp_get_valid(uint64_t value) {
+    return (value & TIMESTAMP_VALID_MASK) != 0u;
+}
+
+static inline uint64_t timestamp_set_valid(uint64_t value, bool field) {
+    return (uint64_t)((value & ~TIMESTAMP_VALID_MASK) | (((uint64_t)field << TIMESTAMP_VALID_OFFSET) & TIMESTAMP_VALID_MASK));
+}
+
+#endif /* UART_H */
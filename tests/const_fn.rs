@@ -0,0 +1,177 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Input,
+    Output,
+    Alternate,
+    Analog,
+}
+
+#[bitsize(3)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq)]
+enum Speed {
+    Low = 1,
+    Medium = 2,
+    High = 4,
+}
+
+#[bitsize(6)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Pull {
+    up: bool,
+    strength: u5,
+}
+
+#[bitsize(32, const_fields)]
+#[derive(TryFromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Pin {
+    mode: Mode,
+    speed: Speed,
+    pull: Pull,
+    alternate: [u4; 2],
+    lock: (bool, i3),
+    reserved: u9,
+}
+
+const PIN: Pin = Pin::new(
+    Mode::Alternate,
+    Speed::High,
+    Pull::new(true, u5::new(3)),
+    [u4::new(7), u4::new(12)],
+    (true, i3::new(-2)),
+);
+
+#[test]
+fn constructor_and_getters() {
+    const MODE: Mode = PIN.mode();
+    const SPEED: Speed = PIN.speed();
+    const STRENGTH: u5 = PIN.pull().strength();
+    const ALTERNATE: u4 = PIN.alternate_at(1);
+    const LOCK: (bool, i3) = PIN.lock();
+
+    assert_eq!(MODE, Mode::Alternate);
+    assert_eq!(SPEED, Speed::High);
+    assert_eq!(STRENGTH, u5::new(3));
+    assert_eq!(ALTERNATE, u4::new(12));
+    assert_eq!(LOCK, (true, i3::new(-2)));
+    assert_eq!(PIN.to_raw(), 0b1101_1100_0111_000111_100_10);
+    assert_eq!(Pin::try_from(PIN.to_raw()), Ok(PIN));
+}
+
+#[test]
+fn setters() {
+    const PIN2: Pin = {
+        let mut pin = PIN.with_mode(Mode::Input).with_lock((false, i3::new(3)));
+        pin.set_speed(Speed::Low);
+        pin.set_alternate_at(0, u4::new(1));
+        pin
+    };
+
+    assert_eq!(PIN2.mode(), Mode::Input);
+    assert_eq!(PIN2.speed(), Speed::Low);
+    assert_eq!(PIN2.pull(), PIN.pull());
+    assert_eq!(PIN2.alternate(), [u4::new(1), u4::new(12)]);
+    assert_eq!(PIN2.lock(), (false, i3::new(3)));
+}
+
+#[test]
+fn builder() {
+    const BUILT: Pin = Pin::builder()
+        .mode(Mode::Alternate)
+        .speed(Speed::High)
+        .pull(Pull::new(true, u5::new(3)))
+        .alternate([u4::new(7), u4::new(12)])
+        .lock((true, i3::new(-2)))
+        .build();
+
+    assert_eq!(BUILT, PIN);
+}
+
+#[test]
+fn raw_conversions() {
+    const PULL: Pull = Pull::from_raw(u6::new(0b00101_1));
+    const RAW: u6 = PULL.to_raw();
+    // `TryFromBits` has no `from_raw`, since validating isn't const
+    const UNCHECKED: Pin = unsafe { Pin::from_raw_unchecked(PIN.to_raw()) };
+    const BYTES: [u8; 1] = PULL.to_le_bytes();
+    const FROM_BYTES: Pull = Pull::from_be_bytes([0b11_00101_1]);
+
+    assert_eq!(PULL.strength(), u5::new(5));
+    assert_eq!(RAW, u6::new(0b00101_1));
+    assert_eq!(UNCHECKED, PIN);
+    assert_eq!(BYTES, [0b00101_1]);
+    // bits above the bitsize are ignored
+    assert_eq!(FROM_BYTES, PULL);
+}
+
+#[bitsize(8, tag_bits = 2, const_fields)]
+#[derive(FromBits, Debug, Clone, Copy, PartialEq)]
+enum Command {
+    #[tag = 0]
+    Reset,
+    #[tag = 1]
+    Select(u6),
+    #[tag = 2]
+    Pull(Pull),
+    #[fallback]
+    Unknown(u8),
+}
+
+#[bitsize(4)]
+#[derive(FromBits, Debug, Clone, Copy, PartialEq)]
+enum Level {
+    Off,
+    On,
+    #[fallback]
+    Dimmed(u4),
+}
+
+#[bitsize(12, msb_first, const_fields)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Frame {
+    command: Command,
+    level: Level,
+}
+
+#[test]
+fn tagged_and_fallback() {
+    const FRAME: Frame = Frame::new(Command::Pull(Pull::new(false, u5::new(9))), Level::Dimmed(u4::new(7)));
+    const COMMAND: Command = FRAME.command();
+    const LEVEL: Level = FRAME.level();
+    const UNKNOWN: Command = Frame::from_raw(u12::new(0b0000_0011_0111)).command();
+
+    assert_eq!(COMMAND, Command::Pull(Pull::new(false, u5::new(9))));
+    assert_eq!(LEVEL, Level::Dimmed(u4::new(7)));
+    assert_eq!(UNKNOWN, Command::Unknown(0b0000_0011));
+    assert_eq!(FRAME.to_raw(), u12::new(0b01001_0_10_0111));
+    assert_eq!(Frame::from(FRAME.to_raw()), FRAME);
+}
+
+#[bitsize(136, const_fields)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Wide {
+    mode: Mode,
+    payload: u128,
+    level: Level,
+    flag: bool,
+    rest: u1,
+}
+
+#[test]
+fn wide() {
+    const WIDE: Wide = Wide::new(Mode::Analog, u128::MAX - 1, Level::On, true, u1::new(0)).with_rest(u1::new(1));
+    const PAYLOAD: u128 = WIDE.payload();
+    const LEVEL: Level = WIDE.level();
+    const BYTES: [u8; 17] = WIDE.to_le_bytes();
+
+    assert_eq!(WIDE.mode(), Mode::Analog);
+    assert_eq!(PAYLOAD, u128::MAX - 1);
+    assert_eq!(LEVEL, Level::On);
+    assert!(WIDE.flag());
+    assert_eq!(WIDE.rest(), u1::new(1));
+    assert_eq!(Wide::from_le_bytes(BYTES), WIDE);
+}
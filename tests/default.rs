@@ -0,0 +1,210 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq, Default)]
+enum Parity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, DefaultBits, PartialEq, Clone, Copy)]
+struct Timer {
+    #[default = true]
+    enable: bool,
+    #[default = u3::new(5)]
+    prescaler: u3,
+    oneshot: bool,
+    #[default = [u2::new(1), u2::new(2)]]
+    channels: [u2; 2],
+    #[default = (u3::new(7), u4::new(0))]
+    limits: (u3, u4),
+}
+
+// usable in const contexts, since every field is an integer or bool
+const TIMER: Timer = Timer::DEFAULT;
+
+#[test]
+fn field_defaults() {
+    assert_eq!(u16::from(TIMER), 0b0000_111_10_01_0_101_1);
+    assert_eq!(Timer::default(), TIMER);
+}
+
+#[bitsize(8, const_fields)]
+#[derive(TryFromBits, DebugBits, DefaultBits, PartialEq)]
+struct Uart {
+    #[default = Parity::Odd]
+    parity: Parity,
+    #[default = u6::new(9)]
+    baud: u6,
+}
+
+// usable in const contexts, since every field which isn't an integer has a default, which `const_fields` converts
+const UART: Uart = Uart::DEFAULT;
+
+#[test]
+fn field_defaults_with_enums() {
+    assert_eq!(Uart::default(), Uart::new(Parity::Odd, u6::new(9)));
+    assert_eq!(UART, Uart::new(Parity::Odd, u6::new(9)));
+}
+
+#[bitsize(8)]
+#[derive(TryFromBits, DebugBits, DefaultBits, PartialEq)]
+#[reset = 0b000101_01]
+struct UartReset {
+    parity: Parity,
+    baud: u6,
+}
+
+#[bitsize(8)]
+#[derive(TryFromBits, DebugBits, DefaultBits)]
+// the reset value needs to hold the required value of `reserved` as well
+#[reset = 0b1_0000_11_1]
+struct Status {
+    ready: bool,
+    #[reserved(ones)]
+    reserved: u2,
+    code: u4,
+    busy: bool,
+}
+
+const RESET: UartReset = UartReset::DEFAULT;
+
+#[test]
+fn reset() {
+    assert_eq!(RESET, UartReset::new(Parity::Even, u6::new(5)));
+    assert_eq!(UartReset::default(), RESET);
+
+    let status = Status::default();
+    assert!(status.ready() && status.busy());
+    assert_eq!(status.code(), u4::new(0));
+    assert_eq!(u8::from(Status::DEFAULT), 0b1_0000_11_1);
+}
+
+#[bitsize(8, msb_first)]
+#[derive(FromBits, DebugBits, DefaultBits)]
+struct Header {
+    #[default = u3::new(0b101)]
+    version: u3,
+    length: u5,
+}
+
+#[test]
+fn msb_first() {
+    assert_eq!(u8::from(Header::DEFAULT), 0b101_00000);
+}
+
+#[bitsize(136)]
+#[derive(FromBits, DebugBits, DefaultBits)]
+struct Wide {
+    #[default = 0x1234]
+    low: u128,
+    #[default = [true, false, true, false, true, false, true, true]]
+    flags: [bool; 8],
+}
+
+const WIDE: Wide = Wide::DEFAULT;
+
+#[test]
+fn wide() {
+    assert_eq!(WIDE.low(), 0x1234);
+    assert_eq!(WIDE.flags(), [true, false, true, false, true, false, true, true]);
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DebugBits, DefaultBits)]
+struct Entry<T: bilge::BitsizedField + Default> {
+    #[default = true]
+    present: bool,
+    payload: T,
+    #[default = u3::new(3)]
+    level: u3,
+}
+
+#[test]
+fn generic() {
+    let entry = Entry::<u4>::default();
+    assert!(entry.present());
+    assert_eq!(entry.payload(), u4::new(0));
+    assert_eq!(entry.level(), u3::new(3));
+}
+
+#[bitsize(6)]
+#[derive(TryFromBits, DebugBits, Clone, Copy)]
+struct Frame {
+    parities: [Parity; 2],
+    #[reserved(value = 0b10)]
+    reserved: u2,
+}
+
+#[bitsize(8, tag_bits = 2, msb_first)]
+#[derive(TryFromBits, Debug, Clone, Copy)]
+enum Command {
+    #[tag = 0b00]
+    Idle,
+    #[tag = 0b10]
+    Send(Frame),
+}
+
+#[bitsize(14)]
+#[derive(TryFromBits, DebugBits, DefaultBits)]
+#[reset = 0b10_10_10_00_01_1_0_10]
+struct Link {
+    modes: (Parity, bool),
+    enable: bool,
+    command: Command,
+    high: u2,
+}
+
+#[test]
+fn reset_is_checked_like_try_from() {
+    let link = Link::DEFAULT;
+    assert_eq!((link.modes(), link.enable(), link.high()), ((Parity::Odd, false), true, u2::new(0b10)));
+    // `#[reset]` is checked with the same rules as `TryFrom`
+    for value in 0..1 << 14 {
+        let accepted = <Link as Bitsized>::VALIDITY.accepts(value as u128);
+        assert_eq!(accepted, Link::try_from(u14::new(value)).is_ok(), "{value:#016b}");
+    }
+}
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, Clone, Copy)]
+enum Level {
+    Low,
+    High,
+    #[fallback]
+    Other,
+}
+
+#[bitsize(3, tag_bits = 1)]
+#[derive(TryFromBits, Debug, Clone, Copy)]
+enum Slot {
+    #[tag = 0]
+    Empty,
+    #[tag = 1]
+    Used(Parity),
+}
+
+#[bitsize(12)]
+#[derive(TryFromBits, DebugBits)]
+struct Mixed {
+    level: Level,
+    slot: Slot,
+    flags: [bool; 2],
+    parity: Parity,
+    #[reserved(value = 0b101)]
+    reserved: u3,
+}
+
+#[test]
+fn validity_matches_try_from() {
+    let validity = <Mixed as Bitsized>::VALIDITY;
+    assert!(validity.is_known());
+    for value in 0..1 << 12 {
+        assert_eq!(validity.accepts(value as u128), Mixed::try_from(u12::new(value)).is_ok(), "{value:#014b}");
+    }
+}
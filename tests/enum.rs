@@ -39,7 +39,119 @@ fn conversions() {
                 }
                 assert_eq!(u2::from(a), value);
             }
-            Err(e) => assert_eq!(format!("{e:?}"), "BitsError"),
+            Err(e) => {
+                assert_eq!(e.value(), value.value() as u128);
+                assert_eq!(format!("{e:?}"), "BitsError { path: [], offset: 0, value: 3 }");
+            }
         }
     }
 }
+
+#[bitsize(96)]
+#[derive(FromBits, BinaryBits, PartialEq, Debug)]
+enum Opcode {
+    Nop,
+    Halt = 0xdead_beef_0000_0000_0000,
+    #[fallback]
+    Other(u96),
+}
+
+#[bitsize(128)]
+#[derive(TryFromBits, BinaryBits, PartialEq, Debug)]
+enum Uuid {
+    Nil,
+    Max = 0xffff_ffff_ffff_ffff_ffff_ffff_ffff_ffff,
+}
+
+#[bitsize(128)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Instruction {
+    opcode: Opcode,
+    flags: u32,
+}
+
+#[test]
+fn wider_than_64_bits() {
+    assert_eq!(Opcode::from(u96::new(0)), Opcode::Nop);
+    assert_eq!(Opcode::from(u96::new(0xdead_beef_0000_0000_0000)), Opcode::Halt);
+    assert_eq!(Opcode::from(u96::new(7)), Opcode::Other(u96::new(7)));
+    assert_eq!(u96::from(Opcode::Halt), u96::new(0xdead_beef_0000_0000_0000));
+    assert_eq!(format!("{:b}", Opcode::Other(u96::new(5))), format!("{:096b}", 5));
+
+    assert_eq!(Uuid::try_from(u128::MAX), Ok(Uuid::Max));
+    assert_eq!(Uuid::try_from(0), Ok(Uuid::Nil));
+    assert!(Uuid::try_from(1).is_err());
+    assert_eq!(u128::from(Uuid::Max), u128::MAX);
+    assert_eq!(format!("{:b}", Uuid::Max), "1".repeat(128));
+
+    let mut instruction = Instruction::new(Opcode::Halt, 3);
+    assert_eq!(instruction.opcode(), Opcode::Halt);
+    instruction.set_opcode(Opcode::Other(u96::new(1)));
+    assert_eq!(u128::from(instruction), (3 << 96) | 1);
+}
+
+const BASE: u8 = 0x10;
+
+mod modes {
+    pub const FAST: u32 = 0x20;
+}
+
+#[bitsize(8)]
+#[derive(FromBits, BinaryBits, PartialEq, Debug)]
+enum Command {
+    Nop,
+    Load = BASE + 3,
+    Store,
+    Fast = modes::FAST as u8,
+    #[fallback]
+    Other(u8),
+}
+
+#[bitsize(4)]
+#[derive(TryFromBits, PartialEq, Debug)]
+enum Offset {
+    Back2 = -2,
+    Back1,
+    Zero = 0,
+    Forward = 1,
+    Far = -(BASE as i8 / 2),
+}
+
+// counting up from a negative value wraps around to zero, like in rust
+#[bitsize(4)]
+#[derive(TryFromBits, PartialEq, Debug)]
+enum Signed {
+    MinusOne = -1,
+    Next,
+}
+
+#[bitsize(4)]
+#[derive(TryFromBits, PartialEq, Debug)]
+enum ConstSigned {
+    MinusTwo = -(BASE as i8 / 8),
+    MinusOne,
+    Zero,
+}
+
+#[test]
+fn const_discriminants() {
+    assert_eq!(Command::from(0x13), Command::Load);
+    assert_eq!(Command::from(0x14), Command::Store);
+    assert_eq!(Command::from(0x20), Command::Fast);
+    assert_eq!(Command::from(0x15), Command::Other(0x15));
+    assert_eq!(u8::from(Command::Store), 0x14);
+    assert_eq!(format!("{:b}", Command::Load), "00010011");
+
+    assert_eq!(Offset::try_from(u4::new(0b1110)), Ok(Offset::Back2));
+    assert_eq!(Offset::try_from(u4::new(0b1111)), Ok(Offset::Back1));
+    assert_eq!(Offset::try_from(u4::new(0b1000)), Ok(Offset::Far));
+    assert!(Offset::try_from(u4::new(0b0010)).is_err());
+    assert_eq!(u4::from(Offset::Back1), u4::new(0b1111));
+
+    assert_eq!(u4::from(Signed::MinusOne), u4::new(0b1111));
+    assert_eq!(u4::from(Signed::Next), u4::new(0));
+    assert_eq!(Signed::try_from(u4::new(0)), Ok(Signed::Next));
+    assert_eq!(u4::from(ConstSigned::MinusOne), u4::new(0b1111));
+    assert_eq!(u4::from(ConstSigned::Zero), u4::new(0));
+    assert_eq!(ConstSigned::try_from(u4::new(0)), Ok(ConstSigned::Zero));
+}
@@ -82,3 +82,38 @@ fn different_unit_fallback_positions() {
     assert_matches!(UnitBaz::from(val), UnitBaz::Baz);
     assert_eq!(u5::from(UnitBaz::Baz), u5::new(2));
 }
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Mode {
+    Off = 0,
+    RxOnly = 1,
+    Duplex = 3,
+    #[fallback]
+    Reserved(u2),
+}
+
+#[test]
+fn value_fallback_with_discriminants() {
+    assert_eq!(Mode::from(u2::new(3)), Mode::Duplex);
+    // only the missing value is left for the fallback
+    assert_eq!(Mode::from(u2::new(2)), Mode::Reserved(u2::new(2)));
+    assert_eq!(u2::from(Mode::Reserved(u2::new(2))), u2::new(2));
+    assert_eq!(u2::from(Mode::Duplex), u2::new(3));
+}
+
+#[bitsize(3)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Speed {
+    Slow = 1,
+    Fast = 4,
+    #[fallback]
+    Other(u3),
+}
+
+#[test]
+fn tuple_fallback_with_discriminants() {
+    assert_eq!(Speed::from(u3::new(4)), Speed::Fast);
+    assert_eq!(Speed::from(u3::new(6)), Speed::Other(u3::new(6)));
+    assert_eq!(u3::from(Speed::Slow), u3::new(1));
+}
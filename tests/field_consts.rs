@@ -0,0 +1,86 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+use bilge::prelude::*;
+
+#[bitsize(32)]
+#[derive(FromBits)]
+struct Descriptor {
+    valid: bool,
+    reserved: u3,
+    header: u4,
+    lanes: [u2; 4],
+    length: u16,
+}
+
+#[test]
+fn offsets_bits_and_masks() {
+    assert_eq!(Descriptor::VALID_OFFSET, 0);
+    assert_eq!(Descriptor::VALID_BITS, 1);
+    assert_eq!(Descriptor::HEADER_OFFSET, 4);
+    assert_eq!(Descriptor::HEADER_BITS, 4);
+    assert_eq!(Descriptor::HEADER_MASK, 0x0000_00f0u32);
+    assert_eq!(Descriptor::LANES_OFFSET, 8);
+    assert_eq!(Descriptor::LANES_BITS, 8);
+    assert_eq!(Descriptor::LANES_STRIDE, 2);
+    assert_eq!(Descriptor::LENGTH_OFFSET, 16);
+    assert_eq!(Descriptor::LENGTH_MASK, 0xffff_0000);
+
+    // they agree with the accessors
+    let value = 0xabcd_5a71;
+    let descriptor = Descriptor::from(value);
+    assert_eq!(
+        ((value & Descriptor::HEADER_MASK) >> Descriptor::HEADER_OFFSET) as u8,
+        descriptor.header().value()
+    );
+    assert_eq!(
+        ((value & Descriptor::LENGTH_MASK) >> Descriptor::LENGTH_OFFSET) as u16,
+        descriptor.length()
+    );
+    let lane_2 = (value >> (Descriptor::LANES_OFFSET + 2 * Descriptor::LANES_STRIDE)) & 0b11;
+    assert_eq!(lane_2 as u8, descriptor.lanes_at(2).value());
+}
+
+#[bitsize(16, msb_first)]
+#[derive(FromBits)]
+struct Header(u4, bool, u11);
+
+#[test]
+fn msb_first() {
+    assert_eq!(Header::VAL_0_OFFSET, 12);
+    assert_eq!(Header::VAL_0_MASK, 0xf000u16);
+    assert_eq!(Header::VAL_1_OFFSET, 11);
+    assert_eq!(Header::VAL_1_MASK, 0x0800);
+    assert_eq!(Header::VAL_2_OFFSET, 0);
+    assert_eq!(Header::VAL_2_MASK, 0x07ff);
+}
+
+#[bitsize(5)]
+#[derive(FromBits)]
+struct Small {
+    flag: bool,
+    rest: u4,
+}
+
+#[bitsize(8)]
+#[derive(FromBits)]
+struct Entry<T> {
+    flag: bool,
+    payload: T,
+    reserved: u3,
+}
+
+#[bitsize(192)]
+#[derive(FromBits)]
+struct Wide {
+    head: u64,
+    body: u128,
+}
+
+#[test]
+fn other_representations() {
+    // `u5` is backed by a `u8`
+    assert_eq!(Small::REST_MASK, 0b11110u8);
+    assert_eq!(Entry::<u4>::PAYLOAD_OFFSET, 1);
+    assert_eq!(Entry::<u4>::PAYLOAD_MASK, 0b0001_1110);
+    assert_eq!(Wide::BODY_OFFSET, 64);
+    assert_eq!(Wide::BODY_BITS, 128);
+}
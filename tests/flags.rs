@@ -0,0 +1,113 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(8)]
+#[derive(FromBits, DebugBits, FlagsBits, Clone, Copy, PartialEq)]
+struct Permissions {
+    read: bool,
+    write: bool,
+    execute: bool,
+    #[reserved(value = 0b1_0101)]
+    reserved: u5,
+}
+
+#[bitsize(16, msb_first)]
+#[derive(FromBits, DebugBits, FlagsBits, Clone, Copy, PartialEq)]
+pub struct Interrupts {
+    pub enabled: bool,
+    pub pending: [bool; 3],
+    padding: u11,
+    pub masked: bool,
+}
+
+const READ_WRITE: Permissions = Permissions::READ.union(Permissions::WRITE);
+
+#[test]
+fn operators() {
+    assert_eq!(Permissions::READ | Permissions::WRITE, READ_WRITE);
+    assert_eq!(READ_WRITE & Permissions::WRITE, Permissions::WRITE);
+    assert_eq!(READ_WRITE ^ Permissions::all(), Permissions::EXECUTE);
+    assert_eq!(Permissions::all() - Permissions::EXECUTE, READ_WRITE);
+    assert_eq!(!READ_WRITE, Permissions::EXECUTE);
+    assert_eq!(!Permissions::empty(), Permissions::all());
+
+    let mut flags = Permissions::empty();
+    flags |= Permissions::EXECUTE;
+    flags ^= Permissions::READ;
+    assert_eq!(flags, Permissions::new(true, false, true));
+    flags &= Permissions::READ;
+    assert_eq!(flags, Permissions::READ);
+    flags -= Permissions::READ;
+    assert!(flags.is_empty());
+}
+
+#[test]
+fn queries() {
+    assert!(READ_WRITE.contains(Permissions::READ));
+    assert!(!READ_WRITE.contains(Permissions::READ | Permissions::EXECUTE));
+    assert!(READ_WRITE.intersects(Permissions::READ | Permissions::EXECUTE));
+    assert!(!READ_WRITE.intersects(Permissions::EXECUTE));
+    assert!(Permissions::empty().is_empty());
+    assert!(Permissions::all().is_all());
+    assert!(!READ_WRITE.is_all());
+    // every value contains the empty set
+    assert!(Permissions::empty().contains(Permissions::empty()));
+}
+
+#[test]
+fn insert_remove_toggle() {
+    let mut flags = Permissions::READ;
+    flags.insert(Permissions::EXECUTE);
+    assert_eq!(flags, Permissions::new(true, false, true));
+    flags.remove(Permissions::READ | Permissions::WRITE);
+    assert_eq!(flags, Permissions::EXECUTE);
+    flags.toggle(Permissions::all());
+    assert_eq!(flags, READ_WRITE);
+
+    const TOGGLED: Permissions = {
+        let mut flags = Permissions::empty();
+        flags.toggle(Permissions::WRITE);
+        flags
+    };
+    assert_eq!(TOGGLED, Permissions::WRITE);
+}
+
+#[test]
+fn reserved_bits_are_masked_out() {
+    assert_eq!(Permissions::all().to_raw(), 0b10101_111);
+    assert_eq!(Permissions::empty().to_raw(), 0b10101_000);
+
+    // reserved bits read from somewhere else are kept, but don't count as flags
+    let raw = Permissions::from(0b11111_010);
+    assert_eq!((!raw).to_raw(), 0b11111_101);
+    assert_eq!((raw | Permissions::READ).to_raw(), 0b11111_011);
+    assert!(raw.contains(Permissions::WRITE));
+    assert!((raw - Permissions::WRITE).is_empty());
+    assert_eq!(raw.iter_names().collect::<Vec<_>>(), ["write"]);
+}
+
+#[test]
+fn arrays() {
+    let pending = Interrupts::new(false, [true, false, true], false);
+    assert_eq!(Interrupts::PENDING, Interrupts::new(false, [true; 3], false));
+    assert!(Interrupts::PENDING.contains(pending));
+    assert_eq!(!pending, Interrupts::new(true, [false, true, false], true));
+    assert_eq!(Interrupts::all().to_raw(), 0b1_111_00000000000_1);
+    assert_eq!(Interrupts::FLAGS.len(), 5);
+    assert_eq!(Interrupts::FLAGS[2], ("pending[1]", Interrupts::new(false, [false, true, false], false)));
+}
+
+#[test]
+fn names() {
+    assert_eq!(Permissions::empty().iter_names().count(), 0);
+    assert_eq!(Permissions::all().iter_names().collect::<Vec<_>>(), ["read", "write", "execute"]);
+
+    let interrupts = Interrupts::ENABLED | Interrupts::new(false, [false, true, true], true);
+    assert_eq!(
+        interrupts.iter_names().collect::<Vec<_>>(),
+        ["enabled", "pending[1]", "pending[2]", "masked"]
+    );
+    let names: Vec<_> = Interrupts::FLAGS.iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, ["enabled", "pending[0]", "pending[1]", "pending[2]", "masked"]);
+}
@@ -0,0 +1,143 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::{prelude::*, PathSegment};
+
+#[bitsize(5)]
+#[derive(TryFromBits, DebugBits, DefaultBits, BinaryBits, Clone, Copy, PartialEq)]
+struct Entry<T> {
+    flag: bool,
+    payload: T,
+}
+
+#[bitsize(4)]
+#[derive(TryFromBits, Debug, Default, Clone, Copy, PartialEq)]
+enum Kind {
+    #[default]
+    Read,
+    Write,
+    Erase = 15,
+}
+
+#[test]
+fn type_param() {
+    let mut entry = Entry::new(true, u4::new(9));
+    assert!(entry.flag());
+    assert_eq!(entry.payload(), u4::new(9));
+    entry.set_payload(u4::new(3));
+    assert_eq!(u5::from(entry), u5::new(0b0011_1));
+
+    let mut entry = Entry::new(false, Kind::Erase);
+    assert_eq!(entry.payload(), Kind::Erase);
+    entry.set_payload(Kind::Write);
+    entry.set_flag(true);
+    assert_eq!(u5::from(entry), u5::new(0b0001_1));
+
+    assert_eq!(Entry::<Kind>::try_from(u5::new(0b1111_0)), Ok(Entry::new(false, Kind::Erase)));
+    assert!(Entry::<Kind>::try_from(u5::new(0b0111_0)).is_err());
+    assert!(Entry::<u4>::try_from(u5::new(0b0111_0)).is_ok());
+}
+
+#[bitsize(4)]
+#[derive(TryFromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Slot {
+    kind: Kind,
+}
+
+#[test]
+fn type_param_error_path() {
+    let error = Slot::try_from(u4::new(0b0111)).unwrap_err();
+    assert_eq!(error.path(), &[PathSegment::Field("kind")]);
+
+    // the path below a generic field is lost, so the error starts at that field
+    let error = Entry::<Slot>::try_from(u5::new(0b0111_0)).unwrap_err();
+    assert_eq!(error.path(), &[PathSegment::Field("payload")]);
+    assert_eq!((error.offset(), error.value()), (1, 0b0111));
+}
+
+#[test]
+fn type_param_derives() {
+    let entry = Entry::new(true, Kind::Erase);
+    assert_eq!(format!("{entry:?}"), "Entry { flag: true, payload: Erase }");
+    assert_eq!(format!("{entry:b}"), "1111_1");
+    assert_eq!(Entry::<Kind>::default(), Entry::new(false, Kind::Read));
+    assert_eq!(Entry::<u4>::from_le_bytes([0xff]), Ok(Entry::new(true, u4::new(15))));
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, DefaultBits, PartialEq)]
+struct Lanes<const N: usize>([u4; N]);
+
+#[test]
+fn const_param() {
+    let mut lanes = Lanes::<4>::new([u4::new(1), u4::new(2), u4::new(3), u4::new(4)]);
+    assert_eq!(lanes.val_0_at(2), u4::new(3));
+    lanes.set_val_0_at(0, u4::new(15));
+    assert_eq!(u16::from(lanes), 0x432f);
+    assert_eq!(Lanes::<4>::from(0x1234).val_0(), [u4::new(4), u4::new(3), u4::new(2), u4::new(1)]);
+    assert_eq!(Lanes::<4>::default(), Lanes::from(0));
+}
+
+#[bitsize(12)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Pair<A, B>(A, [B; 2])
+where
+    A: Copy;
+
+#[test]
+fn type_params_in_arrays() {
+    let mut pair = Pair::new(u4::new(1), [u4::new(1), u4::new(0)]);
+    pair.set_val_1_at(1, u4::new(2));
+    assert_eq!(pair.val_1(), [u4::new(1), u4::new(2)]);
+    assert_eq!(u12::from(pair), u12::new(0x211));
+
+    let mut pair = Pair::<u2, u5>::from(u12::new(0b10101_00001_11));
+    assert_eq!(pair.val_0(), u2::new(0b11));
+    assert_eq!(pair.val_1(), [u5::new(1), u5::new(0b10101)]);
+    pair.set_val_1([u5::new(0b11111), u5::new(0)]);
+    assert_eq!(u12::from(pair), u12::new(0b00000_11111_11));
+}
+
+#[bitsize(136)]
+#[derive(FromBits, DebugBits, DefaultBits, PartialEq)]
+struct WideEntry<T> {
+    head: u8,
+    body: [T; 2],
+}
+
+#[test]
+fn wide() {
+    let mut entry = WideEntry::new(0xab, [u64::MAX, 1]);
+    assert_eq!(entry.body_at(0), u64::MAX);
+    entry.set_body_at(0, 2);
+    let bytes = <[u8; 17]>::from(entry);
+    assert_eq!(bytes[0], 0xab);
+    assert_eq!(bytes[1], 2);
+    assert_eq!(bytes[9], 1);
+    assert_eq!(WideEntry::<u64>::from(bytes).body(), [2, 1]);
+    assert_eq!(WideEntry::<u64>::default(), WideEntry::from([0; 17]));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    use serde_test::{assert_tokens, Token};
+
+    #[bitsize(5)]
+    #[derive(SerializeBits, DeserializeBits, FromBits, DebugBits, PartialEq)]
+    struct Message<T> {
+        urgent: bool,
+        body: T,
+    }
+
+    assert_tokens(
+        &Message::new(true, u4::new(7)),
+        &[
+            Token::Struct { name: "Message", len: 2 },
+            Token::Str("urgent"),
+            Token::Bool(true),
+            Token::Str("body"),
+            Token::U8(7),
+            Token::StructEnd,
+        ],
+    );
+}
@@ -0,0 +1,98 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+use bilge::{prelude::*, Access, FieldInfo, Layout, VariantInfo};
+
+#[bitsize(2)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Mode {
+    Off,
+    Slow,
+    Fast,
+    Turbo,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits)]
+struct Status {
+    ready: bool,
+    mode: Mode,
+    reserved: u1,
+    #[access(w1c)]
+    error: bool,
+    #[access(ro)]
+    lanes: [u2; 3],
+    padding: u5,
+}
+
+#[test]
+fn fields() {
+    let fields = Status::FIELDS;
+    assert_eq!(fields.len(), 6);
+    assert_eq!(fields[0], FieldInfo::new("ready", 0, 1, "bool", Access::ReadWrite, false));
+    assert_eq!(fields[1], FieldInfo::new("mode", 1, 2, "Mode", Access::ReadWrite, false));
+    assert_eq!(fields[2], FieldInfo::new("reserved_i", 3, 1, "u1", Access::ReadWrite, true));
+    assert_eq!(fields[3], FieldInfo::new("error", 4, 1, "bool", Access::WriteOneToClear, false));
+    assert_eq!(fields[4], FieldInfo::new("lanes", 5, 6, "[u2; 3]", Access::ReadOnly, false));
+    assert_eq!(fields[5], FieldInfo::new("padding_i", 11, 5, "u5", Access::ReadWrite, true));
+
+    // the fields cover every bit
+    let total: usize = fields.iter().map(|field| field.width).sum();
+    assert_eq!(total, <Status as Bitsized>::BITS);
+}
+
+/// Generic tooling only needs the trait, like this dump of every non-reserved field.
+fn dump<T: Layout>(value: u128, out: &mut [(&'static str, u128)]) -> usize {
+    let mut count = 0;
+    for field in T::FIELDS.iter().filter(|field| !field.reserved) {
+        let mask = (1 << field.width) - 1;
+        out[count] = (field.name, (value >> field.offset) & mask);
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn generic_tooling() {
+    let status = Status::new(true, Mode::Fast, false, [u2::new(3), u2::new(0), u2::new(1)]);
+    let mut out = [("", 0); 8];
+    let count = dump::<Status>(u16::from(status) as u128, &mut out);
+    assert_eq!(&out[..count], &[("ready", 1), ("mode", 2), ("error", 0), ("lanes", 0b01_00_11)]);
+}
+
+#[bitsize(12, msb_first)]
+#[derive(FromBits)]
+struct Header(u4, u8);
+
+#[bitsize(5)]
+#[derive(FromBits)]
+struct Entry<T> {
+    flag: bool,
+    payload: T,
+}
+
+#[test]
+fn other_structs() {
+    assert_eq!(Header::FIELDS[0], FieldInfo::new("val_0", 8, 4, "u4", Access::ReadWrite, false));
+    assert_eq!(Header::FIELDS[1], FieldInfo::new("val_1", 0, 8, "u8", Access::ReadWrite, false));
+
+    let payload = Entry::<u4>::FIELDS[1];
+    assert_eq!((payload.name, payload.offset, payload.width, payload.type_name), ("payload", 1, 4, "T"));
+}
+
+#[bitsize(3)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Level {
+    Low = 1,
+    High = 6,
+    #[fallback]
+    Other(u3),
+}
+
+#[test]
+fn variants() {
+    assert_eq!(Mode::FIELDS, &[]);
+    assert_eq!(Mode::VARIANTS[2], VariantInfo::new("Fast", 2));
+    assert_eq!(Mode::VARIANTS.len(), 4);
+    // the fallback keeping the value has none of its own
+    assert_eq!(Level::VARIANTS, &[VariantInfo::new("Low", 1), VariantInfo::new("High", 6)]);
+    assert_eq!(Status::VARIANTS, &[]);
+}
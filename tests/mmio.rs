@@ -0,0 +1,154 @@
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::mmio::{Reg, RoReg, WoReg};
+use bilge::prelude::*;
+
+#[bitsize(32)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Control {
+    enable: bool,
+    divider: u4,
+    reserved: u27,
+}
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Off,
+    Slow,
+    Fast,
+}
+
+#[bitsize(8)]
+#[derive(TryFromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Config {
+    mode: Mode,
+    level: u6,
+}
+
+#[bitsize(5)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Small {
+    ready: bool,
+    count: u4,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Irq {
+    #[access(w1c)]
+    rx: bool,
+    #[access(w1c)]
+    tx: bool,
+    enable: bool,
+    reserved: u5,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Irqs {
+    count: u8,
+    irq: Irq,
+}
+
+#[test]
+fn read_write_modify() {
+    let mut memory = 0u32;
+    let control = unsafe { Reg::<Control>::new(&mut memory) };
+    assert_eq!(control.as_ptr(), &mut memory as *mut u32);
+
+    control.write(Control::new(true, u4::new(3)));
+    assert_eq!(control.read(), Control::new(true, u4::new(3)));
+
+    let mut calls = 0;
+    control.modify(|control| {
+        calls += 1;
+        control.set_divider(u4::new(9));
+    });
+    assert_eq!(calls, 1);
+    assert_eq!(memory, 0b1001_1);
+}
+
+#[test]
+fn registers_in_a_buffer() {
+    let mut memory = [0u32; 2];
+    let first = unsafe { Reg::<Control>::new(memory.as_mut_ptr()) };
+    let second = unsafe { Reg::<Control>::new(memory.as_mut_ptr().add(1)) };
+
+    first.write(Control::new(true, u4::new(1)));
+    second.write(Control::new(false, u4::new(15)));
+    second.modify(|control| control.set_enable(true));
+    assert_eq!(memory, [0b0001_1, 0b1111_1]);
+}
+
+#[test]
+fn fallible() {
+    let mut memory = 0b000101_01u8;
+    let config = unsafe { Reg::<Config>::new(&mut memory) };
+    assert_eq!(config.try_read(), Ok(Config::new(Mode::Slow, u6::new(5))));
+
+    assert_eq!(config.try_modify(|config| config.set_mode(Mode::Fast)), Ok(()));
+    assert_eq!(memory, 0b000101_10);
+
+    // `Mode` has no variant for `0b11`, so nothing is written
+    memory = 0b11;
+    let config = unsafe { Reg::<Config>::new(&mut memory) };
+    let mut calls = 0;
+    assert!(config.try_modify(|_| calls += 1).is_err());
+    assert_eq!(calls, 0);
+    assert_eq!(memory, 0b11);
+}
+
+#[test]
+fn bits_above_bitsize() {
+    let mut memory = 0b111_0011_1u8;
+    let small = unsafe { Reg::<Small>::new(&mut memory) };
+    // bits 5 to 7 are ignored when reading
+    assert_eq!(small.read(), Small::new(true, u4::new(3)));
+    // and written as zero
+    small.modify(|small| small.set_ready(false));
+    assert_eq!(memory, 0b0011_0);
+}
+
+#[test]
+fn read_only_and_write_only() {
+    let mut memory = 0b0101_1u32;
+    let status = unsafe { RoReg::<Control>::new(&mut memory) };
+    assert_eq!(status.read(), Control::new(true, u4::new(5)));
+
+    let mut memory = 0u8;
+    let command = unsafe { WoReg::<Config>::new(&mut memory) };
+    command.write(Config::new(Mode::Fast, u6::new(1)));
+    assert_eq!(memory, 0b000001_10);
+
+    let mut memory = 0b11u8;
+    let status = unsafe { RoReg::<Config>::new(&mut memory) };
+    assert!(status.try_read().is_err());
+}
+
+#[test]
+fn modify_keeps_pending_w1c_flags() {
+    // both `rx` and `tx` are pending
+    let mut memory = 0b011u8;
+    let irq = unsafe { Reg::<Irq>::new(&mut memory) };
+    assert_eq!(irq.read(), Irq::new(true, true, false));
+
+    // writing them back as 1 would acknowledge them
+    irq.modify(|irq| irq.set_enable(true));
+    assert_eq!(memory, 0b100);
+
+    memory = 0b011;
+    let irq = unsafe { Reg::<Irq>::new(&mut memory) };
+    irq.modify(|irq| irq.clear_rx());
+    assert_eq!(memory, 0b001);
+
+    memory = 0b011;
+    let irq = unsafe { Reg::<Irq>::new(&mut memory) };
+    assert_eq!(irq.try_modify(|irq| irq.clear_tx()), Ok(()));
+    assert_eq!(memory, 0b010);
+
+    // also within nested bitfields
+    let mut memory = 0b111_00000101u16;
+    let irqs = unsafe { Reg::<Irqs>::new(&mut memory) };
+    irqs.modify(|irqs| irqs.set_count(6));
+    assert_eq!(memory, 0b100_00000110);
+}
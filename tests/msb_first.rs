@@ -0,0 +1,84 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+// the first word of an IPv4 header, as drawn in RFC 791
+#[bitsize(32, msb_first)]
+#[derive(FromBits, DebugBits, DefaultBits, BinaryBits, PartialEq)]
+struct Ipv4Word {
+    version: u4,
+    ihl: u4,
+    dscp: u6,
+    ecn: u2,
+    total_length: u16,
+    #[alias(of = "version")]
+    version_ihl: u8,
+}
+
+#[test]
+fn msb_first() {
+    let mut word = Ipv4Word::from(0x4500_0054);
+    assert_eq!(word.version(), u4::new(4));
+    assert_eq!(word.ihl(), u4::new(5));
+    assert_eq!(word.dscp(), u6::new(0));
+    assert_eq!(word.total_length(), 0x54);
+    assert_eq!(word.version_ihl(), 0x45);
+
+    word.set_ecn(u2::new(0b11));
+    assert_eq!(u32::from(word), 0x4503_0054);
+    assert_eq!(
+        Ipv4Word::new(u4::new(6), u4::new(0), u6::new(1), u2::new(0), 40),
+        Ipv4Word::from(0x6004_0028)
+    );
+    assert_eq!(format!("{:b}", Ipv4Word::from(0x6004_0028)), "0110_0000_000001_00_0000000000101000");
+    assert_eq!(Ipv4Word::default(), Ipv4Word::from(0));
+}
+
+#[bitsize(8, msb_first)]
+#[derive(TryFromBits, DebugBits, DefaultBits, PartialEq)]
+struct Frame {
+    #[bits(0..=1)]
+    kind: Kind,
+    #[bit(7)]
+    last: bool,
+}
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, PartialEq, Default)]
+enum Kind {
+    #[default]
+    Data = 1,
+    Control,
+}
+
+#[test]
+fn msb_first_bit_ranges() {
+    // bit ranges count from the most significant bit, like in most protocol diagrams
+    let frame = Frame::try_from(0b10_00000_1).unwrap();
+    assert_eq!(frame.kind(), Kind::Control);
+    assert!(frame.last());
+    assert!(Frame::try_from(0b00_00000_1).is_err());
+    assert_eq!(u8::from(Frame::default()), 0b01_00000_0);
+}
+
+#[bitsize(256, msb_first)]
+#[derive(FromBits, DefaultBits, BinaryBits)]
+struct WideWord {
+    tag: u8,
+    body: [u64; 3],
+    reserved: u56,
+}
+
+#[test]
+fn wide_msb_first() {
+    let word = WideWord::new(0xab, [1, 2, 3]);
+    let bytes: [u8; 32] = word.into();
+    // `tag` ends up in the most significant byte
+    assert_eq!(bytes[31], 0xab);
+    // elements of a field are still laid out from its least significant bit up
+    assert_eq!(bytes[7], 1);
+    assert_eq!(bytes[23], 3);
+    let word = WideWord::from(bytes);
+    assert_eq!(word.body_at(1), 2);
+    assert!(format!("{word:b}").starts_with("10101011_"));
+}
@@ -0,0 +1,99 @@
+#![allow(clippy::unusual_byte_groupings)]
+use core::mem::{offset_of, size_of};
+
+use bilge::mmio::{Reg, RoReg, WoReg};
+use bilge::prelude::*;
+use bilge::register_block;
+
+#[bitsize(32)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Control {
+    enable: bool,
+    divider: u4,
+    reserved: u27,
+}
+
+#[bitsize(32)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Status {
+    ready: bool,
+    reserved: u31,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Data {
+    value: u8,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct Baud {
+    rate: u16,
+}
+
+#[register_block]
+struct Uart {
+    /// Enables the peripheral.
+    #[offset(0x00)]
+    control: Reg<Control>,
+    #[offset(0x08)]
+    status: RoReg<Status>,
+    #[offset(0x0c)]
+    data: WoReg<Data>,
+    #[offset(0x0e)]
+    baud: Reg<Baud>,
+}
+
+#[test]
+fn layout() {
+    assert_eq!(offset_of!(Uart, control), 0x00);
+    assert_eq!(offset_of!(Uart, status), 0x08);
+    assert_eq!(offset_of!(Uart, data), 0x0c);
+    assert_eq!(offset_of!(Uart, baud), 0x0e);
+    assert_eq!(size_of::<Uart>(), 0x10);
+}
+
+#[test]
+fn accessors() {
+    let mut memory = [0u32; 4];
+    let uart = unsafe { Uart::from_ptr(memory.as_mut_ptr().cast()) };
+
+    uart.control().write(Control::new(true, u4::new(3)));
+    uart.control().modify(|control| control.set_divider(u4::new(5)));
+    uart.data().write(Data::new(0xab));
+    uart.baud().write(Baud::new(0x1234));
+    assert_eq!(uart.status().read(), Status::new(false));
+
+    assert_eq!(memory[..3], [0b0101_1, 0, 0]);
+    let bytes = memory[3].to_ne_bytes();
+    assert_eq!(bytes[0], 0xab);
+    assert_eq!(u16::from_ne_bytes([bytes[2], bytes[3]]), 0x1234);
+    // registers point into the block
+    assert_eq!(uart.status().as_ptr().cast(), memory.as_mut_ptr().wrapping_add(2));
+
+    memory[2] = 1;
+    let uart = unsafe { Uart::from_ptr(memory.as_mut_ptr().cast()) };
+    assert!(uart.status().read().ready());
+}
+
+// registers are sorted by their offset
+#[register_block]
+pub struct Timer {
+    #[offset(0x10)]
+    pub compare: Reg<Status>,
+    #[offset(0x4)]
+    pub counter: RoReg<Status>,
+}
+
+#[test]
+fn unsorted() {
+    assert_eq!(offset_of!(Timer, counter), 0x4);
+    assert_eq!(offset_of!(Timer, compare), 0x10);
+    assert_eq!(size_of::<Timer>(), 0x14);
+
+    let mut memory = [0u32; 5];
+    let timer = unsafe { Timer::from_ptr(memory.as_mut_ptr().cast()) };
+    timer.compare().write(Status::new(true));
+    assert_eq!(memory, [0, 0, 0, 0, 1]);
+}
@@ -0,0 +1,124 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::{prelude::*, PathSegment};
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq, Default)]
+enum Mode {
+    #[default]
+    Off,
+    On,
+    Auto,
+}
+
+#[bitsize(16)]
+#[derive(TryFromBits, DebugBits, DefaultBits, PartialEq)]
+struct Control {
+    enable: bool,
+    #[reserved(zero)]
+    reserved: u3,
+    mode: Mode,
+    #[reserved(ones)]
+    reserved: u2,
+    #[reserved(value = 0b10)]
+    padding: u2,
+    // without a rule, any value is accepted
+    reserved: u6,
+}
+
+#[test]
+fn new_and_default_write_required_values() {
+    let control = Control::new(true, Mode::Auto);
+    assert_eq!(u16::from(control), 0b000000_10_11_10_000_1);
+    assert_eq!(u16::from(Control::default()), 0b000000_10_11_00_000_0);
+    let built = Control::builder().enable(false).mode(Mode::On).build();
+    assert_eq!(u16::from(built), 0b000000_10_11_01_000_0);
+}
+
+#[test]
+fn try_from_enforces_them() {
+    let control = Control::try_from(0b101010_10_11_10_000_1).unwrap();
+    assert_eq!((control.enable(), control.mode()), (true, Mode::Auto));
+    assert_eq!(u16::from(control), 0b101010_10_11_10_000_1);
+
+    let error = Control::try_from(0b000000_10_11_00_010_0).unwrap_err();
+    assert_eq!(error.path(), &[PathSegment::Field("reserved_i")]);
+    assert_eq!((error.offset(), error.value()), (1, 0b010));
+
+    let error = Control::try_from(0b000000_10_01_00_000_0).unwrap_err();
+    assert_eq!(
+        (error.path(), error.offset(), error.value()),
+        (&[PathSegment::Field("reserved_ii")][..], 6, 0b01)
+    );
+
+    let error = Control::try_from(0b000000_11_11_00_000_0).unwrap_err();
+    assert_eq!(
+        (error.path(), error.offset(), error.value()),
+        (&[PathSegment::Field("padding_i")][..], 8, 0b11)
+    );
+    assert_eq!(error.to_string(), "invalid value 0x3 in `padding_i` at bit 8");
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DebugBits, DefaultBits)]
+struct Infallible {
+    #[reserved(ones)]
+    reserved: u2,
+    value: u6,
+}
+
+#[test]
+fn from_keeps_any_value() {
+    assert_eq!(u8::from(Infallible::new(u6::new(1))), 0b000001_11);
+    assert_eq!(u8::from(Infallible::default()), 0b000000_11);
+    // `From` can't reject the cleared bits, so they're kept
+    let infallible = Infallible::from(0b000001_00);
+    assert_eq!((infallible.value(), infallible.reserved_i()), (u6::new(1), u2::new(0)));
+}
+
+#[bitsize(8, msb_first)]
+#[derive(TryFromBits, DebugBits, DefaultBits)]
+struct Header {
+    #[reserved(value = 0b101)]
+    reserved: u3,
+    length: u5,
+}
+
+#[test]
+fn msb_first() {
+    assert_eq!(u8::from(Header::new(u5::new(3))), 0b101_00011);
+    assert_eq!(u8::from(Header::default()), 0b101_00000);
+    assert!(Header::try_from(0b101_11111).is_ok());
+
+    let error = Header::try_from(0b001_11111).unwrap_err();
+    assert_eq!(
+        (error.path(), error.offset(), error.value()),
+        (&[PathSegment::Field("reserved_i")][..], 5, 0b001)
+    );
+}
+
+#[bitsize(136)]
+#[derive(TryFromBits, DebugBits, DefaultBits)]
+struct Wide {
+    payload: u128,
+    #[reserved(ones)]
+    reserved: u8,
+}
+
+#[test]
+fn wide() {
+    let wide = Wide::new(7);
+    assert_eq!(wide.payload(), 7);
+    assert_eq!(wide.reserved_i(), u8::MAX);
+    assert_eq!(Wide::default().reserved_i(), u8::MAX);
+
+    let mut bytes = [0; 17];
+    bytes[16] = 0xFF;
+    assert!(Wide::try_from(bytes).is_ok());
+    bytes[16] = 0xFE;
+    let error = Wide::try_from(bytes).unwrap_err();
+    assert_eq!(
+        (error.path(), error.offset(), error.value()),
+        (&[PathSegment::Field("reserved_i")][..], 128, 0xFE)
+    );
+}
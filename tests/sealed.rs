@@ -0,0 +1,60 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+mod registers {
+    use super::*;
+
+    #[bitsize(8, sealed)]
+    #[derive(TryFromBits, DebugBits, DefaultBits, BinaryBits, PartialEq, Clone, Copy)]
+    pub struct Control {
+        pub mode: Mode,
+        pub(crate) enabled: bool,
+        reserved: u1,
+        pub(super) divider: u4,
+    }
+
+    #[bitsize(2)]
+    #[derive(TryFromBits, Debug, PartialEq, Default)]
+    pub enum Mode {
+        #[default]
+        Off,
+        Slow,
+        Fast,
+    }
+
+    #[bitsize(256, sealed)]
+    #[derive(FromBits, DebugBits)]
+    pub struct Wide(pub [u64; 4]);
+}
+use registers::*;
+
+#[test]
+fn sealed() {
+    let mut control = Control::new(Mode::Fast, true, u4::new(3));
+    control.set_divider(u4::new(5));
+    assert_eq!(control.mode(), Mode::Fast);
+    assert!(control.enabled());
+    assert_eq!(control.divider(), u4::new(5));
+    assert_eq!(control.to_raw(), u8::new(0b0101_0_1_10));
+
+    // derives are expanded inside the sealed module, so they still work
+    assert_eq!(Control::try_from(0b0101_0_1_10), Ok(control));
+    assert!(Control::try_from(0b11).is_err());
+    assert_eq!(Control::default(), Control::new(Mode::Off, false, u4::new(0)));
+    assert_eq!(u8::from(control), 0b0101_0_1_10);
+    assert_eq!(format!("{control:?}"), "Control { mode: Fast, enabled: true, reserved_i: 0, divider: 5 }");
+    assert_eq!(format!("{control:b}"), "0101_0_1_10");
+
+    let raw = unsafe { Control::from_raw_unchecked(control.to_raw()) };
+    assert_eq!(raw, control);
+
+    // the builder is re-exported next to the struct
+    let builder: ControlBuilder<false, false, false> = Control::builder();
+    let built = builder.divider(u4::new(5)).enabled(true).mode(Mode::Fast).build();
+    assert_eq!(built, control.with_enabled(true));
+
+    let wide = Wide::new([1, 2, 3, 4]);
+    assert_eq!(wide.val_0_at(2), 3);
+    assert_eq!(wide.to_raw()[8], 2);
+}
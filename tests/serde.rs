@@ -2,7 +2,7 @@
 #![allow(clippy::unusual_byte_groupings)]
 
 use bilge::prelude::*;
-use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, assert_tokens, Token};
 
 #[bitsize(17)]
 #[derive(FromBits, PartialEq, SerializeBits, DeserializeBits, DebugBits)]
@@ -196,3 +196,238 @@ fn serde_tuple_struct_map_signed() {
         r#"invalid type: string "val_0", expected u8"#,
     );
 }
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, PartialEq)]
+enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+#[bitsize(13)]
+#[derive(TryFromBits, PartialEq, SerializeBits, DeserializeBits, DebugBits)]
+#[serde_bits(repr = "int")]
+struct IntRepr {
+    baud: u11,
+    parity: Parity,
+}
+
+#[test]
+fn serde_int_repr() {
+    let bits = IntRepr::new(u11::new(0b101_0101_0101), Parity::Odd);
+
+    assert_tokens(&bits, &[Token::U16(0b10_101_0101_0101)]);
+}
+
+#[test]
+fn serde_int_repr_invalid() {
+    // `Parity` has no variant for 3
+    assert_de_tokens_error::<IntRepr>(&[Token::U16(0b11_000_0000_0000)], "invalid value 0x3 in `parity` at bit 11");
+    assert_de_tokens_error::<IntRepr>(&[Token::U16(1 << 13)], "invalid value: integer `8192`, expected an integer of 13 bits");
+}
+
+#[bitsize(8)]
+#[derive(FromBits, PartialEq, SerializeBits, DeserializeBits, DebugBits)]
+#[serde_bits(repr = "bits")]
+struct BitsRepr {
+    low: u4,
+    high: u4,
+}
+
+#[bitsize(6)]
+#[derive(TryFromBits, PartialEq, SerializeBits, DeserializeBits, DebugBits)]
+#[serde_bits(repr = "bits")]
+struct UnevenBitsRepr {
+    parity: Parity,
+    rest: u4,
+}
+
+#[test]
+fn serde_bits_repr() {
+    assert_tokens(&BitsRepr::from(0b1010_0011), &[Token::Str("0b1010_0011")]);
+    assert_tokens(&UnevenBitsRepr::new(Parity::Even, u4::new(0b1100)), &[Token::Str("0b11_0001")]);
+}
+
+#[test]
+fn serde_bits_repr_without_underscores() {
+    assert_de_tokens(&BitsRepr::from(0b1010_0011), &[Token::Str("0b10100011")]);
+    // leading zeros are optional
+    assert_de_tokens(&BitsRepr::from(0b11), &[Token::Str("0b11")]);
+    assert_de_tokens(&BitsRepr::from(0b11), &[Token::Str("0b0000_0000_0011")]);
+}
+
+#[test]
+fn serde_bits_repr_invalid() {
+    let expected = "expected a binary string like `0b1010` of 8 bits";
+    assert_de_tokens_error::<BitsRepr>(&[Token::Str("1010")], &format!(r#"invalid value: string "1010", {expected}"#));
+    assert_de_tokens_error::<BitsRepr>(&[Token::Str("0b")], &format!(r#"invalid value: string "0b", {expected}"#));
+    assert_de_tokens_error::<BitsRepr>(&[Token::Str("0b1012")], &format!(r#"invalid value: string "0b1012", {expected}"#));
+    assert_de_tokens_error::<BitsRepr>(
+        &[Token::Str("0b1_0000_0000")],
+        &format!(r#"invalid value: string "0b1_0000_0000", {expected}"#),
+    );
+    assert_de_tokens_error::<UnevenBitsRepr>(&[Token::Str("0b00_0011")], "invalid value 0x3 in `parity` at bit 0");
+}
+
+// skipped fields don't need to implement serde's traits
+#[bitsize(2)]
+#[derive(FromBits, Debug, PartialEq, Default)]
+enum Mode {
+    #[default]
+    Idle,
+    Run,
+    Sleep,
+    Halt,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, PartialEq, SerializeBits, DeserializeBits, DebugBits)]
+#[serde(rename = "Config", rename_all = "camelCase")]
+struct Renamed {
+    baud_rate: u8,
+    #[serde(rename = "op")]
+    op_mode: u2,
+    #[serde(skip)]
+    dirty: bool,
+    #[serde(default)]
+    retry_count: u4,
+    reserved: u1,
+}
+
+#[test]
+fn serde_rename() {
+    let config = Renamed::new(115, u2::new(1), false, u4::new(3));
+
+    assert_tokens(
+        &config,
+        &[
+            Token::Struct { name: "Config", len: 3 },
+            Token::Str("baudRate"),
+            Token::U8(115),
+            Token::Str("op"),
+            Token::U8(1),
+            Token::Str("retryCount"),
+            Token::U8(3),
+            Token::StructEnd,
+        ],
+    );
+}
+
+#[test]
+fn serde_skip_and_default() {
+    // skipped fields aren't serialized and get their default back
+    let config = Renamed::new(115, u2::new(1), true, u4::new(3));
+    assert_ser_tokens(
+        &config,
+        &[
+            Token::Struct { name: "Config", len: 3 },
+            Token::Str("baudRate"),
+            Token::U8(115),
+            Token::Str("op"),
+            Token::U8(1),
+            Token::Str("retryCount"),
+            Token::U8(3),
+            Token::StructEnd,
+        ],
+    );
+    assert_de_tokens(
+        &Renamed::new(115, u2::new(1), false, u4::new(0)),
+        &[
+            Token::Struct { name: "Config", len: 2 },
+            Token::Str("baudRate"),
+            Token::U8(115),
+            Token::Str("op"),
+            Token::U8(1),
+            Token::StructEnd,
+        ],
+    );
+    assert_de_tokens(
+        &Renamed::new(115, u2::new(1), false, u4::new(0)),
+        &[Token::Seq { len: Some(2) }, Token::U8(115), Token::U8(1), Token::SeqEnd],
+    );
+    assert_de_tokens_error::<Renamed>(
+        &[
+            Token::Struct { name: "Config", len: 1 },
+            Token::Str("retryCount"),
+            Token::U8(3),
+            Token::StructEnd,
+        ],
+        "missing field `baudRate`",
+    );
+    assert_de_tokens_error::<Renamed>(
+        &[Token::Struct { name: "Config", len: 1 }, Token::Str("dirty")],
+        "unknown field `dirty`, expected one of `baudRate`, `op`, `retryCount`",
+    );
+}
+
+fn default_timeout() -> u6 {
+    u6::new(30)
+}
+
+#[bitsize(16)]
+#[derive(FromBits, PartialEq, SerializeBits, DeserializeBits, DebugBits, DefaultBits)]
+#[serde(default, rename_all = "SCREAMING-KEBAB-CASE")]
+struct WithDefaults {
+    #[default = u6::new(10)]
+    poll_interval: u6,
+    #[serde(default = "default_timeout")]
+    timeout: u6,
+    #[default = true]
+    auto_start: bool,
+    #[serde(skip)]
+    #[default = Mode::Sleep]
+    mode: Mode,
+    padding: u1,
+}
+
+#[test]
+fn serde_container_default() {
+    // missing fields are taken from `Default`, unless they have their own default
+    assert_de_tokens(
+        &WithDefaults::new(u6::new(10), u6::new(30), false, Mode::Sleep),
+        &[
+            Token::Struct {
+                name: "WithDefaults",
+                len: 1,
+            },
+            Token::Str("AUTO-START"),
+            Token::Bool(false),
+            Token::StructEnd,
+        ],
+    );
+    assert_de_tokens(
+        &WithDefaults::new(u6::new(1), u6::new(30), true, Mode::Sleep),
+        &[Token::Seq { len: Some(1) }, Token::U8(1), Token::SeqEnd],
+    );
+}
+
+#[bitsize(13)]
+#[derive(FromBits, PartialEq, SerializeBits, DeserializeBits, DebugBits)]
+struct SkippedTupleStruct(u8, #[serde(skip)] u5);
+
+#[test]
+fn serde_tuple_struct_skip() {
+    assert_ser_tokens(
+        &SkippedTupleStruct::new(3, u5::new(7)),
+        &[
+            Token::TupleStruct {
+                name: "SkippedTupleStruct",
+                len: 1,
+            },
+            Token::U8(3),
+            Token::TupleStructEnd,
+        ],
+    );
+    assert_de_tokens(
+        &SkippedTupleStruct::new(3, u5::new(0)),
+        &[
+            Token::TupleStruct {
+                name: "SkippedTupleStruct",
+                len: 1,
+            },
+            Token::U8(3),
+            Token::TupleStructEnd,
+        ],
+    );
+}
@@ -45,7 +45,7 @@ fn conversions() {
                 }
                 assert_eq!(u2::from(a), value);
             }
-            Err(e) => assert_eq!(format!("{e:?}"), "BitsError"),
+            Err(e) => assert_eq!(format!("{e}"), "invalid value 0x3 in `inner` at bit 0"),
         }
     }
 }
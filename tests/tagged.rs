@@ -0,0 +1,173 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs))]
+#![allow(clippy::unusual_byte_groupings)]
+use bilge::prelude::*;
+
+#[bitsize(28)]
+#[derive(FromBits, DebugBits, Clone, Copy, PartialEq)]
+struct LoadFields {
+    register: u4,
+    address: u24,
+}
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq)]
+enum Width {
+    Byte,
+    Half,
+    Word,
+}
+
+#[bitsize(28)]
+#[derive(TryFromBits, DebugBits, Clone, Copy, PartialEq)]
+struct StoreFields {
+    width: Width,
+    register: u4,
+    address: u22,
+}
+
+#[bitsize(32, tag_bits = 4)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq)]
+enum Instruction {
+    #[tag = 0b0000]
+    Nop,
+    #[tag = 0b0011]
+    Load(LoadFields),
+    #[tag = 0b0100]
+    Store(StoreFields),
+}
+
+#[test]
+fn tag_selects_payload() {
+    let load = Instruction::try_from(0xabcdef5_3);
+    assert_eq!(load, Ok(Instruction::Load(LoadFields::new(u4::new(5), u24::new(0xabcdef)))));
+    assert_eq!(u32::from(load.unwrap()), 0xabcdef5_3);
+
+    let store = Instruction::Store(StoreFields::new(Width::Word, u4::new(7), u22::new(0x1234)));
+    let value = u32::from(store);
+    assert_eq!(value, 0b0000000001001000110100_0111_10_0100);
+    assert_eq!(Instruction::try_from(value), Ok(store));
+
+    assert_eq!(Instruction::try_from(0x0), Ok(Instruction::Nop));
+    assert_eq!(u32::from(Instruction::Nop), 0);
+}
+
+#[test]
+fn invalid_values() {
+    // no variant has this tag
+    assert!(Instruction::try_from(0x1).is_err());
+    // `Width` has no variant for `0b11`
+    assert!(Instruction::try_from(0b11_0100).is_err());
+    assert!(Instruction::from_le_bytes([0x34, 0, 0, 0]).is_err());
+    // unit variants need their payload bits to be zero
+    let error = Instruction::try_from(0xfff_0).unwrap_err();
+    assert_eq!(error.path(), &[bilge::PathSegment::Variant("Nop")]);
+    assert_eq!((error.offset(), error.value()), (4, 0xfff));
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Lenient {
+    #[tag = 0]
+    Idle,
+    #[tag = 1]
+    Busy,
+    #[tag = 2]
+    Done(u6),
+    #[tag = 3]
+    Failed(u6),
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(TryFromBits, Debug, PartialEq)]
+enum State {
+    #[tag = 0]
+    Idle,
+    #[tag = 1]
+    Run(u6),
+}
+
+#[bitsize(8)]
+#[derive(TryFromBits, DebugBits, PartialEq)]
+struct Strict {
+    state: State,
+}
+
+#[test]
+fn unit_payload_bits() {
+    // `FromBits` ignores the payload bits of unit variants
+    assert_eq!(Lenient::from(0b111111_01), Lenient::Busy);
+    assert_eq!(u8::from(Lenient::Busy), 0b01);
+    assert!(<Lenient as Bitsized>::VALIDITY.accepts(0b000000_01));
+    assert!(!<Lenient as Bitsized>::VALIDITY.accepts(0b111111_01));
+
+    // `TryFromBits` and its validity reject them, also for nested fields
+    assert!(Strict::try_from(0b000001_00).is_err());
+    assert_eq!(Strict::try_from(0b000001_01).map(|strict| strict.state()), Ok(State::Run(u6::new(1))));
+    for value in 0..=u8::MAX {
+        assert_eq!(<Strict as Bitsized>::VALIDITY.accepts(value as u128), Strict::try_from(value).is_ok());
+    }
+}
+
+#[bitsize(8, tag_bits = 2, msb_first)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Packet {
+    #[tag = 0]
+    Ping,
+    #[tag = 1]
+    Data(u6),
+    #[tag = 2]
+    Flag(Flags),
+    #[fallback]
+    Unknown(u8),
+}
+
+#[bitsize(6)]
+#[derive(FromBits, DebugBits, PartialEq)]
+struct Flags {
+    ack: bool,
+    retry: bool,
+    reserved: u4,
+}
+
+#[test]
+fn msb_first_tag_with_fallback() {
+    assert_eq!(Packet::from(0b01_101010), Packet::Data(u6::new(0b101010)));
+    assert_eq!(Packet::from(0b10_000011), Packet::Flag(Flags::new(true, true)));
+    assert_eq!(Packet::from(0b11_000001), Packet::Unknown(0b11_000001));
+    assert_eq!(Packet::from(0b00_111111), Packet::Ping);
+
+    assert_eq!(u8::from(Packet::Data(u6::new(5))), 0b01_000101);
+    assert_eq!(u8::from(Packet::Flag(Flags::new(false, true))), 0b10_000010);
+    assert_eq!(u8::from(Packet::Unknown(0b11_000001)), 0b11_000001);
+}
+
+#[bitsize(3, tag_bits = 1)]
+#[derive(FromBits, Debug, PartialEq)]
+enum Either {
+    #[tag = 0]
+    Left(u2),
+    #[tag = 1]
+    Right(u2),
+}
+
+#[test]
+fn every_tag_used() {
+    assert_eq!(Either::from(u3::new(0b11_0)), Either::Left(u2::new(3)));
+    assert_eq!(Either::from(u3::new(0b10_1)), Either::Right(u2::new(2)));
+    assert_eq!(u3::from(Either::Right(u2::new(1))), u3::new(0b01_1));
+}
+
+#[bitsize(32)]
+#[derive(TryFromBits, DebugBits, PartialEq)]
+struct Program {
+    first: Instruction,
+}
+
+#[test]
+fn nested() {
+    assert_eq!(
+        Program::try_from(0xabcdef5_3).map(|program| program.first()),
+        Instruction::try_from(0xabcdef5_3)
+    );
+    assert!(Program::try_from(0x2).is_err());
+}
@@ -0,0 +1,36 @@
+use bilge::prelude::*;
+
+#[bitsize(8)]
+#[derive(FromBits)]
+struct Status {
+    #[access(ro)]
+    ready: bool,
+    #[access(wo)]
+    command: u3,
+    #[access(w1c)]
+    error: bool,
+    #[access(rc)]
+    count: u3,
+}
+
+// only one policy per field
+#[bitsize(8)]
+struct Twice {
+    #[access(ro)]
+    #[access(wo)]
+    value: u8,
+}
+
+#[bitsize(8)]
+struct Unknown {
+    #[access(read)]
+    value: u8,
+}
+
+fn main() {
+    let mut status = Status::from(0);
+    status.set_ready(true);
+    status.command();
+    status.set_error(false);
+    status.set_count(u3::new(1));
+}
@@ -0,0 +1,36 @@
+use bilge::prelude::*;
+
+// the viewed field doesn't exist
+#[bitsize(8)]
+struct Missing {
+    raw: u8,
+    #[alias(of = "row")]
+    split: (u4, u4),
+}
+
+// the alias is bigger than the rest of the struct
+#[bitsize(8)]
+struct TooBig {
+    low: u4,
+    high: u4,
+    #[alias(of = "high")]
+    both: u8,
+}
+
+#[bitsize(2)]
+#[derive(TryFromBits)]
+enum Unfilled {
+    A,
+    B,
+    C,
+}
+
+// aliases are not validated, so they need to be filled
+#[bitsize(8)]
+struct NotFilled {
+    raw: u8,
+    #[alias(of = "raw")]
+    view: (Unfilled, u6),
+}
+
+fn main() {}
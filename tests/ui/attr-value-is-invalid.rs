@@ -9,15 +9,19 @@ enum Test {}
 enum Test {}
 
 // one above highest (struct) value
+#[bitsize(4097)]
+struct Test {}
+
+// wide (byte array backed) structs need a multiple of 8
 #[bitsize(129)]
 struct Test {}
 
-// one above highest enum value
-#[bitsize(65)]
+// one above highest enum value, enums can't be wide
+#[bitsize(136)]
 enum Test {}
 
 // one above highest enum value, this compiles
-#[bitsize(65)]
-struct Test { field: u65 }
+#[bitsize(136)]
+struct Test { field: [u8; 17] }
 
 fn main() {}
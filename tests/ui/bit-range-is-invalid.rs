@@ -0,0 +1,43 @@
+use bilge::prelude::*;
+
+// overlapping fields
+#[bitsize(8)]
+struct Overlap {
+    #[bits(0..=3)]
+    a: u4,
+    #[bits(3..=6)]
+    b: u4,
+}
+
+// fields out of order
+#[bitsize(8)]
+struct OutOfOrder {
+    #[bits(4..=7)]
+    high: u4,
+    #[bits(0..=3)]
+    low: u4,
+}
+
+// outside of the declared bitsize
+#[bitsize(8)]
+struct Outside {
+    #[bits(4..=8)]
+    a: u5,
+}
+
+// every field needs a range, or none
+#[bitsize(8)]
+struct Missing {
+    #[bit(0)]
+    a: bool,
+    b: u7,
+}
+
+// the range doesn't match the field type
+#[bitsize(8)]
+struct Mismatch {
+    #[bits(0..4)]
+    a: u3,
+}
+
+fn main() {}
@@ -0,0 +1,15 @@
+use bilge::prelude::*;
+
+#[bitsize(8)]
+#[derive(FromBits)]
+struct Register {
+    header: u4,
+    #[access(ro)]
+    footer: u4,
+}
+
+fn main() {
+    let _ = Register::builder().header(u4::new(1)).build();
+    let _ = Register::builder().build();
+    let _ = Register::from(0).with_footer(u4::new(1));
+}
@@ -0,0 +1,102 @@
+use bilge::prelude::*;
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, Clone, Copy, PartialEq, Default)]
+enum Parity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+// `Parity` has no variant for 3
+#[bitsize(8)]
+#[derive(TryFromBits, DefaultBits)]
+#[reset = 0b000000_11]
+struct InvalidReset {
+    parity: Parity,
+    baud: u6,
+}
+
+#[bitsize(4)]
+#[derive(FromBits, DefaultBits)]
+#[reset = 0x1F]
+struct ResetTooBig {
+    value: u4,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DefaultBits)]
+#[reset = 0b10_00_0000]
+struct ResetMissesRequired {
+    value: u4,
+    #[reserved(ones)]
+    reserved: u2,
+    high: u2,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DefaultBits)]
+#[reset = 0x12]
+struct ResetAndDefault {
+    #[default = u4::new(3)]
+    low: u4,
+    high: u4,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DefaultBits)]
+struct DefaultOnReserved {
+    value: u4,
+    #[default = u4::new(3)]
+    reserved: u4,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, DefaultBits)]
+struct DefaultIsMissing {
+    #[default]
+    value: u8,
+}
+
+#[bitsize(136)]
+#[derive(FromBits, DefaultBits)]
+#[reset = 0]
+struct WideReset {
+    low: u128,
+    high: u8,
+}
+
+/// `TryFrom` is implemented by hand, so `#[reset]` can't know which values it accepts.
+#[derive(Clone, Copy)]
+struct Even(u8);
+
+impl Bitsized for Even {
+    type ArbitraryInt = u4;
+    const BITS: usize = 4;
+    const MAX: u4 = u4::new(15);
+}
+
+impl TryFrom<u4> for Even {
+    type Error = ();
+
+    fn try_from(value: u4) -> Result<Self, ()> {
+        if value.value() % 2 == 0 { Ok(Even(value.value())) } else { Err(()) }
+    }
+}
+
+impl From<Even> for u4 {
+    fn from(even: Even) -> u4 {
+        u4::new(even.0)
+    }
+}
+
+#[bitsize(8)]
+#[derive(TryFromBits, DefaultBits)]
+#[reset = 0b0001_0000]
+struct ResetOfHandWritten {
+    low: u4,
+    even: Even,
+}
+
+fn main() {}
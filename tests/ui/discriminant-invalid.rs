@@ -10,6 +10,13 @@ enum B {
     B,
 }
 
+#[bitsize(2)]
+#[derive(TryFromBits)]
+enum Duplicate {
+    A = EXTERNAL,
+    B = 1 + 0,
+}
+
 #[bitsize(1)]
 #[derive(FromBits)]
 enum C {
@@ -35,4 +42,27 @@ enum E {
     PlusPlus = 2,
 }
 
+// counting up from the largest value doesn't fit anymore
+#[bitsize(2)]
+#[derive(FromBits)]
+enum F {
+    Three = 3,
+    Four,
+}
+
+#[bitsize(2)]
+#[derive(TryFromBits)]
+enum G {
+    Three = EXTERNAL as u8 + 2,
+    Four,
+}
+
+// rust only sees different values, but both are `0b1111` in 4 bits
+#[bitsize(4)]
+#[derive(TryFromBits)]
+enum Offset {
+    A = -1,
+    B = 15,
+}
+
 fn main() {}
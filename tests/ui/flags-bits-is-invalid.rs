@@ -0,0 +1,53 @@
+use bilge::prelude::*;
+
+#[bitsize(8)]
+#[derive(FromBits, FlagsBits)]
+struct NotAFlag {
+    read: bool,
+    mode: u7,
+}
+
+const PINS: usize = 4;
+
+#[bitsize(8)]
+#[derive(FromBits, FlagsBits)]
+struct LengthIsNotALiteral {
+    pins: [bool; PINS],
+    reserved: u4,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, FlagsBits)]
+struct Unnamed(bool, [bool; 7]);
+
+#[bitsize(1)]
+#[derive(FromBits, FlagsBits)]
+enum Enum {
+    Off,
+    On,
+}
+
+#[bitsize(136)]
+#[derive(FromBits, FlagsBits)]
+struct Wide {
+    flags: [bool; 136],
+}
+
+// `FLAGS` lists every flag, and `DEFAULT` is used by `DefaultBits`
+#[bitsize(8)]
+#[derive(FromBits, FlagsBits)]
+struct ReservedNames {
+    flags: bool,
+    default: bool,
+    reserved: u6,
+}
+
+#[bitsize(8)]
+#[derive(FromBits, FlagsBits)]
+struct FieldConstName {
+    ready: bool,
+    ready_mask: bool,
+    reserved: u6,
+}
+
+fn main() {}
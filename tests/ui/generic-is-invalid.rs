@@ -0,0 +1,32 @@
+use bilge::prelude::*;
+
+#[bitsize(8)]
+struct Borrowed<'a, T> {
+    flag: bool,
+    payload: &'a T,
+}
+
+#[bitsize(2)]
+enum Generic<T> {
+    A(T),
+    B,
+    C,
+    D,
+}
+
+#[bitsize(8)]
+struct Ranges<T> {
+    #[bits(0..4)]
+    low: T,
+    #[bits(4..8)]
+    high: u4,
+}
+
+#[bitsize(8)]
+struct Aliased<T> {
+    raw: T,
+    #[alias(of = "raw")]
+    split: (u4, u4),
+}
+
+fn main() {}
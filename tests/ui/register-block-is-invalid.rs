@@ -0,0 +1,35 @@
+use bilge::mmio::Reg;
+use bilge::prelude::*;
+use bilge::register_block;
+
+#[bitsize(32)]
+#[derive(FromBits)]
+struct Control {
+    value: u32,
+}
+
+#[register_block]
+struct MissingOffset {
+    #[offset(0x0)]
+    first: Reg<Control>,
+    second: Reg<Control>,
+}
+
+#[register_block]
+struct SameOffset {
+    #[offset(0x4)]
+    first: Reg<Control>,
+    #[offset(0x4)]
+    second: Reg<Control>,
+}
+
+#[register_block]
+struct InvalidOffset {
+    #[offset = 4]
+    first: Reg<Control>,
+}
+
+#[register_block]
+struct Tuple(Reg<Control>);
+
+fn main() {}
@@ -0,0 +1,33 @@
+use bilge::mmio::{Reg, RoReg};
+use bilge::prelude::*;
+use bilge::register_block;
+
+#[bitsize(32)]
+#[derive(FromBits)]
+struct Control {
+    value: u32,
+}
+
+#[bitsize(16)]
+#[derive(FromBits)]
+struct Half {
+    value: u16,
+}
+
+#[register_block]
+struct Overlapping {
+    #[offset(0x0)]
+    control: Reg<Control>,
+    #[offset(0x2)]
+    half: RoReg<Half>,
+}
+
+#[register_block]
+struct Misaligned {
+    #[offset(0x0)]
+    half: RoReg<Half>,
+    #[offset(0x6)]
+    control: Reg<Control>,
+}
+
+fn main() {}
@@ -0,0 +1,43 @@
+use bilge::prelude::*;
+
+// only reserved fields can have a required value
+#[bitsize(8)]
+struct NotReserved {
+    #[reserved(zero)]
+    value: u8,
+}
+
+#[bitsize(8)]
+#[derive(FromBits)]
+struct Inner {
+    value: u8,
+}
+
+// the required value is compared as an integer
+#[bitsize(8)]
+struct NotAnInteger {
+    #[reserved(ones)]
+    reserved: Inner,
+}
+
+#[bitsize(8)]
+struct InvalidRule {
+    #[reserved(full)]
+    reserved: u8,
+}
+
+#[bitsize(8)]
+struct Duplicate {
+    #[reserved(zero)]
+    #[reserved(ones)]
+    reserved: u8,
+}
+
+#[bitsize(8)]
+struct TooBig {
+    #[reserved(value = 0b100)]
+    reserved: u2,
+    value: u6,
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+use bilge::prelude::*;
+
+#[bitsize(4, sealed)]
+#[derive(TryFromBits)]
+struct Sealed(Unfilled);
+
+#[bitsize(4)]
+#[derive(TryFromBits)]
+enum Unfilled {
+    A,
+    B,
+    C,
+}
+
+#[bitsize(2, sealed)]
+#[derive(FromBits)]
+enum NotAStruct {
+    A,
+    B,
+    C,
+    D,
+}
+
+#[bitsize(8, sealed, sealed)]
+struct Twice(u8);
+
+#[bitsize(8, closed)]
+struct Unknown(u8);
+
+fn main() {
+    let mut a = Sealed::new(Unfilled::A);
+    a.value = u4::new(3);
+}
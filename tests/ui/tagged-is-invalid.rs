@@ -0,0 +1,70 @@
+use bilge::prelude::*;
+
+#[bitsize(8)]
+#[derive(TryFromBits)]
+enum MissingTagBits {
+    #[tag = 0]
+    A(u7),
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(TryFromBits)]
+enum MissingTag {
+    #[tag = 0]
+    A(u6),
+    B(u6),
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(TryFromBits)]
+enum DuplicateTag {
+    #[tag = 1]
+    A(u6),
+    #[tag = 1]
+    B(u6),
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(TryFromBits)]
+enum TagTooBig {
+    #[tag = 4]
+    A(u6),
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(FromBits)]
+enum NotAllTags {
+    #[tag = 0]
+    A(u6),
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(FromBits)]
+enum UnitFallback {
+    #[tag = 0]
+    A(u6),
+    #[fallback]
+    B,
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(TryFromBits)]
+enum PayloadTooSmall {
+    #[tag = 0]
+    A(u5),
+}
+
+#[bitsize(8, tag_bits = 8)]
+#[derive(TryFromBits)]
+enum NoPayloadBits {
+    #[tag = 0]
+    A,
+}
+
+#[bitsize(8, tag_bits = 2)]
+#[derive(FromBits)]
+struct TaggedStruct {
+    field: u8,
+}
+
+fn main() {}
@@ -0,0 +1,98 @@
+#![cfg_attr(feature = "nightly", feature(const_convert, const_trait_impl, const_mut_refs, const_maybe_uninit_write))]
+use bilge::prelude::*;
+
+#[bitsize(256)]
+#[derive(Clone, Copy, FromBits, DebugBits, PartialEq, DefaultBits)]
+struct Descriptor {
+    kind: u4,
+    // crosses the first 64-bit boundary
+    address: u62,
+    reserved: u2,
+    flags: (bool, bool),
+    header: Header,
+    lanes: [u32; 4],
+    tail: u42,
+}
+
+#[bitsize(16)]
+#[derive(FromBits, DebugBits, PartialEq, Clone, Copy, Default)]
+struct Header {
+    version: u4,
+    length: u12,
+}
+
+#[test]
+fn wide_struct() {
+    let lanes = [0xdead_beef, 0x1234_5678, 0, u32::MAX];
+    let header = Header::new(u4::new(3), u12::new(0xabc));
+    let address = u62::new(0x2aaa_bbbb_cccc_dddd);
+    let mut descriptor = Descriptor::new(u4::new(0b1010), address, (true, false), header, lanes, u42::new(0x3ff_ffff_ffff));
+
+    assert_eq!(descriptor.kind(), u4::new(0b1010));
+    assert_eq!(descriptor.address(), address);
+    assert_eq!(descriptor.reserved_i(), u2::new(0));
+    assert_eq!(descriptor.flags(), (true, false));
+    assert_eq!(descriptor.header(), header);
+    assert_eq!(descriptor.lanes(), lanes);
+    assert_eq!(descriptor.lanes_at(3), u32::MAX);
+    assert_eq!(descriptor.tail(), u42::new(0x3ff_ffff_ffff));
+
+    // setters only touch their own bits
+    descriptor.set_address(u62::new(1));
+    descriptor.set_lanes_at(1, 7);
+    descriptor.set_flags((false, true));
+    assert_eq!(descriptor.kind(), u4::new(0b1010));
+    assert_eq!(descriptor.address(), u62::new(1));
+    assert_eq!(descriptor.flags(), (false, true));
+    assert_eq!(descriptor.header(), header);
+    assert_eq!(descriptor.lanes(), [0xdead_beef, 7, 0, u32::MAX]);
+    assert_eq!(descriptor.tail(), u42::new(0x3ff_ffff_ffff));
+
+    // the backing storage is a little-endian byte array
+    let bytes: [u8; 32] = descriptor.into();
+    assert_eq!(bytes[0], 0b0001_1010);
+    assert_eq!(bytes[31], 0xff);
+    assert_eq!(Descriptor::from(bytes), descriptor);
+    assert_eq!(<Descriptor as Bitsized>::BITS, 256);
+    assert_eq!(<Descriptor as Bitsized>::MAX, [u8::MAX; 32]);
+
+    assert_eq!(Descriptor::default(), Descriptor::from([0; 32]));
+}
+
+#[bitsize(2)]
+#[derive(TryFromBits, Debug, PartialEq)]
+enum Speed {
+    Slow,
+    Fast,
+    Fastest,
+}
+
+#[bitsize(512)]
+#[derive(TryFromBits, DebugBits, BinaryBits, PartialEq)]
+struct ConfigHeader {
+    ids: [u16; 30],
+    speed: Speed,
+    reserved: u30,
+}
+
+#[test]
+fn wide_struct_try_from() {
+    let mut bytes = [0; 64];
+    bytes[0] = 0x34;
+    bytes[1] = 0x12;
+    bytes[60] = 0b10;
+    let header = ConfigHeader::try_from(bytes).unwrap();
+    assert_eq!(header.ids_at(0), 0x1234);
+    assert_eq!(header.speed(), Speed::Fastest);
+    assert_eq!(<[u8; 64]>::from(header), bytes);
+
+    bytes[60] = 0b11;
+    assert!(ConfigHeader::try_from(bytes).is_err());
+
+    let header = ConfigHeader::new([1; 30], Speed::Fast);
+    let binary = format!("{header:b}");
+    // arrays are printed as a whole, like in the smaller bitfields
+    assert!(binary.starts_with("000000000000000000000000000000_01_00000000000000010000000000000001"));
+    assert!(binary.ends_with("0000000000000001"));
+    assert_eq!(binary.len(), 512 + 2);
+}